@@ -0,0 +1,66 @@
+// Compares pushing and popping one item at a time through the SPSC ring
+// buffer with batches.
+//
+// This is an example rather than an ignored test, so the library is built
+// without `cfg(test)` and the buffer uses the `std` atomics, not the model
+// checker's instrumented ones. Run with
+// `cargo run --release --example spsc_throughput`.
+
+use std::thread;
+use std::time::Instant;
+
+use algorithms::data_structures::spsc::channel;
+
+const ITEMS: usize = 50_000_000;
+
+fn main() {
+    for batch in [1, 16, 256] {
+        let (mut producer, mut consumer) = channel::<u64>(4096);
+        let start = Instant::now();
+        let sum = thread::scope(|s| {
+            s.spawn(move || {
+                let items: Vec<u64> = (0..batch as u64).collect();
+                let mut pushed = 0;
+                while pushed < ITEMS {
+                    let n = batch.min(ITEMS - pushed);
+                    let n = if batch == 1 {
+                        producer.push(1).map_or(0, |()| 1)
+                    } else {
+                        producer.push_slice(&items[..n])
+                    };
+                    if n == 0 {
+                        thread::yield_now();
+                    }
+                    pushed += n;
+                }
+            });
+
+            let mut buffer = vec![0; batch];
+            let (mut popped, mut sum) = (0, 0);
+            while popped < ITEMS {
+                let n = if batch == 1 {
+                    consumer.pop().map_or(0, |item| {
+                        sum += item;
+                        1
+                    })
+                } else {
+                    let n = consumer.pop_slice(&mut buffer);
+                    sum += buffer[..n].iter().sum::<u64>();
+                    n
+                };
+                if n == 0 {
+                    thread::yield_now();
+                }
+                popped += n;
+            }
+            sum
+        });
+        let elapsed = start.elapsed();
+        println!(
+            "batch {:>3}: {:>7.1} M items/s (checksum {})",
+            batch,
+            ITEMS as f64 / elapsed.as_secs_f64() / 1e6,
+            sum
+        );
+    }
+}
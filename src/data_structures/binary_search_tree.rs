@@ -40,7 +40,7 @@ impl<T: std::cmp::PartialOrd + Clone> BinarySearchTree<T> {
             // non-empty BST case
             Some(node) => {
                 // define the current node the root node
-                let mut current_node = node;
+                let current_node = node;
 
                 // iterate over all nodes that fits the rules
                 loop {
@@ -76,6 +76,12 @@ impl<T: std::cmp::PartialOrd + Clone> BinarySearchTree<T> {
     }
 }
 
+impl<T: std::cmp::PartialOrd + Clone> Default for BinarySearchTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
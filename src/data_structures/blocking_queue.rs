@@ -0,0 +1,483 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::queue::Queue;
+
+// How a blocking queue picks the next thread to go on among those waiting.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fairness {
+    // Whichever thread gets the lock first, including one that just arrived
+    // and never waited. It's the faster choice, but a waiting thread may be
+    // overtaken again and again.
+    #[default]
+    Unfair,
+    // The threads go on in the order they arrived, puts and takes each in
+    // their own line.
+    Fair,
+}
+
+// The error returned by `put` on a closed queue, holding the item.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Closed<T>(pub T);
+
+impl<T> Closed<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Closed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Closed").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for Closed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the queue is closed")
+    }
+}
+
+impl<T> std::error::Error for Closed<T> {}
+
+// The error returned by `offer`, holding the item.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OfferError<T> {
+    // The queue was still full when the time was up.
+    Timeout(T),
+    // The queue is closed.
+    Closed(T),
+}
+
+impl<T> OfferError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            OfferError::Timeout(item) | OfferError::Closed(item) => item,
+        }
+    }
+}
+
+impl<T> fmt::Debug for OfferError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfferError::Timeout(_) => f.debug_tuple("Timeout").finish_non_exhaustive(),
+            OfferError::Closed(_) => f.debug_tuple("Closed").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl<T> fmt::Display for OfferError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfferError::Timeout(_) => write!(f, "the queue stayed full"),
+            OfferError::Closed(_) => write!(f, "the queue is closed"),
+        }
+    }
+}
+
+impl<T> std::error::Error for OfferError<T> {}
+
+// The error returned by `poll`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PollError {
+    // The queue was still empty when the time was up.
+    Timeout,
+    // The queue is closed, and has no items left.
+    Closed,
+}
+
+impl fmt::Display for PollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PollError::Timeout => write!(f, "the queue stayed empty"),
+            PollError::Closed => write!(f, "the queue is closed and empty"),
+        }
+    }
+}
+
+impl std::error::Error for PollError {}
+
+// A line of waiting threads, as in a bakery: each thread takes a ticket, and
+// goes on when its number is served. The tickets of the threads that gave up
+// are skipped.
+#[derive(Debug, Default)]
+struct Line {
+    next: u64,
+    serving: u64,
+    abandoned: BTreeSet<u64>,
+}
+
+impl Line {
+    fn ticket(&mut self) -> u64 {
+        self.next += 1;
+        return self.next - 1;
+    }
+
+    fn is_turn(&self, ticket: u64) -> bool {
+        return self.serving == ticket;
+    }
+
+    fn advance(&mut self) {
+        self.serving += 1;
+        while self.abandoned.remove(&self.serving) {
+            self.serving += 1;
+        }
+    }
+
+    fn abandon(&mut self, ticket: u64) {
+        if self.is_turn(ticket) {
+            self.advance();
+        } else {
+            self.abandoned.insert(ticket);
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        return (self.next - self.serving) as usize - self.abandoned.len();
+    }
+}
+
+struct State<T> {
+    items: Queue<T>,
+    closed: bool,
+    // Only used by fair queues.
+    puts: Line,
+    takes: Line,
+}
+
+// A bounded FIFO queue for any number of producer and consumer threads, which
+// wait while it's full or empty.
+//
+// Once the queue is closed, puts fail, and takes get the items left, then
+// fail too. Every waiting thread wakes up when it's closed.
+pub struct BlockingQueue<T> {
+    state: Mutex<State<T>>,
+    not_full: Condvar,
+    not_empty: Condvar,
+    capacity: usize,
+    fairness: Fairness,
+}
+
+impl<T> BlockingQueue<T> {
+    // Creates an unfair queue of at most `capacity` items.
+    //
+    // Panics if the capacity is 0.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_fairness(capacity, Fairness::Unfair)
+    }
+
+    pub fn with_fairness(capacity: usize, fairness: Fairness) -> Self {
+        assert!(capacity > 0, "a blocking queue must hold at least one item");
+        Self {
+            state: Mutex::new(State {
+                items: Queue::with_capacity(capacity),
+                closed: false,
+                puts: Line::default(),
+                takes: Line::default(),
+            }),
+            not_full: Condvar::new(),
+            not_empty: Condvar::new(),
+            capacity,
+            fairness,
+        }
+    }
+
+    // Enqueues an item, waiting for room if the queue is full.
+    pub fn put(&self, item: T) -> Result<(), Closed<T>> {
+        match self.put_until(item, None) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(Closed(error.into_inner())),
+        }
+    }
+
+    // Enqueues an item, waiting at most `timeout` for room if the queue is
+    // full. A zero timeout doesn't wait at all.
+    pub fn offer(&self, item: T, timeout: Duration) -> Result<(), OfferError<T>> {
+        self.put_until(item, Instant::now().checked_add(timeout))
+    }
+
+    // Dequeues an item, waiting for one if the queue is empty. Returns `None`
+    // once the queue is closed and empty.
+    pub fn take(&self) -> Option<T> {
+        return self.take_until(None).ok();
+    }
+
+    // Dequeues an item, waiting at most `timeout` for one if the queue is
+    // empty. A zero timeout doesn't wait at all.
+    pub fn poll(&self, timeout: Duration) -> Result<T, PollError> {
+        self.take_until(Instant::now().checked_add(timeout))
+    }
+
+    // Closes the queue, and wakes up every waiting thread.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_full.notify_all();
+        self.not_empty.notify_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        return self.lock().closed;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.lock().items.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.lock().items.is_empty();
+    }
+
+    pub fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+
+    pub fn fairness(&self) -> Fairness {
+        return self.fairness;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap()
+    }
+
+    // Waits on `condvar` until `deadline`, or for good if there's none.
+    // Returns whether the time was already up, without waiting then.
+    fn wait<'a>(
+        condvar: &Condvar,
+        state: MutexGuard<'a, State<T>>,
+        deadline: Option<Instant>,
+    ) -> (MutexGuard<'a, State<T>>, bool) {
+        match deadline {
+            None => (condvar.wait(state).unwrap(), false),
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return (state, true);
+                }
+                (condvar.wait_timeout(state, deadline - now).unwrap().0, false)
+            }
+        }
+    }
+
+    fn put_until(&self, item: T, deadline: Option<Instant>) -> Result<(), OfferError<T>> {
+        let fair = self.fairness == Fairness::Fair;
+        let mut state = self.lock();
+        let ticket = if fair { state.puts.ticket() } else { 0 };
+
+        loop {
+            if state.closed {
+                if fair {
+                    state.puts.abandon(ticket);
+                }
+                return Err(OfferError::Closed(item));
+            }
+            if state.items.len() < self.capacity && (!fair || state.puts.is_turn(ticket)) {
+                break;
+            }
+            let timed_out;
+            (state, timed_out) = Self::wait(&self.not_full, state, deadline);
+            if timed_out {
+                if fair {
+                    state.puts.abandon(ticket);
+                    // The next in line may be able to go on now.
+                    self.not_full.notify_all();
+                }
+                return Err(OfferError::Timeout(item));
+            }
+        }
+
+        state.items.enqueue(item);
+        if fair {
+            // Only the next in line can go on, so every waiting thread has to
+            // check whether it's its turn.
+            state.puts.advance();
+            self.not_full.notify_all();
+            self.not_empty.notify_all();
+        } else {
+            self.not_empty.notify_one();
+        }
+        return Ok(());
+    }
+
+    fn take_until(&self, deadline: Option<Instant>) -> Result<T, PollError> {
+        let fair = self.fairness == Fairness::Fair;
+        let mut state = self.lock();
+        let ticket = if fair { state.takes.ticket() } else { 0 };
+
+        loop {
+            if !state.items.is_empty() && (!fair || state.takes.is_turn(ticket)) {
+                break;
+            }
+            if state.closed && state.items.is_empty() {
+                if fair {
+                    state.takes.abandon(ticket);
+                }
+                return Err(PollError::Closed);
+            }
+            let timed_out;
+            (state, timed_out) = Self::wait(&self.not_empty, state, deadline);
+            if timed_out {
+                if fair {
+                    state.takes.abandon(ticket);
+                    self.not_empty.notify_all();
+                }
+                return Err(PollError::Timeout);
+            }
+        }
+
+        let item = state.items.dequeue().unwrap();
+        if fair {
+            state.takes.advance();
+            self.not_empty.notify_all();
+            self.not_full.notify_all();
+        } else {
+            self.not_full.notify_one();
+        }
+        return Ok(item);
+    }
+
+    // How many threads wait in line to put items, in a fair queue.
+    #[cfg(test)]
+    fn waiting_puts(&self) -> usize {
+        return self.lock().puts.len();
+    }
+}
+
+impl<T> fmt::Debug for BlockingQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("BlockingQueue")
+            .field("len", &state.items.len())
+            .field("capacity", &self.capacity)
+            .field("closed", &state.closed)
+            .field("fairness", &self.fairness)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_put_take() {
+        let queue = BlockingQueue::new(2);
+        assert_eq!(queue.put(1), Ok(()));
+        assert_eq!(queue.offer(2, Duration::ZERO), Ok(()));
+        assert_eq!(queue.offer(3, Duration::ZERO), Err(OfferError::Timeout(3)));
+        assert_eq!(queue.len(), 2);
+
+        assert_eq!(queue.take(), Some(1));
+        assert_eq!(queue.poll(Duration::ZERO), Ok(2));
+        assert_eq!(queue.poll(Duration::ZERO), Err(PollError::Timeout));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_timeouts_wait() {
+        let queue = BlockingQueue::with_fairness(1, Fairness::Fair);
+        let start = Instant::now();
+        assert_eq!(queue.poll(Duration::from_millis(20)), Err(PollError::Timeout));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        queue.put("a").unwrap();
+        let start = Instant::now();
+        assert_eq!(queue.offer("b", Duration::from_millis(20)), Err(OfferError::Timeout("b")));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // the threads that gave up don't hold up the line
+        assert_eq!(queue.take(), Some("a"));
+        assert_eq!(queue.offer("c", Duration::ZERO), Ok(()));
+        assert_eq!(queue.poll(Duration::ZERO), Ok("c"));
+    }
+
+    #[test]
+    fn test_close_wakes_up_waiters() {
+        for fairness in [Fairness::Unfair, Fairness::Fair] {
+            let full = BlockingQueue::with_fairness(1, fairness);
+            let empty: BlockingQueue<i32> = BlockingQueue::with_fairness(1, fairness);
+            full.put(0).unwrap();
+
+            thread::scope(|s| {
+                let (full, empty) = (&full, &empty);
+                let putters: Vec<_> = (1..4).map(|i| s.spawn(move || full.put(i))).collect();
+                let takers: Vec<_> = (0..3).map(|_| s.spawn(move || empty.take())).collect();
+                thread::sleep(Duration::from_millis(20));
+                full.close();
+                empty.close();
+
+                for putter in putters {
+                    assert!(matches!(putter.join().unwrap(), Err(Closed(1..=3))));
+                }
+                for taker in takers {
+                    assert_eq!(taker.join().unwrap(), None);
+                }
+            });
+
+            // the items already in can still be taken
+            assert!(full.is_closed());
+            assert_eq!(full.offer(5, Duration::ZERO), Err(OfferError::Closed(5)));
+            assert_eq!(full.take(), Some(0));
+            assert_eq!(full.poll(Duration::from_secs(1)), Err(PollError::Closed));
+        }
+    }
+
+    #[test]
+    fn test_fair_puts_go_in_order() {
+        let queue = BlockingQueue::with_fairness(1, Fairness::Fair);
+        queue.put(0).unwrap();
+
+        let taken = thread::scope(|s| {
+            let queue = &queue;
+            for i in 1..=5 {
+                s.spawn(move || queue.put(i).unwrap());
+                // wait until the thread is in line before starting the next
+                while queue.waiting_puts() < i {
+                    thread::yield_now();
+                }
+            }
+            (0..=5).map(|_| queue.take().unwrap()).collect::<Vec<usize>>()
+        });
+        assert_eq!(taken, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_many_producers_and_consumers() {
+        const PRODUCERS: usize = 4;
+        const ITEMS: usize = 5000;
+
+        for fairness in [Fairness::Unfair, Fairness::Fair] {
+            let queue = BlockingQueue::with_fairness(8, fairness);
+            let mut taken: Vec<(usize, usize)> = thread::scope(|s| {
+                let queue = &queue;
+                let producers: Vec<_> = (0..PRODUCERS)
+                    .map(|p| s.spawn(move || (0..ITEMS).for_each(|i| queue.put((p, i)).unwrap())))
+                    .collect();
+                let consumers: Vec<_> = (0..3)
+                    .map(|_| {
+                        s.spawn(move || {
+                            let mut taken = Vec::new();
+                            while let Some(item) = queue.take() {
+                                taken.push(item);
+                            }
+                            taken
+                        })
+                    })
+                    .collect();
+
+                for producer in producers {
+                    producer.join().unwrap();
+                }
+                queue.close();
+                consumers.into_iter().flat_map(|c| c.join().unwrap()).collect()
+            });
+
+            taken.sort_unstable();
+            let expected: Vec<(usize, usize)> = (0..PRODUCERS).flat_map(|p| (0..ITEMS).map(move |i| (p, i))).collect();
+            assert_eq!(taken, expected);
+        }
+    }
+}
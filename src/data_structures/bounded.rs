@@ -0,0 +1,693 @@
+use std::fmt;
+use std::iter::{FusedIterator, Rev};
+use std::mem::MaybeUninit;
+use std::sync::{Condvar, Mutex};
+
+// What a bounded container does with an item that comes when it's full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    // Hand the item back in a `Full` error.
+    #[default]
+    Reject,
+    // Drop the oldest item to make room: the bottom of a stack, the front of
+    // a queue.
+    OverwriteOldest,
+    // Wait until another thread takes an item out. Only a container shared
+    // through `Blocking` can wait: on its own, nothing else could make room,
+    // so adding an item to it panics.
+    Block,
+}
+
+// The error returned when an item doesn't fit, holding the item.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Full<T>(pub T);
+
+impl<T> Full<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Full").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the container is full")
+    }
+}
+
+impl<T> std::error::Error for Full<T> {}
+
+const BLOCK_WITHOUT_BLOCKING: &str =
+    "`Overflow::Block` needs the container to be shared through `Blocking`, or nothing could make room";
+
+// A container with room for a fixed number of items, and an overflow policy
+// for the rest. `Blocking` works with any of them.
+pub trait Bounded {
+    type Item;
+
+    fn policy(&self) -> Overflow;
+
+    fn is_full(&self) -> bool;
+
+    fn capacity(&self) -> usize;
+
+    // Adds an item, following the overflow policy when the container is full.
+    // Returns the item that was dropped to make room, if any.
+    fn insert(&mut self, item: Self::Item) -> Result<Option<Self::Item>, Full<Self::Item>>;
+
+    // Adds an item if there's room for it, whatever the overflow policy.
+    fn try_insert(&mut self, item: Self::Item) -> Result<(), Full<Self::Item>>;
+
+    // Takes out the next item: the top of a stack, the front of a queue.
+    fn remove(&mut self) -> Option<Self::Item>;
+}
+
+// `N` slots used as a ring, with the oldest item at `head`. Every bounded
+// container needs to drop its oldest item in O(1) time, so even the stack is
+// stored this way.
+struct Slots<T, const N: usize> {
+    slots: [MaybeUninit<T>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> Slots<T, N> {
+    const fn new() -> Self {
+        Self {
+            slots: [const { MaybeUninit::uninit() }; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    // Maps a position, counted from the oldest item, to its slot.
+    fn slot(&self, i: usize) -> usize {
+        let slot = self.head + i;
+        if slot >= N {
+            slot - N
+        } else {
+            slot
+        }
+    }
+
+    fn get(&self, i: usize) -> Option<&T> {
+        if i >= self.len {
+            return None;
+        }
+        // SAFETY: the first `len` slots from `head` hold initialized items.
+        return Some(unsafe { self.slots[self.slot(i)].assume_init_ref() });
+    }
+
+    fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        if i >= self.len {
+            return None;
+        }
+        let slot = self.slot(i);
+        // SAFETY: as in `get`.
+        return Some(unsafe { self.slots[slot].assume_init_mut() });
+    }
+
+    // Adds a newest item. There must be room for it.
+    fn push(&mut self, item: T) {
+        debug_assert!(self.len < N);
+        let slot = self.slot(self.len);
+        self.slots[slot].write(item);
+        self.len += 1;
+    }
+
+    // Adds a newest item, dropping the oldest one first if there's no room.
+    // With no slots at all, the new item is the one dropped.
+    fn push_overwriting(&mut self, item: T) -> Option<T> {
+        if N == 0 {
+            return Some(item);
+        }
+        let oldest = if self.len == N { self.pop_oldest() } else { None };
+        self.push(item);
+        return oldest;
+    }
+
+    fn pop_oldest(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: the slot at `head` holds the oldest item, which is no longer
+        // counted once `head` moves past it.
+        let item = unsafe { self.slots[self.head].assume_init_read() };
+        self.head = self.slot(1);
+        self.len -= 1;
+        return Some(item);
+    }
+
+    fn pop_newest(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: the slot right after the remaining items holds the newest
+        // one, which is no longer counted.
+        return Some(unsafe { self.slots[self.slot(self.len)].assume_init_read() });
+    }
+
+    fn clear(&mut self) {
+        while self.pop_oldest().is_some() {}
+        self.head = 0;
+    }
+
+    fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            slots: &self.slots,
+            head: self.head,
+            front: 0,
+            back: self.len,
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for Slots<T, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for Slots<T, N> {
+    fn clone(&self) -> Self {
+        let mut slots = Self::new();
+        for item in self.iter() {
+            slots.push(item.clone());
+        }
+        slots
+    }
+}
+
+// Iterator over the items of a bounded container, created by `iter`.
+#[derive(Clone, Debug)]
+pub struct Iter<'a, T> {
+    slots: &'a [MaybeUninit<T>],
+    head: usize,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn item(&self, i: usize) -> &'a T {
+        let slot = (self.head + i) % self.slots.len();
+        // SAFETY: positions in `front..back` name initialized items, and the
+        // container can't change while it's borrowed.
+        unsafe { self.slots[slot].assume_init_ref() }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        return Some(self.item(self.front - 1));
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        return Some(self.item(self.back));
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+// A LIFO stack of at most `N` items, stored inline without any allocation.
+#[derive(Clone)]
+pub struct BoundedStack<T, const N: usize> {
+    data: Slots<T, N>,
+    policy: Overflow,
+}
+
+impl<T, const N: usize> BoundedStack<T, N> {
+    // Creates an empty stack that rejects items once it's full.
+    pub const fn new() -> Self {
+        Self::with_policy(Overflow::Reject)
+    }
+
+    pub const fn with_policy(policy: Overflow) -> Self {
+        Self {
+            data: Slots::new(),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> Overflow {
+        return self.policy;
+    }
+
+    // Pushes an item, following the overflow policy when the stack is full.
+    // Returns the bottom item if it was dropped to make room.
+    //
+    // Panics if the policy is `Overflow::Block`, which needs the stack to be
+    // shared through `Blocking`.
+    pub fn push(&mut self, item: T) -> Result<Option<T>, Full<T>> {
+        match self.policy {
+            Overflow::Reject => self.try_push(item).map(|()| None),
+            Overflow::OverwriteOldest => Ok(self.data.push_overwriting(item)),
+            Overflow::Block => panic!("{}", BLOCK_WITHOUT_BLOCKING),
+        }
+    }
+
+    // Pushes an item if there's room for it, whatever the overflow policy.
+    pub fn try_push(&mut self, item: T) -> Result<(), Full<T>> {
+        if self.is_full() {
+            return Err(Full(item));
+        }
+        self.data.push(item);
+        return Ok(());
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        return self.data.pop_newest();
+    }
+
+    // Returns the item on top of the stack, without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        return self.data.get(self.len().wrapping_sub(1));
+    }
+
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        let top = self.len().wrapping_sub(1);
+        return self.data.get_mut(top);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.data.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.data.len == 0;
+    }
+
+    pub fn is_full(&self) -> bool {
+        return self.data.len == N;
+    }
+
+    pub fn capacity(&self) -> usize {
+        return N;
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    // Returns an iterator over the items, from the top to the bottom.
+    pub fn iter(&self) -> Rev<Iter<'_, T>> {
+        self.data.iter().rev()
+    }
+}
+
+impl<T, const N: usize> Default for BoundedStack<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for BoundedStack<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.data.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Bounded for BoundedStack<T, N> {
+    type Item = T;
+
+    fn policy(&self) -> Overflow {
+        self.policy
+    }
+
+    fn is_full(&self) -> bool {
+        self.is_full()
+    }
+
+    fn capacity(&self) -> usize {
+        N
+    }
+
+    fn insert(&mut self, item: T) -> Result<Option<T>, Full<T>> {
+        self.push(item)
+    }
+
+    fn try_insert(&mut self, item: T) -> Result<(), Full<T>> {
+        self.try_push(item)
+    }
+
+    fn remove(&mut self) -> Option<T> {
+        self.pop()
+    }
+}
+
+// A FIFO queue of at most `N` items, stored inline without any allocation.
+#[derive(Clone)]
+pub struct BoundedQueue<T, const N: usize> {
+    data: Slots<T, N>,
+    policy: Overflow,
+}
+
+impl<T, const N: usize> BoundedQueue<T, N> {
+    // Creates an empty queue that rejects items once it's full.
+    pub const fn new() -> Self {
+        Self::with_policy(Overflow::Reject)
+    }
+
+    pub const fn with_policy(policy: Overflow) -> Self {
+        Self {
+            data: Slots::new(),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> Overflow {
+        return self.policy;
+    }
+
+    // Enqueues an item, following the overflow policy when the queue is full.
+    // Returns the front item if it was dropped to make room.
+    //
+    // Panics if the policy is `Overflow::Block`, which needs the queue to be
+    // shared through `Blocking`.
+    pub fn enqueue(&mut self, item: T) -> Result<Option<T>, Full<T>> {
+        match self.policy {
+            Overflow::Reject => self.try_enqueue(item).map(|()| None),
+            Overflow::OverwriteOldest => Ok(self.data.push_overwriting(item)),
+            Overflow::Block => panic!("{}", BLOCK_WITHOUT_BLOCKING),
+        }
+    }
+
+    // Enqueues an item if there's room for it, whatever the overflow policy.
+    pub fn try_enqueue(&mut self, item: T) -> Result<(), Full<T>> {
+        if self.is_full() {
+            return Err(Full(item));
+        }
+        self.data.push(item);
+        return Ok(());
+    }
+
+    pub fn dequeue(&mut self) -> Option<T> {
+        return self.data.pop_oldest();
+    }
+
+    // Returns the item at the front of the queue, the next one to be
+    // dequeued, without removing it.
+    pub fn peek_front(&self) -> Option<&T> {
+        return self.data.get(0);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.data.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.data.len == 0;
+    }
+
+    pub fn is_full(&self) -> bool {
+        return self.data.len == N;
+    }
+
+    pub fn capacity(&self) -> usize {
+        return N;
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    // Returns an iterator over the items, from the front to the back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.data.iter()
+    }
+}
+
+impl<T, const N: usize> Default for BoundedQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for BoundedQueue<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.data.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Bounded for BoundedQueue<T, N> {
+    type Item = T;
+
+    fn policy(&self) -> Overflow {
+        self.policy
+    }
+
+    fn is_full(&self) -> bool {
+        self.is_full()
+    }
+
+    fn capacity(&self) -> usize {
+        N
+    }
+
+    fn insert(&mut self, item: T) -> Result<Option<T>, Full<T>> {
+        self.enqueue(item)
+    }
+
+    fn try_insert(&mut self, item: T) -> Result<(), Full<T>> {
+        self.try_enqueue(item)
+    }
+
+    fn remove(&mut self) -> Option<T> {
+        self.dequeue()
+    }
+}
+
+// A bounded container shared between threads. Under `Overflow::Block`,
+// `push` waits until a `pop` on another thread makes room; the other policies
+// behave as they do on the container itself.
+#[derive(Debug, Default)]
+pub struct Blocking<C> {
+    inner: Mutex<C>,
+    not_full: Condvar,
+}
+
+impl<C: Bounded> Blocking<C> {
+    pub fn new(container: C) -> Self {
+        Self {
+            inner: Mutex::new(container),
+            not_full: Condvar::new(),
+        }
+    }
+
+    // Adds an item, waiting for room if the policy is `Overflow::Block`.
+    // Returns the item that was dropped to make room, if any.
+    //
+    // A container with no capacity at all never has room, so it rejects the
+    // item instead of waiting forever.
+    pub fn push(&self, item: C::Item) -> Result<Option<C::Item>, Full<C::Item>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.policy() != Overflow::Block {
+            return inner.insert(item);
+        }
+        if inner.capacity() == 0 {
+            return Err(Full(item));
+        }
+        inner = self.not_full.wait_while(inner, |c| c.is_full()).unwrap();
+        inner.try_insert(item).map(|()| None)
+    }
+
+    // Takes out the next item, if there's one, without waiting.
+    pub fn pop(&self) -> Option<C::Item> {
+        let item = self.inner.lock().unwrap().remove();
+        if item.is_some() {
+            // One item out makes room for one item in.
+            self.not_full.notify_one();
+        }
+        item
+    }
+
+    pub fn into_inner(self) -> C {
+        self.inner.into_inner().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn test_stack_reject() {
+        let mut stack: BoundedStack<i32, 3> = BoundedStack::new();
+        assert_eq!(stack.push(1), Ok(None));
+        assert_eq!(stack.push(2), Ok(None));
+        assert_eq!(stack.push(3), Ok(None));
+        assert!(stack.is_full());
+        assert_eq!(stack.push(4), Err(Full(4)));
+
+        assert_eq!(stack.peek(), Some(&3));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.try_push(5), Ok(()));
+        assert_eq!(stack.iter().copied().collect::<Vec<i32>>(), vec![5, 2, 1]);
+    }
+
+    #[test]
+    fn test_stack_overwrite_oldest() {
+        let mut stack: BoundedStack<i32, 3> = BoundedStack::with_policy(Overflow::OverwriteOldest);
+        for i in 1..=3 {
+            assert_eq!(stack.push(i), Ok(None));
+        }
+        // the bottom of the stack goes first
+        assert_eq!(stack.push(4), Ok(Some(1)));
+        assert_eq!(stack.push(5), Ok(Some(2)));
+        assert_eq!(stack.try_push(6), Err(Full(6)));
+
+        assert_eq!(stack.pop(), Some(5));
+        assert_eq!(stack.pop(), Some(4));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), None);
+    }
+
+    #[test]
+    fn test_queue_policies() {
+        let mut queue: BoundedQueue<i32, 2> = BoundedQueue::new();
+        assert_eq!(queue.enqueue(1), Ok(None));
+        assert_eq!(queue.enqueue(2), Ok(None));
+        assert_eq!(queue.try_enqueue(3).map_err(Full::into_inner), Err(3));
+        assert_eq!(queue.dequeue(), Some(1));
+
+        let mut queue: BoundedQueue<i32, 2> = BoundedQueue::with_policy(Overflow::OverwriteOldest);
+        for i in 0..5 {
+            queue.enqueue(i).unwrap();
+        }
+        assert_eq!(queue.peek_front(), Some(&3));
+        assert_eq!(queue.iter().copied().collect::<Vec<i32>>(), vec![3, 4]);
+        assert_eq!(format!("{:?}", queue), "[3, 4]");
+    }
+
+    #[test]
+    #[should_panic(expected = "shared through `Blocking`")]
+    fn test_block_without_blocking() {
+        // nothing else can dequeue while the queue is borrowed, so it can't wait
+        let mut queue: BoundedQueue<i32, 1> = BoundedQueue::with_policy(Overflow::Block);
+        let _ = queue.enqueue(1);
+    }
+
+    #[test]
+    fn test_no_capacity() {
+        let mut stack: BoundedStack<i32, 0> = BoundedStack::with_policy(Overflow::OverwriteOldest);
+        assert_eq!(stack.push(1), Ok(Some(1)));
+        assert!(stack.is_empty() && stack.is_full());
+
+        let mut queue: BoundedQueue<i32, 0> = BoundedQueue::new();
+        assert_eq!(queue.enqueue(1), Err(Full(1)));
+        assert_eq!(queue.dequeue(), None);
+
+        // there will never be room to wait for
+        let queue = Blocking::new(BoundedQueue::<i32, 0>::with_policy(Overflow::Block));
+        assert_eq!(queue.push(1), Err(Full(1)));
+    }
+
+    #[test]
+    fn test_items_are_dropped_once() {
+        let item = Rc::new(0);
+        let mut queue: BoundedQueue<Rc<i32>, 4> = BoundedQueue::with_policy(Overflow::OverwriteOldest);
+        for _ in 0..6 {
+            queue.enqueue(Rc::clone(&item)).unwrap();
+        }
+        assert_eq!(Rc::strong_count(&item), 5);
+
+        let copy = queue.clone();
+        assert_eq!(Rc::strong_count(&item), 9);
+        drop(copy);
+        drop(queue.dequeue());
+        assert_eq!(Rc::strong_count(&item), 4);
+        drop(queue);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    fn test_agrees_with_vec() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+
+        let mut queue: BoundedQueue<u64, 7> = BoundedQueue::with_policy(Overflow::OverwriteOldest);
+        let mut stack: BoundedStack<u64, 7> = BoundedStack::with_policy(Overflow::OverwriteOldest);
+        let mut expected_queue = Vec::new();
+        let mut expected_stack = Vec::new();
+        for _ in 0..5000 {
+            if rng.below(3) < 2 {
+                let item = rng.below(1000);
+                let dropped = (expected_queue.len() == 7).then(|| expected_queue.remove(0));
+                assert_eq!(queue.enqueue(item), Ok(dropped));
+                expected_queue.push(item);
+
+                let dropped = (expected_stack.len() == 7).then(|| expected_stack.remove(0));
+                assert_eq!(stack.push(item), Ok(dropped));
+                expected_stack.push(item);
+            } else {
+                let front = (!expected_queue.is_empty()).then(|| expected_queue.remove(0));
+                assert_eq!(queue.dequeue(), front);
+                assert_eq!(stack.pop(), expected_stack.pop());
+            }
+            assert!(queue.iter().eq(expected_queue.iter()));
+            assert!(stack.iter().eq(expected_stack.iter().rev()));
+        }
+    }
+
+    #[test]
+    fn test_blocking_push_waits_for_room() {
+        let queue: Blocking<BoundedQueue<u32, 4>> = Blocking::new(BoundedQueue::with_policy(Overflow::Block));
+        let received = thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..1000 {
+                    assert_eq!(queue.push(i), Ok(None));
+                }
+            });
+
+            let mut received = Vec::new();
+            while received.len() < 1000 {
+                match queue.pop() {
+                    Some(item) => received.push(item),
+                    None => thread::yield_now(),
+                }
+            }
+            received
+        });
+
+        assert_eq!(received, (0..1000).collect::<Vec<u32>>());
+        assert!(queue.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_blocking_with_other_policies() {
+        let stack = Blocking::new(BoundedStack::<i32, 1>::new());
+        assert_eq!(stack.push(1), Ok(None));
+        assert_eq!(stack.push(2), Err(Full(2)));
+
+        let stack = Blocking::new(BoundedStack::<i32, 1>::with_policy(Overflow::OverwriteOldest));
+        assert_eq!(stack.push(1), Ok(None));
+        assert_eq!(stack.push(2), Ok(Some(1)));
+        assert_eq!(stack.pop(), Some(2));
+    }
+}
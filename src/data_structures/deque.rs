@@ -0,0 +1,384 @@
+use std::ops::{Index, IndexMut, RangeBounds};
+
+use super::ring_buffer::RingBuffer;
+pub use super::ring_buffer::{Drain, IntoIter, Iter, IterMut};
+
+const DEFAULT_CAPACITY: usize = 5;
+
+// A double-ended queue over a growable ring buffer, shared with `Queue`.
+//
+// Items are pushed and popped at both ends in amortized O(1) time, and the
+// `i`-th item from the front is reached in O(1) time. Positions always count
+// from the front.
+#[derive(Clone, Debug)]
+pub struct Deque<T> {
+    data: RingBuffer<T>,
+}
+
+impl<T> Deque<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    // Creates an empty deque that can hold `capacity` items before growing.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: RingBuffer::with_capacity(capacity),
+        }
+    }
+
+    pub fn push_front(&mut self, item: T) {
+        self.data.push_front(item);
+    }
+
+    pub fn push_back(&mut self, item: T) {
+        self.data.push_back(item);
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        return self.data.pop_front();
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        return self.data.pop_back();
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        return self.data.get(0);
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        return self.data.get(self.len().wrapping_sub(1));
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        return self.data.get_mut(0);
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        let last = self.len().wrapping_sub(1);
+        return self.data.get_mut(last);
+    }
+
+    // Returns the item at position `i`, or `None` if it's out of bounds.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        return self.data.get(i);
+    }
+
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        return self.data.get_mut(i);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.data.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.data.len() == 0;
+    }
+
+    // Returns how many items the deque can hold before growing.
+    pub fn capacity(&self) -> usize {
+        return self.data.capacity();
+    }
+
+    // Drops every item, keeping the capacity.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    // Moves the first `n` items to the back, so the item at position `n`
+    // becomes the front. Time complexity: O(min(n, len - n)).
+    //
+    // Panics if `n` is greater than the length.
+    pub fn rotate_left(&mut self, n: usize) {
+        self.data.rotate_left(n);
+    }
+
+    // Moves the last `n` items to the front. Time complexity:
+    // O(min(n, len - n)).
+    //
+    // Panics if `n` is greater than the length.
+    pub fn rotate_right(&mut self, n: usize) {
+        assert!(n <= self.len(), "can't rotate {} items of {}", n, self.len());
+        self.data.rotate_left(self.len() - n);
+    }
+
+    // Returns the items as two slices, which together hold them from the
+    // front to the back. The second one is empty unless the items wrap
+    // around the end of the buffer.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        self.data.as_slices()
+    }
+
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        self.data.as_mut_slices()
+    }
+
+    // Moves the items so they're a single slice, e.g. to sort them, and
+    // returns it. Time complexity: O(capacity) if they wrapped around.
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        self.data.make_contiguous()
+    }
+
+    // Keeps only the items for which `keep` returns true, in order.
+    // Time complexity: O(n).
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, keep: F) {
+        self.data.retain(keep);
+    }
+
+    // Returns an iterator over the items, from the front to the back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.data.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.data.iter_mut()
+    }
+
+    // Returns an iterator over the items at the positions in `range`.
+    //
+    // Panics if the range is out of bounds.
+    pub fn range<R: RangeBounds<usize>>(&self, range: R) -> Iter<'_, T> {
+        self.data.range(range)
+    }
+
+    // Returns an iterator that removes every item, from the front or from the
+    // back. The items it doesn't get to are dropped with it.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        self.data.drain()
+    }
+}
+
+impl<T> Default for Deque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: PartialEq> PartialEq for Deque<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Deque<T> {}
+
+impl<T> Index<usize> for Deque<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        let len = self.len();
+        self.get(i).unwrap_or_else(|| panic!("index {} out of bounds for length {}", i, len))
+    }
+}
+
+impl<T> IndexMut<usize> for Deque<T> {
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        let len = self.len();
+        self.get_mut(i).unwrap_or_else(|| panic!("index {} out of bounds for length {}", i, len))
+    }
+}
+
+impl<T> FromIterator<T> for Deque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut deque = Self::new();
+        deque.extend(iter);
+        deque
+    }
+}
+
+impl<T> Extend<T> for Deque<T> {
+    // Pushes the items to the back, in order.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_back(item);
+        }
+    }
+}
+
+impl<T> IntoIterator for Deque<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    // Consumes the deque, yielding the items from the front to the back.
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter::new(self.data)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Deque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Deque<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_both_ends() {
+        let mut deque = Deque::new();
+        deque.push_back(2);
+        deque.push_front(1);
+        deque.push_back(3);
+        deque.push_front(0);
+
+        assert_eq!(deque.front(), Some(&0));
+        assert_eq!(deque.back(), Some(&3));
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_front(), Some(0));
+        assert_eq!(deque.iter().copied().collect::<Vec<i32>>(), vec![1, 2]);
+
+        *deque.front_mut().unwrap() = 10;
+        *deque.back_mut().unwrap() += 10;
+        assert_eq!((deque[0], deque[1]), (10, 12));
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut deque: Deque<i32> = Deque::with_capacity(0);
+        assert!(deque.is_empty());
+        assert_eq!(deque.back(), None);
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.pop_back(), None);
+        assert_eq!(deque.as_slices(), (&[][..], &[][..]));
+    }
+
+    #[test]
+    #[should_panic(expected = "index 3 out of bounds for length 3")]
+    fn test_index_out_of_bounds() {
+        let deque: Deque<i32> = (0..3).collect();
+        let _ = deque[3];
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut deque: Deque<i32> = (0..6).collect();
+        deque.rotate_left(2);
+        assert_eq!(deque.iter().copied().collect::<Vec<i32>>(), vec![2, 3, 4, 5, 0, 1]);
+        deque.rotate_right(5);
+        assert_eq!(deque.iter().copied().collect::<Vec<i32>>(), vec![3, 4, 5, 0, 1, 2]);
+        deque.rotate_left(0);
+        deque.rotate_right(6);
+        assert_eq!(deque[0], 3);
+    }
+
+    #[test]
+    fn test_slices_and_make_contiguous() {
+        let mut deque = Deque::with_capacity(8);
+        deque.extend([3, 4, 5]);
+        deque.push_front(2);
+        deque.push_front(1);
+
+        let (front, back) = deque.as_slices();
+        assert_eq!(front, &[1, 2]);
+        assert_eq!(back, &[3, 4, 5]);
+
+        let items = deque.make_contiguous();
+        items.sort_by(|a, b| b.cmp(a));
+        assert_eq!(deque.as_slices(), (&[5, 4, 3, 2, 1][..], &[][..]));
+    }
+
+    #[test]
+    fn test_range_and_retain() {
+        let mut deque: Deque<i32> = (0..5).collect();
+        deque.push_front(-1);
+        deque.push_front(-2);
+
+        let range: Vec<i32> = deque.range(1..=4).copied().collect();
+        assert_eq!(range, vec![-1, 0, 1, 2]);
+        assert_eq!(deque.range(..).len(), 7);
+        assert_eq!(deque.range(5..).rev().copied().collect::<Vec<i32>>(), vec![4, 3]);
+
+        deque.retain(|x| x % 2 == 0);
+        assert_eq!(deque.iter().copied().collect::<Vec<i32>>(), vec![-2, 0, 2, 4]);
+
+        for x in &mut deque {
+            *x *= 10;
+        }
+        assert_eq!(deque.drain().rev().collect::<Vec<i32>>(), vec![40, 20, 0, -20]);
+        assert!(deque.is_empty());
+    }
+
+    // 0-1 BFS: the shortest paths when edges weigh 0 or 1, pushing the ends
+    // of 0 edges to the front and those of 1 edges to the back.
+    #[test]
+    fn test_zero_one_bfs() {
+        let edges: [&[(usize, u32)]; 5] = [&[(1, 1), (2, 0)], &[(3, 0)], &[(1, 0), (3, 1)], &[(4, 1)], &[]];
+        let mut distance = [u32::MAX; 5];
+        let mut deque = Deque::new();
+        distance[0] = 0;
+        deque.push_back(0);
+
+        while let Some(node) = deque.pop_front() {
+            for &(next, weight) in edges[node] {
+                if distance[node] + weight < distance[next] {
+                    distance[next] = distance[node] + weight;
+                    if weight == 0 {
+                        deque.push_front(next);
+                    } else {
+                        deque.push_back(next);
+                    }
+                }
+            }
+        }
+        assert_eq!(distance, [0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_agrees_with_vec_deque() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+
+        let mut deque = Deque::with_capacity(1);
+        let mut expected = VecDeque::new();
+        for step in 0..5000 {
+            match rng.below(10) {
+                0..=2 => {
+                    let item = rng.below(1000);
+                    deque.push_back(item);
+                    expected.push_back(item);
+                }
+                3..=5 => {
+                    let item = rng.below(1000);
+                    deque.push_front(item);
+                    expected.push_front(item);
+                }
+                6 => assert_eq!(deque.pop_back(), expected.pop_back()),
+                7 => assert_eq!(deque.pop_front(), expected.pop_front()),
+                8 if !expected.is_empty() => {
+                    let n = rng.below(expected.len() as u64) as usize;
+                    deque.rotate_left(n);
+                    expected.rotate_left(n);
+                }
+                _ if !expected.is_empty() => {
+                    let i = rng.below(expected.len() as u64) as usize;
+                    assert_eq!(deque.get(i), expected.get(i));
+                }
+                _ => {}
+            }
+
+            assert_eq!(deque.len(), expected.len());
+            if step % 101 == 0 {
+                assert!(deque.iter().eq(expected.iter()));
+                let (a, b) = deque.as_slices();
+                assert!(a.iter().chain(b).eq(expected.iter()));
+            }
+        }
+    }
+}
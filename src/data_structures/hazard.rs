@@ -0,0 +1,267 @@
+// Hazard pointers, to free the nodes of a lock-free structure safely.
+//
+// A thread that's about to read a node publishes its address in a hazard
+// pointer first. A thread that removes a node from the structure doesn't free
+// it right away but retires it, and retired nodes are only freed once no
+// hazard pointer holds their address. Each structure has its own domain of
+// hazard pointers and retired nodes, so dropping the structure frees them all.
+
+use std::ptr;
+use std::sync::atomic::Ordering;
+
+use super::sync::{fence, AtomicBool, AtomicPtr, AtomicUsize};
+
+// How many nodes may wait to be freed before a thread looks for those it can
+// free. Tests look after every retired node, so that a node freed too early
+// shows up in short runs.
+const SCAN_THRESHOLD: usize = if cfg!(test) { 1 } else { 64 };
+
+// A hazard pointer, owned by one thread at a time while `active` is set.
+// Records are never freed before the domain, so the list only grows, up to
+// the number of hazard pointers held at once.
+struct Record {
+    hazard: AtomicPtr<()>,
+    active: AtomicBool,
+    next: *mut Record,
+}
+
+struct Retired {
+    ptr: *mut (),
+    free: unsafe fn(*mut ()),
+    next: *mut Retired,
+}
+
+pub(crate) struct Domain {
+    records: AtomicPtr<Record>,
+    retired: AtomicPtr<Retired>,
+    retired_count: AtomicUsize,
+}
+
+// SAFETY: the records and retired nodes are only shared through atomics, and
+// whoever retires a node hands it over to the domain.
+unsafe impl Send for Domain {}
+unsafe impl Sync for Domain {}
+
+impl Domain {
+    pub(crate) fn new() -> Self {
+        Self {
+            records: AtomicPtr::new(ptr::null_mut()),
+            retired: AtomicPtr::new(ptr::null_mut()),
+            retired_count: AtomicUsize::new(0),
+        }
+    }
+
+    // Returns a hazard pointer that doesn't protect anything yet, reusing a
+    // free record if there's one.
+    pub(crate) fn hazard_pointer(&self) -> HazardPointer<'_> {
+        let mut record = self.records.load(Ordering::Acquire);
+        while !record.is_null() {
+            // SAFETY: records live as long as the domain.
+            let r = unsafe { &*record };
+            if r.active.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+                return HazardPointer { record: r };
+            }
+            record = r.next;
+        }
+
+        let record = Box::into_raw(Box::new(Record {
+            hazard: AtomicPtr::new(ptr::null_mut()),
+            active: AtomicBool::new(true),
+            next: ptr::null_mut(),
+        }));
+        loop {
+            let head = self.records.load(Ordering::Acquire);
+            // SAFETY: the record isn't shared until the exchange succeeds.
+            unsafe { (*record).next = head };
+            if self.records.compare_exchange(head, record, Ordering::Release, Ordering::Relaxed).is_ok() {
+                // SAFETY: as above.
+                return HazardPointer { record: unsafe { &*record } };
+            }
+        }
+    }
+
+    // Hands over a node that's no longer reachable from the structure, to be
+    // freed as a `Box<T>` once no hazard pointer protects it.
+    //
+    // SAFETY: `ptr` must come from `Box::into_raw`, and must not be retired
+    // twice or used by the caller afterwards.
+    pub(crate) unsafe fn retire<T>(&self, ptr: *mut T) {
+        unsafe fn free<T>(ptr: *mut ()) {
+            drop(unsafe { Box::from_raw(ptr.cast::<T>()) });
+        }
+
+        // Count the node before it's in the list, so a scan never frees more
+        // nodes than were counted.
+        let count = self.retired_count.fetch_add(1, Ordering::Relaxed) + 1;
+        let retired = Box::into_raw(Box::new(Retired {
+            ptr: ptr.cast(),
+            free: free::<T>,
+            next: ptr::null_mut(),
+        }));
+        self.push_retired(retired, retired);
+        if count >= SCAN_THRESHOLD {
+            self.scan();
+        }
+    }
+
+    // Pushes a list of retired nodes, from `first` to `last`.
+    fn push_retired(&self, first: *mut Retired, last: *mut Retired) {
+        loop {
+            let head = self.retired.load(Ordering::Acquire);
+            // SAFETY: the list isn't shared until the exchange succeeds.
+            unsafe { (*last).next = head };
+            if self.retired.compare_exchange(head, first, Ordering::Release, Ordering::Relaxed).is_ok() {
+                return;
+            }
+        }
+    }
+
+    // Frees the retired nodes that no hazard pointer protects, and puts the
+    // others back.
+    fn scan(&self) {
+        // Pairs with the fence in `protect`: either the hazard pointer set
+        // there is seen below, or that thread sees the node was removed.
+        fence(Ordering::SeqCst);
+        let mut retired = self.retired.swap(ptr::null_mut(), Ordering::Acquire);
+
+        let mut hazards = Vec::new();
+        let mut record = self.records.load(Ordering::Acquire);
+        while !record.is_null() {
+            // SAFETY: records live as long as the domain.
+            let r = unsafe { &*record };
+            let hazard = r.hazard.load(Ordering::Acquire);
+            if !hazard.is_null() {
+                hazards.push(hazard);
+            }
+            record = r.next;
+        }
+        hazards.sort_unstable();
+
+        let (mut kept, mut kept_last): (*mut Retired, *mut Retired) = (ptr::null_mut(), ptr::null_mut());
+        let mut freed = 0;
+        while !retired.is_null() {
+            // SAFETY: this thread took the whole list over with the swap.
+            let node = unsafe { Box::from_raw(retired) };
+            let next = node.next;
+            if hazards.binary_search(&node.ptr).is_ok() {
+                let node = Box::into_raw(node);
+                // SAFETY: as above.
+                unsafe { (*node).next = kept };
+                if kept_last.is_null() {
+                    kept_last = node;
+                }
+                kept = node;
+            } else {
+                // SAFETY: the node was retired, and nothing protects it.
+                unsafe { (node.free)(node.ptr) };
+                freed += 1;
+            }
+            retired = next;
+        }
+
+        self.retired_count.fetch_sub(freed, Ordering::Relaxed);
+        if !kept.is_null() {
+            self.push_retired(kept, kept_last);
+        }
+    }
+}
+
+impl Drop for Domain {
+    fn drop(&mut self) {
+        let mut retired = *self.retired.get_mut();
+        while !retired.is_null() {
+            // SAFETY: no other thread can protect anything anymore.
+            let node = unsafe { Box::from_raw(retired) };
+            unsafe { (node.free)(node.ptr) };
+            retired = node.next;
+        }
+
+        let mut record = *self.records.get_mut();
+        while !record.is_null() {
+            // SAFETY: as above.
+            let r = unsafe { Box::from_raw(record) };
+            record = r.next;
+        }
+    }
+}
+
+// A hazard pointer, protecting at most one node at a time. It goes back to
+// the domain when dropped.
+pub(crate) struct HazardPointer<'d> {
+    record: &'d Record,
+}
+
+impl HazardPointer<'_> {
+    // Loads the pointer in `src` and protects the node it points to, if any.
+    // The node won't be freed until the hazard pointer protects something
+    // else, is cleared or dropped, as long as it was retired after being
+    // removed from wherever `src` is.
+    pub(crate) fn protect<T>(&self, src: &AtomicPtr<T>) -> *mut T {
+        let mut ptr = src.load(Ordering::Relaxed);
+        loop {
+            self.record.hazard.store(ptr.cast(), Ordering::Relaxed);
+            fence(Ordering::SeqCst);
+            // If `src` still holds the node, it wasn't retired before the
+            // hazard pointer was visible, so no scan will free it.
+            let current = src.load(Ordering::Acquire);
+            if current == ptr {
+                return ptr;
+            }
+            ptr = current;
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        self.record.hazard.store(ptr::null_mut(), Ordering::Release);
+    }
+}
+
+impl Drop for HazardPointer<'_> {
+    fn drop(&mut self) {
+        self.clear();
+        self.record.active.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize as Counter;
+    use std::sync::Arc;
+
+    struct Tracked(Arc<Counter>);
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_protected_nodes_outlive_scans() {
+        let dropped = Arc::new(Counter::new(0));
+        let domain = Domain::new();
+        let first = Box::into_raw(Box::new(Tracked(Arc::clone(&dropped))));
+        let second = Box::into_raw(Box::new(Tracked(Arc::clone(&dropped))));
+        let src = AtomicPtr::new(first);
+
+        let hazard = domain.hazard_pointer();
+        assert_eq!(hazard.protect(&src), first);
+        src.store(second, Ordering::Release);
+        unsafe { domain.retire(first) };
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+
+        // the record is reused once it's back
+        drop(hazard);
+        let hazard = domain.hazard_pointer();
+        assert_eq!(hazard.protect(&src), second);
+        src.store(ptr::null_mut(), Ordering::Release);
+        unsafe { domain.retire(second) };
+        assert_eq!(dropped.load(Ordering::Relaxed), 1);
+
+        hazard.clear();
+        drop(hazard);
+        drop(domain);
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+    }
+}
@@ -199,6 +199,7 @@ impl<T: std::clone::Clone + std::fmt::Debug + std::cmp::PartialEq> DoublyLinkedL
         }
     }
 
+    #[allow(clippy::borrowed_box)]
     pub fn search_by_key(&mut self, key: T) -> Option<&Box<Node<T>>> {
         // set current node as head (starting from head, then)
         let mut current_node = &self.head;
@@ -227,6 +228,12 @@ impl<T: std::clone::Clone + std::fmt::Debug + std::cmp::PartialEq> DoublyLinkedL
     //pub fn reverse(&mut self) -> DoublyLinkedList<T> {}
 }
 
+impl<T: std::clone::Clone + std::fmt::Debug + std::cmp::PartialEq> Default for DoublyLinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,461 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ptr;
+use std::sync::atomic::Ordering;
+
+use super::hazard::Domain;
+use super::sync::AtomicPtr;
+
+struct StackNode<T> {
+    item: ManuallyDrop<T>,
+    // Set before the node is pushed, and never changed afterwards.
+    next: *mut StackNode<T>,
+}
+
+// A lock-free LIFO stack (Treiber, 1986), shared between threads by reference.
+//
+// The top is a single atomic pointer, swung with compare-and-swap by pushes
+// and pops. Popped nodes are freed through hazard pointers: a thread reading
+// the top node can't have it freed, or reused at the same address, under its
+// feet, which also rules out the ABA problem.
+pub struct LockFreeStack<T> {
+    head: AtomicPtr<StackNode<T>>,
+    domain: Domain,
+    marker: PhantomData<T>,
+}
+
+// SAFETY: items move between threads through the stack, but are never shared.
+unsafe impl<T: Send> Send for LockFreeStack<T> {}
+unsafe impl<T: Send> Sync for LockFreeStack<T> {}
+
+impl<T> LockFreeStack<T> {
+    pub fn new() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+            domain: Domain::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn push(&self, item: T) {
+        let node = Box::into_raw(Box::new(StackNode {
+            item: ManuallyDrop::new(item),
+            next: ptr::null_mut(),
+        }));
+        loop {
+            let head = self.head.load(Ordering::Relaxed);
+            // SAFETY: the node isn't shared until the exchange succeeds.
+            unsafe { (*node).next = head };
+            if self.head.compare_exchange(head, node, Ordering::Release, Ordering::Relaxed).is_ok() {
+                return;
+            }
+        }
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let hazard = self.domain.hazard_pointer();
+        loop {
+            let head = hazard.protect(&self.head);
+            if head.is_null() {
+                return None;
+            }
+            // SAFETY: the node is protected, so it's still allocated even if
+            // another thread popped it meanwhile.
+            let next = unsafe { (*head).next };
+            if self.head.compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                hazard.clear();
+                // SAFETY: only the thread that unlinked the node takes its
+                // item, and retiring it doesn't drop the item again.
+                let item = unsafe { ptr::read(&(*head).item) };
+                unsafe { self.domain.retire(head) };
+                return Some(ManuallyDrop::into_inner(item));
+            }
+        }
+    }
+
+    // Returns whether the stack was empty at some point during the call.
+    pub fn is_empty(&self) -> bool {
+        return self.head.load(Ordering::Acquire).is_null();
+    }
+}
+
+impl<T> Default for LockFreeStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for LockFreeStack<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockFreeStack").finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for LockFreeStack<T> {
+    fn drop(&mut self) {
+        let mut node = *self.head.get_mut();
+        while !node.is_null() {
+            // SAFETY: nothing else can reach the nodes anymore.
+            let mut boxed = unsafe { Box::from_raw(node) };
+            unsafe { ManuallyDrop::drop(&mut boxed.item) };
+            node = boxed.next;
+        }
+    }
+}
+
+struct QueueNode<T> {
+    // Uninitialized in the sentinel node, which `head` points to.
+    item: MaybeUninit<T>,
+    next: AtomicPtr<QueueNode<T>>,
+}
+
+impl<T> QueueNode<T> {
+    fn new(item: MaybeUninit<T>) -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            item,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+// A lock-free FIFO queue for any number of producers and consumers (Michael
+// and Scott, 1996), shared between threads by reference.
+//
+// The nodes form a linked list that starts with a sentinel, whose successor
+// holds the front item. Enqueues link a node after the last one, then swing
+// `tail` to it; any thread that finds `tail` lagging behind helps swing it
+// first, so no thread waits for another. Dequeues swing `head` forward, and
+// the node that held the front item becomes the new sentinel.
+pub struct LockFreeQueue<T> {
+    head: AtomicPtr<QueueNode<T>>,
+    tail: AtomicPtr<QueueNode<T>>,
+    domain: Domain,
+    marker: PhantomData<T>,
+}
+
+// SAFETY: as for `LockFreeStack`.
+unsafe impl<T: Send> Send for LockFreeQueue<T> {}
+unsafe impl<T: Send> Sync for LockFreeQueue<T> {}
+
+impl<T> LockFreeQueue<T> {
+    pub fn new() -> Self {
+        let sentinel = QueueNode::new(MaybeUninit::uninit());
+        Self {
+            head: AtomicPtr::new(sentinel),
+            tail: AtomicPtr::new(sentinel),
+            domain: Domain::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn enqueue(&self, item: T) {
+        let node = QueueNode::new(MaybeUninit::new(item));
+        let hazard = self.domain.hazard_pointer();
+        loop {
+            let tail = hazard.protect(&self.tail);
+            // SAFETY: the tail is protected, and never null.
+            let next = unsafe { (*tail).next.load(Ordering::Acquire) };
+            if !next.is_null() {
+                // Help the enqueue that linked `next` finish.
+                let _ = self.tail.compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+                continue;
+            }
+            // SAFETY: as above.
+            let linked = unsafe { &(*tail).next };
+            if linked.compare_exchange(next, node, Ordering::Release, Ordering::Relaxed).is_ok() {
+                // Fails only if another thread already helped.
+                let _ = self.tail.compare_exchange(tail, node, Ordering::Release, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+
+    pub fn dequeue(&self) -> Option<T> {
+        let head_hazard = self.domain.hazard_pointer();
+        let next_hazard = self.domain.hazard_pointer();
+        loop {
+            let head = head_hazard.protect(&self.head);
+            // SAFETY: the sentinel is protected, and never null.
+            let next = next_hazard.protect(unsafe { &(*head).next });
+            // `next` is only retired after `head` moves past it, so while
+            // `head` hasn't moved, `next` was protected in time.
+            if self.head.load(Ordering::Acquire) != head {
+                continue;
+            }
+            if next.is_null() {
+                return None;
+            }
+            if self.tail.load(Ordering::Acquire) == head {
+                // The tail lags behind the node about to become the sentinel.
+                let _ = self.tail.compare_exchange(head, next, Ordering::Release, Ordering::Relaxed);
+                continue;
+            }
+            if self.head.compare_exchange(head, next, Ordering::AcqRel, Ordering::Relaxed).is_ok() {
+                // SAFETY: only the thread that moved `head` takes the item, and
+                // `next` stays protected until then. The new sentinel's item
+                // counts as uninitialized from now on.
+                let item = unsafe { (*next).item.assume_init_read() };
+                head_hazard.clear();
+                next_hazard.clear();
+                // SAFETY: nothing points to the old sentinel anymore.
+                unsafe { self.domain.retire(head) };
+                return Some(item);
+            }
+        }
+    }
+
+    // Returns whether the queue was empty at some point during the call.
+    pub fn is_empty(&self) -> bool {
+        let hazard = self.domain.hazard_pointer();
+        let head = hazard.protect(&self.head);
+        // SAFETY: the sentinel is protected, and never null.
+        return unsafe { (*head).next.load(Ordering::Acquire) }.is_null();
+    }
+}
+
+impl<T> Default for LockFreeQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for LockFreeQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockFreeQueue").finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for LockFreeQueue<T> {
+    fn drop(&mut self) {
+        // SAFETY: nothing else can reach the nodes anymore. Every node but the
+        // sentinel holds an item.
+        let mut sentinel = unsafe { Box::from_raw(*self.head.get_mut()) };
+        let mut node = *sentinel.next.get_mut();
+        while !node.is_null() {
+            let mut boxed = unsafe { Box::from_raw(node) };
+            unsafe { boxed.item.assume_init_drop() };
+            node = *boxed.next.get_mut();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::model;
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::thread;
+
+    // An item that counts how many times it's dropped.
+    #[derive(Debug)]
+    struct Tracked {
+        value: usize,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Tracked {
+        fn new(value: usize, drops: &Arc<AtomicUsize>) -> Self {
+            Self {
+                value,
+                drops: Arc::clone(drops),
+            }
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_stack_sequential() {
+        let stack = LockFreeStack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+        for i in 0..100 {
+            stack.push(i);
+        }
+        assert!(!stack.is_empty());
+        for i in (50..100).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_queue_sequential() {
+        let queue = LockFreeQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.dequeue(), None);
+        for i in 0..100 {
+            queue.enqueue(i);
+        }
+        assert!(!queue.is_empty());
+        for i in 0..50 {
+            assert_eq!(queue.dequeue(), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_items_are_dropped_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let stack = LockFreeStack::new();
+        let queue = LockFreeQueue::new();
+        for i in 0..10 {
+            stack.push(Tracked::new(i, &drops));
+            queue.enqueue(Tracked::new(i, &drops));
+        }
+        drop(stack.pop());
+        drop(queue.dequeue());
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+
+        drop(stack);
+        drop(queue);
+        assert_eq!(drops.load(Ordering::Relaxed), 20);
+    }
+
+    const THREADS: usize = 8;
+    const ITEMS_PER_THREAD: usize = 20_000;
+
+    #[test]
+    fn test_stack_stress() {
+        let stack = LockFreeStack::new();
+        let popped: Vec<Vec<usize>> = thread::scope(|s| {
+            let workers: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let stack = &stack;
+                    s.spawn(move || {
+                        let mut popped = Vec::new();
+                        for i in 0..ITEMS_PER_THREAD {
+                            stack.push(t * ITEMS_PER_THREAD + i);
+                            if i % 3 != 0 {
+                                popped.extend(stack.pop());
+                            }
+                        }
+                        popped
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+
+        let mut all: Vec<usize> = popped.into_iter().flatten().collect();
+        all.extend(std::iter::from_fn(|| stack.pop()));
+        all.sort_unstable();
+        assert_eq!(all, (0..THREADS * ITEMS_PER_THREAD).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_queue_stress() {
+        let queue = LockFreeQueue::new();
+        let dequeued: Vec<Vec<(usize, usize)>> = thread::scope(|s| {
+            let workers: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        let mut dequeued = Vec::new();
+                        for i in 0..ITEMS_PER_THREAD {
+                            queue.enqueue((t, i));
+                            if i % 3 != 0 {
+                                dequeued.extend(queue.dequeue());
+                            }
+                        }
+                        dequeued
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+
+        // Each consumer sees the items of each producer in order.
+        for items in &dequeued {
+            let mut last = [None; THREADS];
+            for &(t, i) in items {
+                assert!(last[t] < Some(i));
+                last[t] = Some(i);
+            }
+        }
+
+        let mut all: Vec<(usize, usize)> = dequeued.into_iter().flatten().collect();
+        all.extend(std::iter::from_fn(|| queue.dequeue()));
+        all.sort_unstable();
+        let expected: Vec<(usize, usize)> = (0..THREADS)
+            .flat_map(|t| (0..ITEMS_PER_THREAD).map(move |i| (t, i)))
+            .collect();
+        assert_eq!(all, expected);
+    }
+
+    // Pushes an item, then pops on two threads while the second one pushes
+    // another item, under every schedule with at most `max_preemptions`.
+    fn check_stack(max_preemptions: usize) -> usize {
+        model::check(max_preemptions, || {
+            let drops = Arc::new(AtomicUsize::new(0));
+            let stack = Arc::new(LockFreeStack::new());
+            stack.push(Tracked::new(0, &drops));
+
+            let other = Arc::clone(&stack);
+            let other_drops = Arc::clone(&drops);
+            let child = model::spawn(move || {
+                other.push(Tracked::new(1, &other_drops));
+                other.pop().map(|item| item.value)
+            });
+            let mine = stack.pop().map(|item| item.value);
+            let theirs = child.join();
+
+            // Both pops find an item, and never the same one.
+            let mut values = vec![mine.unwrap(), theirs.unwrap()];
+            values.sort_unstable();
+            assert_eq!(values, vec![0, 1]);
+            assert!(stack.is_empty());
+            drop(stack);
+            assert_eq!(drops.load(Ordering::Relaxed), 2);
+        })
+    }
+
+    // The same as `check_stack`, with a queue.
+    fn check_queue(max_preemptions: usize) -> usize {
+        model::check(max_preemptions, || {
+            let drops = Arc::new(AtomicUsize::new(0));
+            let queue = Arc::new(LockFreeQueue::new());
+            queue.enqueue(Tracked::new(0, &drops));
+
+            let other = Arc::clone(&queue);
+            let other_drops = Arc::clone(&drops);
+            let child = model::spawn(move || {
+                other.enqueue(Tracked::new(1, &other_drops));
+                other.dequeue().map(|item| item.value)
+            });
+            let mine = queue.dequeue().map(|item| item.value);
+            let theirs = child.join();
+
+            // Both dequeues find an item, and never the same one.
+            let mut values = vec![mine.unwrap(), theirs.unwrap()];
+            values.sort_unstable();
+            assert_eq!(values, vec![0, 1]);
+            assert!(queue.is_empty());
+            drop(queue);
+            assert_eq!(drops.load(Ordering::Relaxed), 2);
+        })
+    }
+
+    #[test]
+    fn test_stack_model() {
+        assert!(check_stack(2) > 1);
+    }
+
+    #[test]
+    fn test_queue_model() {
+        assert!(check_queue(2) > 1);
+    }
+
+    // Tries tens of thousands of schedules.
+    // Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn test_model_with_more_preemptions() {
+        println!("stack: {} schedules", check_stack(3));
+        println!("queue: {} schedules", check_queue(3));
+    }
+}
@@ -1,4 +1,16 @@
 pub mod stack;
 pub mod queue;
+pub mod deque;
+pub mod bounded;
+pub mod lock_free;
+pub mod spsc;
+pub mod blocking_queue;
+pub mod persistent;
 pub mod linked_list;
 pub mod binary_search_tree;
+
+mod hazard;
+#[cfg(test)]
+mod model;
+mod ring_buffer;
+mod sync;
@@ -0,0 +1,460 @@
+// A small model checker for the lock-free structures, in the spirit of loom.
+//
+// `check` runs a test over and over, each time under a different
+// interleaving of its threads, until every interleaving has been tried. The
+// threads are real ones, but only one of them runs at a time: before each
+// atomic access, the running thread asks the scheduler which thread goes on,
+// and waits for its turn again. The schedules are explored depth first: a run
+// replays the choices of the previous one up to its last choice that still
+// has an alternative, takes that alternative, then picks the defaults.
+//
+// Like most such checkers, it bounds the number of preemptions, i.e. of
+// switches away from a thread that could have gone on; most concurrency bugs
+// show up with two or three. It also only explores sequentially consistent
+// executions, so it can't find a missing `Acquire` or `Release`.
+//
+// Under `cfg(test)`, the structures use the atomics below instead of those in
+// `std`. Outside of `check`, they behave exactly like the `std` ones.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{self, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+thread_local! {
+    static CURRENT: RefCell<Option<(Arc<Execution>, usize)>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Runnable,
+    Joining(usize),
+    Finished,
+}
+
+// A point where more than one thread could go on. `options[0]` is the thread
+// that was running, if it still can.
+#[derive(Clone, Debug)]
+struct Branch {
+    options: Vec<usize>,
+    taken: usize,
+}
+
+struct State {
+    threads: Vec<Status>,
+    running: usize,
+    // The choices to replay, then the ones made in this run.
+    path: Vec<Branch>,
+    position: usize,
+    preemptions: usize,
+    max_preemptions: usize,
+    failure: Option<String>,
+    done: bool,
+}
+
+struct Execution {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Execution {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Waits until it's the turn of thread `me`. If the run fails meanwhile,
+    // the turn never comes and the thread stays parked.
+    fn wait_turn<'a>(&self, mut state: MutexGuard<'a, State>, me: usize) -> MutexGuard<'a, State> {
+        while state.running != me {
+            state = self.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        state
+    }
+
+    // Lets the scheduler pick the next thread, and waits for the turn of
+    // thread `me` again.
+    fn switch(&self, me: usize, yielding: bool) {
+        let mut state = self.lock();
+        schedule(&mut state, me, yielding);
+        self.changed.notify_all();
+        drop(self.wait_turn(state, me));
+    }
+}
+
+// Picks the thread that goes on after thread `current`. A thread that yields
+// lets the others go first, if there are any, without that counting as a
+// preemption.
+fn schedule(state: &mut State, current: usize, yielding: bool) {
+    let mut runnable: Vec<usize> = (0..state.threads.len())
+        .filter(|&t| state.threads[t] == Status::Runnable)
+        .collect();
+    if yielding && runnable.len() > 1 {
+        runnable.retain(|&t| t != current);
+    }
+    if runnable.is_empty() {
+        if state.threads.iter().any(|&status| status != Status::Finished) {
+            state.failure.get_or_insert_with(|| "deadlock: every thread is joining another".to_string());
+        }
+        state.done = true;
+        return;
+    }
+
+    let can_go_on = runnable.contains(&current);
+    let mut options = Vec::new();
+    if can_go_on {
+        options.push(current);
+    }
+    if !can_go_on || state.preemptions < state.max_preemptions {
+        options.extend(runnable.iter().filter(|&&t| t != current));
+    }
+
+    let mut taken = 0;
+    if options.len() > 1 {
+        if state.position < state.path.len() {
+            let branch = &state.path[state.position];
+            if branch.options != options {
+                state.failure = Some("the test doesn't run the same way under the same schedule".to_string());
+                state.done = true;
+                return;
+            }
+            taken = branch.taken;
+        } else {
+            state.path.push(Branch { options: options.clone(), taken });
+        }
+        state.position += 1;
+    }
+
+    let next = options[taken];
+    if can_go_on && next != current {
+        state.preemptions += 1;
+    }
+    state.running = next;
+}
+
+fn describe(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+    return "a thread panicked".to_string();
+}
+
+fn run_thread<F: FnOnce()>(execution: Arc<Execution>, me: usize, f: F) {
+    drop(execution.wait_turn(execution.lock(), me));
+    CURRENT.with(|current| *current.borrow_mut() = Some((Arc::clone(&execution), me)));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CURRENT.with(|current| *current.borrow_mut() = None);
+
+    let mut state = execution.lock();
+    match result {
+        Ok(()) => {
+            state.threads[me] = Status::Finished;
+            for status in &mut state.threads {
+                if *status == Status::Joining(me) {
+                    *status = Status::Runnable;
+                }
+            }
+            schedule(&mut state, me, false);
+        }
+        Err(payload) => {
+            state.failure.get_or_insert(describe(payload));
+            state.done = true;
+        }
+    }
+    execution.changed.notify_all();
+}
+
+fn current() -> Option<(Arc<Execution>, usize)> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+// Gives the scheduler a chance to switch threads, when running under `check`.
+fn yield_point() {
+    if let Some((execution, me)) = current() {
+        execution.switch(me, false);
+    }
+}
+
+// Lets the other threads go first. Under `check`, a thread that waits for
+// another one by spinning must call this in the loop: otherwise, once it runs
+// out of preemptions, it spins forever.
+pub(crate) fn yield_now() {
+    match current() {
+        Some((execution, me)) => execution.switch(me, true),
+        None => thread::yield_now(),
+    }
+}
+
+// Runs `test` under every schedule of its threads with at most
+// `max_preemptions` preemptions, and returns how many schedules there were.
+//
+// Panics with the schedule if the test panics, or its threads deadlock, under
+// any of them.
+pub(crate) fn check<F>(max_preemptions: usize, test: F) -> usize
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let test = Arc::new(test);
+    let mut path: Vec<Branch> = Vec::new();
+    let mut runs = 0;
+    loop {
+        runs += 1;
+        let execution = Arc::new(Execution {
+            state: Mutex::new(State {
+                threads: vec![Status::Runnable],
+                running: 0,
+                path,
+                position: 0,
+                preemptions: 0,
+                max_preemptions,
+                failure: None,
+                done: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let main = Arc::clone(&execution);
+        let run = Arc::clone(&test);
+        thread::spawn(move || run_thread(main, 0, move || run()));
+
+        let mut state = execution.lock();
+        while !state.done {
+            state = execution.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        if let Some(failure) = state.failure.take() {
+            let schedule: Vec<usize> = state.path[..state.position].iter().map(|b| b.options[b.taken]).collect();
+            panic!("{} (run {}, threads chosen {:?})", failure, runs, schedule);
+        }
+
+        // Backtrack to the last choice with an alternative left.
+        path = std::mem::take(&mut state.path);
+        while let Some(last) = path.last_mut() {
+            if last.taken + 1 < last.options.len() {
+                last.taken += 1;
+                break;
+            }
+            path.pop();
+        }
+        if path.is_empty() {
+            return runs;
+        }
+    }
+}
+
+// A thread spawned by `spawn`, to join before the test returns.
+pub(crate) struct JoinHandle<T> {
+    id: usize,
+    result: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    pub(crate) fn join(self) -> T {
+        let (execution, me) = current().expect("join a model thread outside of `check`");
+        let mut state = execution.lock();
+        if state.threads[self.id] != Status::Finished {
+            state.threads[me] = Status::Joining(self.id);
+            schedule(&mut state, me, false);
+            execution.changed.notify_all();
+            state = execution.wait_turn(state, me);
+        }
+        drop(state);
+        let result = self.result.lock().unwrap_or_else(PoisonError::into_inner).take();
+        result.expect("a finished thread has a result")
+    }
+}
+
+// Spawns a thread of the test run by `check`.
+pub(crate) fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (execution, me) = current().expect("spawn a model thread outside of `check`");
+    let id = {
+        let mut state = execution.lock();
+        state.threads.push(Status::Runnable);
+        state.threads.len() - 1
+    };
+
+    let result = Arc::new(Mutex::new(None));
+    let slot = Arc::clone(&result);
+    let child = Arc::clone(&execution);
+    thread::spawn(move || {
+        run_thread(child, id, move || {
+            let value = f();
+            *slot.lock().unwrap_or_else(PoisonError::into_inner) = Some(value);
+        })
+    });
+
+    // The new thread may well run first.
+    execution.switch(me, false);
+    JoinHandle { id, result }
+}
+
+pub(crate) fn fence(order: Ordering) {
+    yield_point();
+    atomic::fence(order);
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct AtomicBool(atomic::AtomicBool);
+
+impl AtomicBool {
+    pub(crate) const fn new(value: bool) -> Self {
+        Self(atomic::AtomicBool::new(value))
+    }
+
+    pub(crate) fn load(&self, order: Ordering) -> bool {
+        yield_point();
+        self.0.load(order)
+    }
+
+    pub(crate) fn store(&self, value: bool, order: Ordering) {
+        yield_point();
+        self.0.store(value, order);
+    }
+
+    pub(crate) fn compare_exchange(
+        &self,
+        current: bool,
+        new: bool,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<bool, bool> {
+        yield_point();
+        self.0.compare_exchange(current, new, success, failure)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct AtomicUsize(atomic::AtomicUsize);
+
+impl AtomicUsize {
+    pub(crate) const fn new(value: usize) -> Self {
+        Self(atomic::AtomicUsize::new(value))
+    }
+
+    pub(crate) fn load(&self, order: Ordering) -> usize {
+        yield_point();
+        self.0.load(order)
+    }
+
+    pub(crate) fn store(&self, value: usize, order: Ordering) {
+        yield_point();
+        self.0.store(value, order);
+    }
+
+    pub(crate) fn fetch_add(&self, value: usize, order: Ordering) -> usize {
+        yield_point();
+        self.0.fetch_add(value, order)
+    }
+
+    pub(crate) fn fetch_sub(&self, value: usize, order: Ordering) -> usize {
+        yield_point();
+        self.0.fetch_sub(value, order)
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut usize {
+        self.0.get_mut()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct AtomicPtr<T>(atomic::AtomicPtr<T>);
+
+impl<T> AtomicPtr<T> {
+    pub(crate) const fn new(ptr: *mut T) -> Self {
+        Self(atomic::AtomicPtr::new(ptr))
+    }
+
+    pub(crate) fn load(&self, order: Ordering) -> *mut T {
+        yield_point();
+        self.0.load(order)
+    }
+
+    pub(crate) fn store(&self, ptr: *mut T, order: Ordering) {
+        yield_point();
+        self.0.store(ptr, order);
+    }
+
+    pub(crate) fn swap(&self, ptr: *mut T, order: Ordering) -> *mut T {
+        yield_point();
+        self.0.swap(ptr, order)
+    }
+
+    pub(crate) fn compare_exchange(
+        &self,
+        current: *mut T,
+        new: *mut T,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<*mut T, *mut T> {
+        yield_point();
+        self.0.compare_exchange(current, new, success, failure)
+    }
+
+    // Nothing else can touch the pointer, so there's nothing to schedule.
+    pub(crate) fn get_mut(&mut self) -> &mut *mut T {
+        self.0.get_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explores_every_schedule() {
+        // Each thread stores once, so the only choice is which one goes first,
+        // plus when to switch to the new thread and back.
+        let runs = check(2, || {
+            let flag = Arc::new(AtomicUsize::new(0));
+            let other = Arc::clone(&flag);
+            let child = spawn(move || other.store(1, Ordering::SeqCst));
+            flag.store(2, Ordering::SeqCst);
+            child.join();
+        });
+        assert!(runs >= 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "lost an increment")]
+    fn test_finds_lost_update() {
+        check(1, || {
+            let counter = Arc::new(AtomicUsize::new(0));
+            let other = Arc::clone(&counter);
+            let child = spawn(move || {
+                let value = other.load(Ordering::SeqCst);
+                other.store(value + 1, Ordering::SeqCst);
+            });
+            let value = counter.load(Ordering::SeqCst);
+            counter.store(value + 1, Ordering::SeqCst);
+            child.join();
+            assert_eq!(counter.load(Ordering::SeqCst), 2, "lost an increment");
+        });
+    }
+
+    #[test]
+    fn test_atomic_increments_are_never_lost() {
+        let runs = check(3, || {
+            let counter = Arc::new(AtomicUsize::new(0));
+            let other = Arc::clone(&counter);
+            let child = spawn(move || other.fetch_add(1, Ordering::SeqCst));
+            counter.fetch_add(1, Ordering::SeqCst);
+            child.join();
+            assert_eq!(counter.load(Ordering::SeqCst), 2);
+        });
+        assert!(runs > 1);
+    }
+
+    #[test]
+    fn test_outside_of_check() {
+        let flag = AtomicBool::new(false);
+        assert_eq!(flag.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire), Ok(false));
+        assert!(flag.load(Ordering::Acquire));
+    }
+}
@@ -0,0 +1,653 @@
+use std::cell::OnceCell;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+
+// How persistent structures share their nodes between versions: `RcPtr` for
+// use on one thread, `ArcPtr` for versions shared between threads.
+pub trait SharedPtr: 'static {
+    type Ptr<T>: Clone + Deref<Target = T>;
+    // A cell written at most once, for lazily computed values.
+    type Once<T>;
+
+    fn new<T>(value: T) -> Self::Ptr<T>;
+
+    // Returns the value if nothing else shares it.
+    fn try_unwrap<T>(ptr: Self::Ptr<T>) -> Result<T, Self::Ptr<T>>;
+
+    fn once<T>() -> Self::Once<T>;
+
+    fn once_with<T>(value: T) -> Self::Once<T>;
+
+    fn get_or_init<T>(cell: &Self::Once<T>, init: impl FnOnce() -> T) -> &T;
+
+    fn take<T>(cell: &mut Self::Once<T>) -> Option<T>;
+}
+
+// Shares nodes through `Rc`, so the structures stay on one thread.
+#[derive(Clone, Copy, Debug)]
+pub enum RcPtr {}
+
+impl SharedPtr for RcPtr {
+    type Ptr<T> = Rc<T>;
+    type Once<T> = OnceCell<T>;
+
+    fn new<T>(value: T) -> Rc<T> {
+        Rc::new(value)
+    }
+
+    fn try_unwrap<T>(ptr: Rc<T>) -> Result<T, Rc<T>> {
+        Rc::try_unwrap(ptr)
+    }
+
+    fn once<T>() -> OnceCell<T> {
+        OnceCell::new()
+    }
+
+    fn once_with<T>(value: T) -> OnceCell<T> {
+        OnceCell::from(value)
+    }
+
+    fn get_or_init<T>(cell: &OnceCell<T>, init: impl FnOnce() -> T) -> &T {
+        cell.get_or_init(init)
+    }
+
+    fn take<T>(cell: &mut OnceCell<T>) -> Option<T> {
+        cell.take()
+    }
+}
+
+// Shares nodes through `Arc`, so the structures are `Send` and `Sync` when
+// their items are.
+#[derive(Clone, Copy, Debug)]
+pub enum ArcPtr {}
+
+impl SharedPtr for ArcPtr {
+    type Ptr<T> = Arc<T>;
+    type Once<T> = OnceLock<T>;
+
+    fn new<T>(value: T) -> Arc<T> {
+        Arc::new(value)
+    }
+
+    fn try_unwrap<T>(ptr: Arc<T>) -> Result<T, Arc<T>> {
+        Arc::try_unwrap(ptr)
+    }
+
+    fn once<T>() -> OnceLock<T> {
+        OnceLock::new()
+    }
+
+    fn once_with<T>(value: T) -> OnceLock<T> {
+        OnceLock::from(value)
+    }
+
+    fn get_or_init<T>(cell: &OnceLock<T>, init: impl FnOnce() -> T) -> &T {
+        cell.get_or_init(init)
+    }
+
+    fn take<T>(cell: &mut OnceLock<T>) -> Option<T> {
+        cell.take()
+    }
+}
+
+pub type SyncPersistentStack<T> = PersistentStack<T, ArcPtr>;
+pub type SyncPersistentQueue<T> = PersistentQueue<T, ArcPtr>;
+
+struct StackNode<T, P: SharedPtr> {
+    item: T,
+    next: Option<P::Ptr<StackNode<T, P>>>,
+}
+
+// An immutable LIFO stack: a linked list whose nodes are shared by every
+// version that contains them. Pushing and popping return a new version in
+// O(1) time, and leave the old one as it was.
+pub struct PersistentStack<T, P: SharedPtr = RcPtr> {
+    head: Option<P::Ptr<StackNode<T, P>>>,
+    len: usize,
+}
+
+impl<T, P: SharedPtr> PersistentStack<T, P> {
+    pub fn new() -> Self {
+        Self { head: None, len: 0 }
+    }
+
+    // Returns the stack with `item` pushed on top.
+    pub fn push(&self, item: T) -> Self {
+        Self {
+            head: Some(P::new(StackNode {
+                item,
+                next: self.head.clone(),
+            })),
+            len: self.len + 1,
+        }
+    }
+
+    // Returns the stack without its top item, or `None` if it's empty.
+    pub fn pop(&self) -> Option<Self> {
+        let head = self.head.as_ref()?;
+        return Some(Self {
+            head: head.next.clone(),
+            len: self.len - 1,
+        });
+    }
+
+    // Returns the item on top of the stack.
+    pub fn peek(&self) -> Option<&T> {
+        return self.head.as_ref().map(|node| &node.item);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    // Returns an iterator over the items, from the top to the bottom.
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        Iter {
+            next: self.head.as_deref(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T, P: SharedPtr> Clone for PersistentStack<T, P> {
+    // Shares every node, in O(1) time.
+    fn clone(&self) -> Self {
+        Self {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T, P: SharedPtr> Drop for PersistentStack<T, P> {
+    // Drops the nodes no other version shares one by one, instead of
+    // recursively, which would overflow the call stack for long stacks.
+    fn drop(&mut self) {
+        let mut next = self.head.take();
+        while let Some(node) = next {
+            match P::try_unwrap(node) {
+                Ok(mut node) => next = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+impl<T, P: SharedPtr> Default for PersistentStack<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, P: SharedPtr> FromIterator<T> for PersistentStack<T, P> {
+    // Pushes the items in order, so the last one ends up on top.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = Self::new();
+        for item in iter {
+            stack = stack.push(item);
+        }
+        stack
+    }
+}
+
+impl<T: PartialEq, P: SharedPtr> PartialEq for PersistentStack<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, P: SharedPtr> Eq for PersistentStack<T, P> {}
+
+impl<T: fmt::Debug, P: SharedPtr> fmt::Debug for PersistentStack<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, P: SharedPtr> IntoIterator for &'a PersistentStack<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
+
+    fn into_iter(self) -> Iter<'a, T, P> {
+        self.iter()
+    }
+}
+
+// Iterator over the items of a persistent stack, from the top to the bottom,
+// created by `PersistentStack::iter`.
+pub struct Iter<'a, T, P: SharedPtr> {
+    next: Option<&'a StackNode<T, P>>,
+    len: usize,
+}
+
+impl<'a, T, P: SharedPtr> Iterator for Iter<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.next?;
+        self.next = node.next.as_deref();
+        self.len -= 1;
+        return Some(&node.item);
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, P: SharedPtr> ExactSizeIterator for Iter<'_, T, P> {}
+
+impl<T, P: SharedPtr> FusedIterator for Iter<'_, T, P> {}
+
+impl<T, P: SharedPtr> Clone for Iter<'_, T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            next: self.next,
+            len: self.len,
+        }
+    }
+}
+
+// A lazy list: each cell is computed at most once, when first needed, then
+// shared by every version that reaches it.
+type Stream<T, P> = <P as SharedPtr>::Ptr<Cell<T, P>>;
+
+// A computed cell: the first item and the rest of the stream, or `None` at
+// the end.
+type Value<T, P> = Option<(<P as SharedPtr>::Ptr<T>, Stream<T, P>)>;
+
+struct Cell<T, P: SharedPtr> {
+    value: P::Once<Value<T, P>>,
+    // How to compute the value, until it's needed.
+    rotation: Option<Rotation<T, P>>,
+}
+
+// The suspended `rotate(front, rear, acc)`: `front ++ reverse(rear) ++ acc`,
+// for a rear one item longer than the front.
+struct Rotation<T, P: SharedPtr> {
+    front: Stream<T, P>,
+    rear: PersistentStack<P::Ptr<T>, P>,
+    acc: Stream<T, P>,
+}
+
+fn empty<T, P: SharedPtr>() -> Stream<T, P> {
+    P::new(Cell {
+        value: P::once_with(None),
+        rotation: None,
+    })
+}
+
+fn cons<T, P: SharedPtr>(item: P::Ptr<T>, rest: Stream<T, P>) -> Stream<T, P> {
+    P::new(Cell {
+        value: P::once_with(Some((item, rest))),
+        rotation: None,
+    })
+}
+
+fn rotate<T, P: SharedPtr>(
+    front: Stream<T, P>,
+    rear: PersistentStack<P::Ptr<T>, P>,
+    acc: Stream<T, P>,
+) -> Stream<T, P> {
+    P::new(Cell {
+        value: P::once(),
+        rotation: Some(Rotation { front, rear, acc }),
+    })
+}
+
+// Returns the first item and the rest of a stream, computing them if needed.
+// A rotation only takes one step, so this takes O(1) time, as long as the
+// first cell of its front was computed already; the queue makes sure of it.
+fn force<'a, T: 'a, P: SharedPtr>(stream: &'a Stream<T, P>) -> &'a Value<T, P> {
+    P::get_or_init(&stream.value, || {
+        let Rotation { front, rear, acc } = stream.rotation.as_ref().unwrap();
+        let last = rear.peek().unwrap().clone();
+        match force::<T, P>(front) {
+            None => Some((last, acc.clone())),
+            Some((first, rest)) => {
+                let rest = rotate::<T, P>(rest.clone(), rear.pop().unwrap(), cons::<T, P>(last, acc.clone()));
+                Some((first.clone(), rest))
+            }
+        }
+    })
+}
+
+impl<T, P: SharedPtr> Drop for Cell<T, P> {
+    // Drops the cells no other stream shares one by one, as for the stack.
+    fn drop(&mut self) {
+        let mut pending: Vec<Stream<T, P>> = Vec::new();
+        let release = |cell: &mut Cell<T, P>, pending: &mut Vec<Stream<T, P>>| {
+            if let Some(Some((_, rest))) = P::take(&mut cell.value) {
+                pending.push(rest);
+            }
+            if let Some(rotation) = cell.rotation.take() {
+                pending.push(rotation.front);
+                pending.push(rotation.acc);
+            }
+        };
+
+        release(self, &mut pending);
+        while let Some(stream) = pending.pop() {
+            if let Ok(mut cell) = P::try_unwrap(stream) {
+                release(&mut cell, &mut pending);
+            }
+        }
+    }
+}
+
+// An immutable FIFO queue (Okasaki's real-time queue). Enqueuing and
+// dequeuing return a new version in O(1) worst-case time, and leave the old
+// one as it was.
+//
+// Items are enqueued on a rear stack. The front is a lazy stream, and once
+// the rear outgrows it, it's replaced with the lazy `front ++ reverse(rear)`.
+// Nothing is computed right then: each operation computes one more cell of
+// the stream, through `schedule`, so every cell is ready by the time it's
+// dequeued, and no dequeue has to reverse a whole stack.
+pub struct PersistentQueue<T, P: SharedPtr = RcPtr> {
+    front: Stream<T, P>,
+    rear: PersistentStack<P::Ptr<T>, P>,
+    // The cells of `front` not computed yet, as many as the front has more
+    // items than the rear.
+    schedule: Stream<T, P>,
+    len: usize,
+}
+
+impl<T, P: SharedPtr> PersistentQueue<T, P> {
+    pub fn new() -> Self {
+        let front = empty::<T, P>();
+        Self {
+            schedule: front.clone(),
+            front,
+            rear: PersistentStack::new(),
+            len: 0,
+        }
+    }
+
+    // Computes the next cell of the schedule, or starts a rotation if there's
+    // none left, which happens when the rear gets one item longer than the
+    // front.
+    fn exec(front: Stream<T, P>, rear: PersistentStack<P::Ptr<T>, P>, schedule: &Stream<T, P>, len: usize) -> Self {
+        match force::<T, P>(schedule) {
+            Some((_, schedule)) => Self {
+                front,
+                rear,
+                schedule: schedule.clone(),
+                len,
+            },
+            None => {
+                let front = rotate::<T, P>(front, rear, empty::<T, P>());
+                Self {
+                    schedule: front.clone(),
+                    front,
+                    rear: PersistentStack::new(),
+                    len,
+                }
+            }
+        }
+    }
+
+    // Returns the queue with `item` enqueued at the back.
+    pub fn enqueue(&self, item: T) -> Self {
+        Self::exec(self.front.clone(), self.rear.push(P::new(item)), &self.schedule, self.len + 1)
+    }
+
+    // Returns the queue without its front item, or `None` if it's empty.
+    pub fn dequeue(&self) -> Option<Self> {
+        let (_, rest) = force::<T, P>(&self.front).as_ref()?;
+        return Some(Self::exec(rest.clone(), self.rear.clone(), &self.schedule, self.len - 1));
+    }
+
+    // Returns the item at the front of the queue, the next one to be
+    // dequeued.
+    pub fn peek_front(&self) -> Option<&T> {
+        return force::<T, P>(&self.front).as_ref().map(|(item, _)| &**item);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    // Returns an iterator over the items, from the front to the back. It
+    // collects references to the rear items first, in O(n) time and space.
+    pub fn iter(&self) -> QueueIter<'_, T, P> {
+        QueueIter {
+            front: Some(&self.front),
+            rear: self.rear.iter().map(|item| &**item).collect(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T, P: SharedPtr> Clone for PersistentQueue<T, P> {
+    // Shares every node, in O(1) time.
+    fn clone(&self) -> Self {
+        Self {
+            front: self.front.clone(),
+            rear: self.rear.clone(),
+            schedule: self.schedule.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T, P: SharedPtr> Default for PersistentQueue<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, P: SharedPtr> FromIterator<T> for PersistentQueue<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        for item in iter {
+            queue = queue.enqueue(item);
+        }
+        queue
+    }
+}
+
+impl<T: PartialEq, P: SharedPtr> PartialEq for PersistentQueue<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, P: SharedPtr> Eq for PersistentQueue<T, P> {}
+
+impl<T: fmt::Debug, P: SharedPtr> fmt::Debug for PersistentQueue<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T, P: SharedPtr> IntoIterator for &'a PersistentQueue<T, P> {
+    type Item = &'a T;
+    type IntoIter = QueueIter<'a, T, P>;
+
+    fn into_iter(self) -> QueueIter<'a, T, P> {
+        self.iter()
+    }
+}
+
+// Iterator over the items of a persistent queue, from the front to the back,
+// created by `PersistentQueue::iter`.
+pub struct QueueIter<'a, T, P: SharedPtr> {
+    front: Option<&'a Stream<T, P>>,
+    // The rear items, from the back to the front.
+    rear: Vec<&'a T>,
+    len: usize,
+}
+
+impl<'a, T, P: SharedPtr> Iterator for QueueIter<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if let Some(front) = self.front {
+            // Computing the cells the queue would compute later anyway doesn't
+            // change its complexity.
+            match force::<T, P>(front) {
+                Some((item, rest)) => {
+                    self.front = Some(rest);
+                    self.len -= 1;
+                    return Some(&**item);
+                }
+                None => self.front = None,
+            }
+        }
+        let item = self.rear.pop()?;
+        self.len -= 1;
+        return Some(item);
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, P: SharedPtr> ExactSizeIterator for QueueIter<'_, T, P> {}
+
+impl<T, P: SharedPtr> FusedIterator for QueueIter<'_, T, P> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use std::collections::VecDeque;
+    use std::thread;
+
+    #[test]
+    fn test_stack_versions() {
+        let empty: PersistentStack<i32> = PersistentStack::new();
+        let one = empty.push(1);
+        let two = one.push(2);
+        let other = one.push(3);
+
+        assert_eq!(empty.peek(), None);
+        assert_eq!(one.iter().copied().collect::<Vec<i32>>(), vec![1]);
+        assert_eq!(two.iter().copied().collect::<Vec<i32>>(), vec![2, 1]);
+        assert_eq!(other.iter().copied().collect::<Vec<i32>>(), vec![3, 1]);
+
+        let popped = two.pop().unwrap();
+        assert_eq!(popped, one);
+        assert_eq!(popped.peek(), Some(&1));
+        assert_eq!(two.len(), 2);
+        assert!(empty.pop().is_none());
+        assert_eq!(format!("{:?}", two), "[2, 1]");
+    }
+
+    #[test]
+    fn test_queue_versions() {
+        let empty: PersistentQueue<i32> = PersistentQueue::new();
+        let three: PersistentQueue<i32> = (1..=3).collect();
+        let four = three.enqueue(4);
+        let other = three.enqueue(5);
+        let dequeued = four.dequeue().unwrap();
+
+        assert_eq!(empty.dequeue(), None);
+        assert_eq!(three.iter().copied().collect::<Vec<i32>>(), vec![1, 2, 3]);
+        assert_eq!(four.iter().copied().collect::<Vec<i32>>(), vec![1, 2, 3, 4]);
+        assert_eq!(other.iter().copied().collect::<Vec<i32>>(), vec![1, 2, 3, 5]);
+        assert_eq!(dequeued.iter().copied().collect::<Vec<i32>>(), vec![2, 3, 4]);
+        assert_eq!(dequeued.peek_front(), Some(&2));
+        assert_eq!(four.peek_front(), Some(&1));
+        assert_eq!(dequeued.len(), 3);
+    }
+
+    #[test]
+    fn test_queue_agrees_with_vec_deque() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+
+        // Keep some old versions around, and check they never change.
+        let mut queue: PersistentQueue<u64> = PersistentQueue::new();
+        let mut expected = VecDeque::new();
+        let mut snapshots = Vec::new();
+        for step in 0..3000 {
+            if rng.below(5) < 3 {
+                let item = rng.below(1000);
+                queue = queue.enqueue(item);
+                expected.push_back(item);
+            } else if let Some(rest) = queue.dequeue() {
+                assert_eq!(queue.peek_front(), expected.front());
+                queue = rest;
+                expected.pop_front();
+            }
+            assert_eq!(queue.len(), expected.len());
+            assert_eq!(queue.peek_front(), expected.front());
+
+            if step % 150 == 0 {
+                snapshots.push((queue.clone(), expected.clone()));
+            }
+            // Go back to an old version now and then.
+            if step % 700 == 699 {
+                let (old, old_expected) = snapshots[rng.below(snapshots.len() as u64) as usize].clone();
+                queue = old;
+                expected = old_expected;
+            }
+        }
+
+        for (queue, expected) in &snapshots {
+            assert!(queue.iter().eq(expected.iter()));
+        }
+    }
+
+    #[test]
+    fn test_long_versions_drop() {
+        let stack: PersistentStack<u32> = (0..1_000_000).collect();
+        let shorter = stack.pop().unwrap();
+        drop(stack);
+        assert_eq!(shorter.len(), 999_999);
+        drop(shorter);
+
+        // Half the items go through a rotation, the other half stay in the rear.
+        let mut queue: PersistentQueue<u32> = (0..1_000_000).collect();
+        for _ in 0..10 {
+            queue = queue.dequeue().unwrap();
+        }
+        assert_eq!(queue.peek_front(), Some(&10));
+        drop(queue);
+    }
+
+    #[test]
+    fn test_sync_versions() {
+        let stack: SyncPersistentStack<u32> = (0..100).collect();
+        let queue: SyncPersistentQueue<u32> = (0..100).collect();
+
+        let sums: Vec<u32> = thread::scope(|s| {
+            let workers: Vec<_> = (0..4)
+                .map(|t| {
+                    let (mut stack, mut queue) = (stack.clone(), queue.clone());
+                    s.spawn(move || {
+                        for _ in 0..t * 10 {
+                            stack = stack.pop().unwrap();
+                            queue = queue.dequeue().unwrap();
+                        }
+                        stack.iter().sum::<u32>() + queue.iter().sum::<u32>()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+
+        // Each thread dropped `t * 10` items from each end.
+        let total: u32 = (0..100).sum();
+        for (t, sum) in sums.into_iter().enumerate() {
+            let n = t as u32 * 10;
+            let popped: u32 = (100 - n..100).sum();
+            let dequeued: u32 = (0..n).sum();
+            assert_eq!(sum, 2 * total - popped - dequeued);
+        }
+        assert_eq!(stack.len() + queue.len(), 200);
+    }
+}
@@ -1,73 +1,246 @@
-use core::fmt::Debug;
+use std::fmt;
+use std::ops::Add;
 
-#[derive(Debug)]
+use super::ring_buffer::RingBuffer;
+use super::stack::Stack;
+pub use super::ring_buffer::{Drain, IntoIter, Iter};
+
+const DEFAULT_CAPACITY: usize = 5;
+
+// A FIFO queue over a growable ring buffer, shared with `Deque`.
+//
+// Dequeued items are moved out of the buffer, so `T` needs no bounds, and the
+// buffer doubles when it's full, so enqueues take amortized O(1) time.
+#[derive(Clone, Debug)]
 pub struct Queue<T> {
-    head: usize,
-    tail: usize,
-    data: Vec<Option<T>>,
+    data: RingBuffer<T>,
 }
 
-impl<T: Clone + Debug> Queue<T> {
+impl<T> Queue<T> {
     pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    // Creates an empty queue that can hold `capacity` items before growing.
+    pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            head: 0,
-            tail: 0,
-            data: vec![None; 5],
+            data: RingBuffer::with_capacity(capacity),
         }
     }
 
     pub fn enqueue(&mut self, item: T) {
-        if self.is_full() {
-            self.increase_array_size();
+        self.data.push_back(item);
+    }
+
+    pub fn dequeue(&mut self) -> Option<T> {
+        return self.data.pop_front();
+    }
+
+    // Returns the item at the front of the queue, the next one to be
+    // dequeued, without removing it.
+    pub fn peek_front(&self) -> Option<&T> {
+        return self.data.get(0);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.data.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.data.len() == 0;
+    }
+
+    // Returns how many items the queue can hold before growing.
+    pub fn capacity(&self) -> usize {
+        return self.data.capacity();
+    }
+
+    // Returns an iterator over the items, from the front to the back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.data.iter()
+    }
+
+    // Returns an iterator that dequeues every item. The items it doesn't get
+    // to are dropped with it, so the queue ends up empty either way.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        self.data.drain()
+    }
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<T> for Queue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        queue.extend(iter);
+        queue
+    }
+}
+
+impl<T> Extend<T> for Queue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.enqueue(item);
+        }
+    }
+}
+
+impl<T> IntoIterator for Queue<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    // Consumes the queue, yielding the items from the front to the back.
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter::new(self.data)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Queue<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+// A queue that keeps the aggregate of its items under an associative
+// operation, such as the maximum over a sliding window, answering it in O(1).
+//
+// It uses two stacks of items paired with partial aggregates. New items go on
+// the back stack, whose aggregates run from its oldest item up to each one.
+// When the front stack runs out, the back stack is moved onto it, reversing
+// its order, and its aggregates then run from each item to the newest one on
+// that stack. The front stack is refilled as soon as it runs out, so it's only
+// empty when the whole queue is, and the oldest item is always on its top.
+// The aggregate of the whole queue combines the two tops. Every item moves
+// once, so each operation takes amortized O(1) time, and the operation is
+// applied from front to back, so it doesn't need to commute.
+#[derive(Clone)]
+pub struct AggregateQueue<T, F> {
+    front: Stack<(T, T)>,
+    back: Stack<(T, T)>,
+    op: F,
+    // Cached aggregate of both stacks, kept so `aggregate` can lend it out.
+    total: Option<T>,
+}
+
+impl<T: Clone, F: Fn(&T, &T) -> T> AggregateQueue<T, F> {
+    pub fn new(op: F) -> Self {
+        Self {
+            front: Stack::new(),
+            back: Stack::new(),
+            op,
+            total: None,
         }
+    }
 
-        self.data[self.tail] = Some(item);
-        self.tail = (self.tail + 1) % self.data.len();
+    pub fn enqueue(&mut self, item: T) {
+        self.total = Some(match self.total.take() {
+            Some(total) => (self.op)(&total, &item),
+            None => item.clone(),
+        });
+        if self.front.is_empty() {
+            let aggregate = item.clone();
+            self.front.push((item, aggregate));
+            return;
+        }
+        let aggregate = match self.back.peek() {
+            Some((_, before)) => (self.op)(before, &item),
+            None => item.clone(),
+        };
+        self.back.push((item, aggregate));
     }
 
     pub fn dequeue(&mut self) -> Option<T> {
-        if self.is_empty() {
-            return None;
+        let (item, _) = self.front.pop()?;
+        if self.front.is_empty() {
+            while let Some((item, _)) = self.back.pop() {
+                let aggregate = match self.front.peek() {
+                    Some((_, after)) => (self.op)(&item, after),
+                    None => item.clone(),
+                };
+                self.front.push((item, aggregate));
+            }
         }
+        self.total = match (self.front.peek(), self.back.peek()) {
+            (Some((_, front)), Some((_, back))) => Some((self.op)(front, back)),
+            (Some((_, only)), None) | (None, Some((_, only))) => Some(only.clone()),
+            (None, None) => None,
+        };
+        return Some(item);
+    }
 
-        let item = self.data[self.head].clone();
-        self.head = (self.head + 1) % self.data.len();
+    pub fn peek_front(&self) -> Option<&T> {
+        return self.front.peek().map(|(item, _)| item);
+    }
 
-        return item;
+    // Returns the aggregate of every item, from the front to the back, or
+    // `None` if the queue is empty.
+    pub fn aggregate(&self) -> Option<&T> {
+        return self.total.as_ref();
     }
 
-    fn increase_array_size(&mut self) {
-        let current_capacity = self.data.len();
-        let new_capacity = current_capacity + 5;
-        let mut new_data = vec![None; new_capacity];
+    pub fn len(&self) -> usize {
+        return self.front.len() + self.back.len();
+    }
 
-        for i in 0..current_capacity {
-            new_data[i] = self.data[(self.head + i) % current_capacity].take();
-        }
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+}
+
+impl<T: Clone + Ord> AggregateQueue<T, fn(&T, &T) -> T> {
+    // Creates a queue that keeps the minimum of its items.
+    pub fn min() -> Self {
+        Self::new(|a, b| std::cmp::min(a, b).clone())
+    }
 
-        self.head = 0;
-        self.tail = self.data.len() - 1;
-        self.data = new_data;
+    // Creates a queue that keeps the maximum of its items.
+    pub fn max() -> Self {
+        Self::new(|a, b| std::cmp::max(a, b).clone())
     }
+}
 
-    fn is_full(&self) -> bool {
-        return (self.tail + 1) % self.data.len() == self.head;
+impl<T: Clone + Add<Output = T>> AggregateQueue<T, fn(&T, &T) -> T> {
+    // Creates a queue that keeps the sum of its items.
+    pub fn sum() -> Self {
+        Self::new(|a, b| a.clone() + b.clone())
     }
+}
 
-    fn is_empty(&self) -> bool {
-        return self.head == self.tail;
+impl<T: Clone, F: Fn(&T, &T) -> T> Extend<T> for AggregateQueue<T, F> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.enqueue(item);
+        }
     }
+}
 
+impl<T: fmt::Debug, F> fmt::Debug for AggregateQueue<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items = self.front.iter().chain(self.back.iter().collect::<Vec<_>>().into_iter().rev());
+        f.debug_struct("AggregateQueue")
+            .field("items", &items.map(|(item, _)| item).collect::<Vec<&T>>())
+            .field("aggregate", &self.total)
+            .finish()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_rng::XorShift;
+    use std::collections::VecDeque;
 
     #[test]
     fn test_create() {
         let queue: Queue<i32> = Queue::new();
-        assert_eq!(queue.data.len(), 5);
+        assert_eq!(queue.capacity(), 5);
     }
 
     #[test]
@@ -103,6 +276,137 @@ mod tests {
         queue.enqueue(4);
         queue.enqueue(5);
 
-        assert_eq!(queue.data.len(), 10);
+        // every slot is used before growing
+        assert_eq!(queue.capacity(), 5);
+
+        queue.enqueue(6);
+
+        assert_eq!(queue.capacity(), 10);
+    }
+
+    #[test]
+    fn test_grow_while_wrapped_around() {
+        let mut queue = Queue::new();
+        queue.extend(0..4);
+        assert_eq!(queue.dequeue(), Some(0));
+        assert_eq!(queue.dequeue(), Some(1));
+
+        // the back wraps around to the first slots, then the queue grows
+        queue.extend(4..10);
+        assert_eq!(queue.peek_front(), Some(&2));
+        assert_eq!(queue.len(), 8);
+        assert_eq!(queue.into_iter().collect::<Vec<i32>>(), (2..10).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut queue: Queue<i32> = (0..7).collect();
+        queue.dequeue();
+        queue.enqueue(7);
+
+        assert_eq!(queue.iter().copied().collect::<Vec<i32>>(), (1..8).collect::<Vec<i32>>());
+        assert_eq!(queue.iter().next_back(), Some(&7));
+        assert_eq!(queue.iter().len(), 7);
+        assert_eq!((&queue).into_iter().sum::<i32>(), 28);
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut queue: Queue<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(queue.drain().next().as_deref(), Some("a"));
+        assert!(queue.is_empty());
+
+        queue.enqueue("d".to_string());
+        assert_eq!(queue.drain().collect::<Vec<String>>(), vec!["d"]);
+        assert_eq!(queue.peek_front(), None);
+    }
+
+    #[test]
+    fn test_agrees_with_vec_deque() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+
+        for capacity in [0, 1, 2, 5] {
+            let mut queue = Queue::with_capacity(capacity);
+            let mut expected = VecDeque::new();
+
+            for step in 0..5000 {
+                // mostly enqueues at first, mostly dequeues later
+                let enqueue_odds = if step < 2500 { 60 } else { 40 };
+                if rng.below(100) < enqueue_odds {
+                    let item = rng.below(1000);
+                    queue.enqueue(item);
+                    expected.push_back(item);
+                } else {
+                    assert_eq!(queue.dequeue(), expected.pop_front());
+                }
+
+                assert_eq!(queue.len(), expected.len());
+                assert_eq!(queue.peek_front(), expected.front());
+                if step % 97 == 0 {
+                    assert!(queue.iter().eq(expected.iter()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_aggregate_queue() {
+        let mut max = AggregateQueue::max();
+        let mut sum = AggregateQueue::sum();
+        assert_eq!(max.aggregate(), None);
+        assert_eq!(max.dequeue(), None);
+
+        max.extend([4, 9, 2, 7]);
+        sum.extend([4, 9, 2, 7]);
+        assert_eq!((max.aggregate(), sum.aggregate()), (Some(&9), Some(&22)));
+        assert_eq!(max.peek_front(), Some(&4));
+
+        assert_eq!(max.dequeue(), Some(4));
+        assert_eq!(max.dequeue(), Some(9));
+        assert_eq!(max.aggregate(), Some(&7));
+        max.enqueue(1);
+        assert_eq!(max.aggregate(), Some(&7));
+        assert_eq!(max.peek_front(), Some(&2));
+        assert_eq!(max.len(), 3);
+        assert_eq!(format!("{:?}", max), "AggregateQueue { items: [2, 7, 1], aggregate: Some(7) }");
+    }
+
+    #[test]
+    fn test_aggregate_queue_order() {
+        // Concatenation isn't commutative: the aggregate reads front to back,
+        // even while the items are split across both stacks.
+        let mut queue = AggregateQueue::new(|a: &String, b: &String| format!("{}{}", a, b));
+        queue.extend(["a", "b", "c"].iter().map(|s| s.to_string()));
+        assert_eq!(queue.dequeue().as_deref(), Some("a"));
+        queue.extend(["d", "e"].iter().map(|s| s.to_string()));
+        assert_eq!(queue.aggregate().map(String::as_str), Some("bcde"));
+        assert_eq!(queue.dequeue().as_deref(), Some("b"));
+        assert_eq!(queue.aggregate().map(String::as_str), Some("cde"));
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let values: Vec<i64> = XorShift::new(0x9e37_79b9_7f4a_7c15).values(1000, 500);
+
+        for window in [1, 2, 7, 64] {
+            let mut min = AggregateQueue::min();
+            let mut max = AggregateQueue::max();
+            let mut sum = AggregateQueue::sum();
+            for (i, &value) in values.iter().enumerate() {
+                min.enqueue(value);
+                max.enqueue(value);
+                sum.enqueue(value);
+                if min.len() > window {
+                    assert_eq!(min.dequeue(), Some(values[i - window]));
+                    max.dequeue();
+                    sum.dequeue();
+                }
+                let slice = &values[(i + 1).saturating_sub(window)..=i];
+                assert_eq!(min.peek_front(), slice.first());
+                assert_eq!(min.aggregate(), slice.iter().min());
+                assert_eq!(max.aggregate(), slice.iter().max());
+                assert_eq!(sum.aggregate().copied(), Some(slice.iter().sum()));
+            }
+        }
     }
 }
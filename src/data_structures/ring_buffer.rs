@@ -0,0 +1,458 @@
+// The growable ring buffer behind `Queue` and `Deque`.
+//
+// The items are the `len` slots starting at `head` and wrapping around the end
+// of `buf`, from the front to the back. Only those slots are initialized. The
+// buffer doubles when it's full, so pushes take amortized O(1) time.
+
+use std::fmt;
+use std::iter::FusedIterator;
+use std::mem::MaybeUninit;
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
+use std::slice;
+
+pub(crate) struct RingBuffer<T> {
+    buf: Vec<MaybeUninit<T>>,
+    head: usize,
+    len: usize,
+}
+
+impl<T> RingBuffer<T> {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        RingBuffer {
+            buf: uninit_buffer(capacity),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub(crate) fn push_back(&mut self, item: T) {
+        if self.len == self.capacity() {
+            self.grow();
+        }
+        let slot = self.slot(self.len);
+        self.buf[slot].write(item);
+        self.len += 1;
+    }
+
+    pub(crate) fn push_front(&mut self, item: T) {
+        if self.len == self.capacity() {
+            self.grow();
+        }
+        self.head = self.slot(self.capacity() - 1);
+        self.buf[self.head].write(item);
+        self.len += 1;
+    }
+
+    pub(crate) fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: the slot at `head` holds the first item, and it's left out
+        // of the items right away, so it's never read again.
+        let item = unsafe { self.buf[self.head].assume_init_read() };
+        self.head = self.slot(1);
+        self.len -= 1;
+        Some(item)
+    }
+
+    pub(crate) fn pop_back(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let slot = self.slot(self.len);
+        // SAFETY: the slot holds the last item, which was just left out.
+        Some(unsafe { self.buf[slot].assume_init_read() })
+    }
+
+    // Returns the `i`-th item from the front.
+    pub(crate) fn get(&self, i: usize) -> Option<&T> {
+        if i >= self.len {
+            return None;
+        }
+        // SAFETY: the first `len` items are initialized.
+        Some(unsafe { self.buf[self.slot(i)].assume_init_ref() })
+    }
+
+    pub(crate) fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        if i >= self.len {
+            return None;
+        }
+        let slot = self.slot(i);
+        // SAFETY: the first `len` items are initialized.
+        Some(unsafe { self.buf[slot].assume_init_mut() })
+    }
+
+    // Returns the items as two slices, the second one holding the items that
+    // wrapped around to the beginning of the buffer.
+    pub(crate) fn as_slices(&self) -> (&[T], &[T]) {
+        let (first, second) = self.ranges();
+        // SAFETY: both ranges hold initialized items, and `MaybeUninit<T>`
+        // has the layout of `T`.
+        unsafe {
+            let base = self.buf.as_ptr() as *const T;
+            (
+                slice::from_raw_parts(base.add(first.0), first.1),
+                slice::from_raw_parts(base.add(second.0), second.1),
+            )
+        }
+    }
+
+    pub(crate) fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (first, second) = self.ranges();
+        // SAFETY: as in `as_slices`, and the two ranges don't overlap.
+        unsafe {
+            let base = self.buf.as_mut_ptr() as *mut T;
+            (
+                slice::from_raw_parts_mut(base.add(first.0), first.1),
+                slice::from_raw_parts_mut(base.add(second.0), second.1),
+            )
+        }
+    }
+
+    // Moves the items so they don't wrap around, and returns them.
+    // Time complexity: O(capacity) if they wrapped around, O(1) otherwise.
+    pub(crate) fn make_contiguous(&mut self) -> &mut [T] {
+        if self.head + self.len > self.capacity() {
+            // Rotating the whole buffer moves the uninitialized slots along
+            // with the items, which is fine since they're only bits.
+            self.buf.rotate_left(self.head);
+            self.head = 0;
+        }
+        self.as_mut_slices().0
+    }
+
+    // Moves the first `n` items to the back, keeping their order.
+    // Time complexity: O(min(n, len - n)).
+    pub(crate) fn rotate_left(&mut self, n: usize) {
+        assert!(n <= self.len, "can't rotate {} items of {}", n, self.len);
+        if n <= self.len - n {
+            for _ in 0..n {
+                let item = self.pop_front().unwrap();
+                self.push_back(item);
+            }
+        } else {
+            for _ in 0..self.len - n {
+                let item = self.pop_back().unwrap();
+                self.push_front(item);
+            }
+        }
+    }
+
+    // Keeps only the items for which `keep` returns true, in order.
+    //
+    // Every item goes once around the buffer, which never grows since there
+    // are never more items than before, so a panic in `keep` leaves a
+    // consistent buffer behind.
+    pub(crate) fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        for _ in 0..self.len {
+            let item = self.pop_front().unwrap();
+            if keep(&item) {
+                self.push_back(item);
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+        self.head = 0;
+    }
+
+    pub(crate) fn iter(&self) -> Iter<'_, T> {
+        let (front, back) = self.as_slices();
+        Iter {
+            front: front.iter(),
+            back: back.iter(),
+        }
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (front, back) = self.as_mut_slices();
+        IterMut {
+            front: front.iter_mut(),
+            back: back.iter_mut(),
+        }
+    }
+
+    // Returns an iterator over the items at the positions in `range`.
+    //
+    // Panics if the range is out of bounds.
+    pub(crate) fn range<R: RangeBounds<usize>>(&self, range: R) -> Iter<'_, T> {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end && end <= self.len, "range {}..{} out of bounds for length {}", start, end, self.len);
+
+        let (front, back) = self.as_slices();
+        let split = front.len();
+        let front = &front[start.min(split)..end.min(split)];
+        let back = &back[start.max(split) - split..end.max(split) - split];
+        Iter {
+            front: front.iter(),
+            back: back.iter(),
+        }
+    }
+
+    pub(crate) fn drain(&mut self) -> Drain<'_, T> {
+        Drain { buffer: self }
+    }
+
+    // Returns the slot of the `i`-th item from the front, for `i` up to the
+    // capacity.
+    fn slot(&self, i: usize) -> usize {
+        let slot = self.head + i;
+        if slot >= self.capacity() {
+            slot - self.capacity()
+        } else {
+            slot
+        }
+    }
+
+    // Returns the (start, length) of the slots holding the items before and
+    // after the end of the buffer.
+    fn ranges(&self) -> ((usize, usize), (usize, usize)) {
+        let first = self.len.min(self.capacity() - self.head);
+        ((self.head, first), (0, self.len - first))
+    }
+
+    // Doubles the buffer, moving the items to its beginning in order.
+    fn grow(&mut self) {
+        let mut buf = uninit_buffer((2 * self.capacity()).max(1));
+        let (first, second) = self.ranges();
+        // SAFETY: the items are moved bit by bit to the new buffer, and the
+        // old one, made of `MaybeUninit`s, drops nothing.
+        unsafe {
+            ptr::copy_nonoverlapping(self.buf.as_ptr().add(first.0), buf.as_mut_ptr(), first.1);
+            ptr::copy_nonoverlapping(self.buf.as_ptr(), buf.as_mut_ptr().add(first.1), second.1);
+        }
+        self.buf = buf;
+        self.head = 0;
+    }
+}
+
+fn uninit_buffer<T>(capacity: usize) -> Vec<MaybeUninit<T>> {
+    let mut buf = Vec::with_capacity(capacity);
+    buf.resize_with(capacity, MaybeUninit::uninit);
+    buf
+}
+
+impl<T> Drop for RingBuffer<T> {
+    fn drop(&mut self) {
+        let (front, back) = self.as_mut_slices();
+        // SAFETY: the items are dropped once, and the buffer is never used
+        // again.
+        unsafe {
+            ptr::drop_in_place(front);
+            ptr::drop_in_place(back);
+        }
+    }
+}
+
+impl<T: Clone> Clone for RingBuffer<T> {
+    fn clone(&self) -> Self {
+        let mut clone = RingBuffer::with_capacity(self.capacity());
+        for item in self.iter() {
+            clone.push_back(item.clone());
+        }
+        clone
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RingBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// SAFETY: the buffer owns its items like a `Vec<T>` does.
+unsafe impl<T: Send> Send for RingBuffer<T> {}
+unsafe impl<T: Sync> Sync for RingBuffer<T> {}
+
+// Iterator over the items of a `Queue` or a `Deque`, from the front to the
+// back.
+#[derive(Clone, Debug)]
+pub struct Iter<'a, T> {
+    front: slice::Iter<'a, T>,
+    back: slice::Iter<'a, T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+// Mutable iterator over the items of a `Deque`, from the front to the back.
+#[derive(Debug)]
+pub struct IterMut<'a, T> {
+    front: slice::IterMut<'a, T>,
+    back: slice::IterMut<'a, T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+// Owning iterator over the items of a `Queue` or a `Deque`, from the front to
+// the back.
+#[derive(Clone, Debug)]
+pub struct IntoIter<T> {
+    buffer: RingBuffer<T>,
+}
+
+impl<T> IntoIter<T> {
+    pub(crate) fn new(buffer: RingBuffer<T>) -> Self {
+        IntoIter { buffer }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.buffer.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.buffer.len(), Some(self.buffer.len()))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.buffer.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+// Iterator that removes every item of a `Queue` or a `Deque`, from the front
+// to the back. The items it doesn't get to are dropped with it, so the
+// container ends up empty either way.
+#[derive(Debug)]
+pub struct Drain<'a, T> {
+    buffer: &'a mut RingBuffer<T>,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.buffer.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.buffer.len(), Some(self.buffer.len()))
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.buffer.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> FusedIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_wrapped_slices() {
+        let mut buffer = RingBuffer::with_capacity(4);
+        buffer.push_back(2);
+        buffer.push_back(3);
+        buffer.push_front(1);
+        buffer.push_front(0);
+
+        assert_eq!(buffer.as_slices(), (&[0, 1][..], &[2, 3][..]));
+        assert_eq!(buffer.make_contiguous(), &[0, 1, 2, 3]);
+        assert_eq!(buffer.as_slices(), (&[0, 1, 2, 3][..], &[][..]));
+    }
+
+    #[test]
+    fn test_items_are_dropped_once() {
+        let item = Rc::new(0);
+        let mut buffer = RingBuffer::with_capacity(1);
+        for _ in 0..10 {
+            buffer.push_front(Rc::clone(&item));
+            buffer.push_back(Rc::clone(&item));
+        }
+        buffer.pop_front();
+        buffer.retain(|_| false);
+        assert_eq!(Rc::strong_count(&item), 1);
+
+        for _ in 0..7 {
+            buffer.push_back(Rc::clone(&item));
+        }
+        let clone = buffer.clone();
+        drop(buffer);
+        assert_eq!(Rc::strong_count(&item), 8);
+        drop(clone);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+}
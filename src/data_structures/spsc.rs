@@ -0,0 +1,440 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use super::bounded::Full;
+use super::sync::AtomicUsize;
+
+// Keeps a value on a cache line of its own, so that the producer writing its
+// position doesn't slow down the consumer reading its own, and the other way
+// around. 128 bytes covers the pairs of lines prefetched together on x86.
+#[repr(align(128))]
+struct CachePadded<T>(T);
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+// The ring shared by both ends. As in `Queue`, the items are the `len` slots
+// from the front one, wrapping around. Here, the front and the back are
+// positions in `0..2 * capacity` instead of a head and a length: each end
+// owns its position, the one after the last item it handled, and the length
+// is their difference. Counting up to twice the capacity tells an empty ring
+// from a full one without ever overflowing.
+struct Ring<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    // The position of the front item, written by the consumer only.
+    front: CachePadded<AtomicUsize>,
+    // The position after the back item, written by the producer only.
+    back: CachePadded<AtomicUsize>,
+}
+
+impl<T> Ring<T> {
+    fn capacity(&self) -> usize {
+        return self.slots.len();
+    }
+
+    fn len(&self, front: usize, back: usize) -> usize {
+        if back >= front {
+            back - front
+        } else {
+            back + 2 * self.capacity() - front
+        }
+    }
+
+    fn advance(&self, position: usize, n: usize) -> usize {
+        let to_end = 2 * self.capacity() - position;
+        if n >= to_end {
+            n - to_end
+        } else {
+            position + n
+        }
+    }
+
+    fn slot(&self, position: usize) -> usize {
+        if position >= self.capacity() {
+            position - self.capacity()
+        } else {
+            position
+        }
+    }
+
+    fn slot_ptr(&self, position: usize) -> *mut T {
+        self.slots[self.slot(position)].get().cast()
+    }
+
+    // Splits the `n` slots from `position` into the ones before the end of
+    // the buffer and those that wrap around to its start.
+    fn runs(&self, position: usize, n: usize) -> (usize, usize) {
+        let first = n.min(self.capacity() - self.slot(position));
+        (first, n - first)
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let mut front = *self.front.0.get_mut();
+        let back = *self.back.0.get_mut();
+        while front != back {
+            // SAFETY: the slots from the front to the back hold items.
+            unsafe { ptr::drop_in_place(self.slot_ptr(front)) };
+            front = self.advance(front, 1);
+        }
+    }
+}
+
+// Creates a wait-free ring buffer of `capacity` items for one producer thread
+// and one consumer thread, and returns both ends.
+//
+// Each end only ever writes its own position, so neither compare-and-swap nor
+// locks are needed, and every call finishes in a bounded number of steps
+// whatever the other thread does.
+//
+// Panics if the capacity is 0, or more than half of `usize::MAX`.
+pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity > 0, "the ring buffer must hold at least one item");
+    assert!(capacity <= usize::MAX / 2, "a ring buffer of {} items is too large", capacity);
+
+    let ring = Arc::new(Ring {
+        slots: (0..capacity).map(|_| UnsafeCell::new(MaybeUninit::uninit())).collect(),
+        front: CachePadded(AtomicUsize::new(0)),
+        back: CachePadded(AtomicUsize::new(0)),
+    });
+    let producer = Producer {
+        ring: Arc::clone(&ring),
+        back: 0,
+        front: 0,
+    };
+    let consumer = Consumer { ring, front: 0, back: 0 };
+    (producer, consumer)
+}
+
+// The end of a ring buffer that pushes items, created by `channel`.
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+    // The producer's own position.
+    back: usize,
+    // The consumer's position when the producer last looked. It only moves
+    // forward, so the ring has at least as much room as this says.
+    front: usize,
+}
+
+// SAFETY: the producer only writes slots the consumer doesn't read until the
+// back position is published, and only moves items of type `T` to the other
+// thread.
+unsafe impl<T: Send> Send for Producer<T> {}
+
+impl<T> Producer<T> {
+    // Returns how many items can be pushed right now, rereading the
+    // consumer's position only if it looks like there's no room.
+    fn room(&mut self, wanted: usize) -> usize {
+        let ring = &self.ring;
+        let mut room = ring.capacity() - ring.len(self.front, self.back);
+        if room < wanted {
+            self.front = ring.front.load(Ordering::Acquire);
+            room = ring.capacity() - ring.len(self.front, self.back);
+        }
+        room
+    }
+
+    fn publish(&mut self, n: usize) {
+        self.back = self.ring.advance(self.back, n);
+        self.ring.back.store(self.back, Ordering::Release);
+    }
+
+    // Pushes an item if there's room for it.
+    pub fn push(&mut self, item: T) -> Result<(), Full<T>> {
+        if self.room(1) == 0 {
+            return Err(Full(item));
+        }
+        // SAFETY: the slot at the back is free, and the consumer won't read it
+        // before it's published.
+        unsafe { self.ring.slot_ptr(self.back).write(item) };
+        self.publish(1);
+        return Ok(());
+    }
+
+    // Returns how many items the ring buffer holds, at most.
+    pub fn len(&self) -> usize {
+        return self.ring.len(self.ring.front.load(Ordering::Acquire), self.back);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    pub fn is_full(&self) -> bool {
+        return self.len() == self.capacity();
+    }
+
+    pub fn capacity(&self) -> usize {
+        return self.ring.capacity();
+    }
+}
+
+impl<T: Copy> Producer<T> {
+    // Pushes as many items from the start of `items` as there's room for,
+    // publishing them all at once, and returns how many it pushed.
+    pub fn push_slice(&mut self, items: &[T]) -> usize {
+        let n = self.room(items.len()).min(items.len());
+        let (first, second) = self.ring.runs(self.back, n);
+        // SAFETY: the `n` slots from the back are free, and the consumer won't
+        // read them before they're published. The first run ends at the end of
+        // the buffer, and the second one starts at its start.
+        unsafe {
+            ptr::copy_nonoverlapping(items.as_ptr(), self.ring.slot_ptr(self.back), first);
+            ptr::copy_nonoverlapping(items[first..].as_ptr(), self.ring.slot_ptr(0), second);
+        }
+        self.publish(n);
+        return n;
+    }
+}
+
+impl<T> fmt::Debug for Producer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Producer")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+// The end of a ring buffer that pops items, created by `channel`.
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+    // The consumer's own position.
+    front: usize,
+    // The producer's position when the consumer last looked. It only moves
+    // forward, so the ring holds at least as many items as this says.
+    back: usize,
+}
+
+// SAFETY: as for `Producer`, the other way around.
+unsafe impl<T: Send> Send for Consumer<T> {}
+
+impl<T> Consumer<T> {
+    // Returns how many items can be popped right now, rereading the
+    // producer's position only if it looks like there aren't enough.
+    fn available(&mut self, wanted: usize) -> usize {
+        let ring = &self.ring;
+        let mut available = ring.len(self.front, self.back);
+        if available < wanted {
+            self.back = ring.back.load(Ordering::Acquire);
+            available = ring.len(self.front, self.back);
+        }
+        available
+    }
+
+    fn release(&mut self, n: usize) {
+        self.front = self.ring.advance(self.front, n);
+        self.ring.front.store(self.front, Ordering::Release);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.available(1) == 0 {
+            return None;
+        }
+        // SAFETY: the slot at the front holds a published item, and the
+        // producer won't reuse it before it's released.
+        let item = unsafe { self.ring.slot_ptr(self.front).read() };
+        self.release(1);
+        return Some(item);
+    }
+
+    // Returns the item at the front, without removing it.
+    pub fn peek(&mut self) -> Option<&T> {
+        if self.available(1) == 0 {
+            return None;
+        }
+        // SAFETY: as in `pop`, and the item stays until the next pop.
+        return Some(unsafe { &*self.ring.slot_ptr(self.front) });
+    }
+
+    // Returns how many items the ring buffer holds, at least.
+    pub fn len(&self) -> usize {
+        return self.ring.len(self.front, self.ring.back.load(Ordering::Acquire));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    pub fn is_full(&self) -> bool {
+        return self.len() == self.capacity();
+    }
+
+    pub fn capacity(&self) -> usize {
+        return self.ring.capacity();
+    }
+}
+
+impl<T: Copy> Consumer<T> {
+    // Pops as many items as fit in `items`, or as there are, releasing their
+    // slots all at once, and returns how many it popped.
+    pub fn pop_slice(&mut self, items: &mut [T]) -> usize {
+        let n = self.available(items.len()).min(items.len());
+        let (first, second) = self.ring.runs(self.front, n);
+        // SAFETY: the `n` slots from the front hold published items, and the
+        // producer won't reuse them before they're released.
+        unsafe {
+            ptr::copy_nonoverlapping(self.ring.slot_ptr(self.front), items.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(self.ring.slot_ptr(0), items[first..].as_mut_ptr(), second);
+        }
+        self.release(n);
+        return n;
+    }
+}
+
+impl<T> Iterator for Consumer<T> {
+    type Item = T;
+
+    // Pops the next item, or returns `None` if there's none right now.
+    fn next(&mut self) -> Option<T> {
+        self.pop()
+    }
+}
+
+impl<T> fmt::Debug for Consumer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Consumer")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::model;
+    use super::*;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn test_push_pop() {
+        let (mut producer, mut consumer) = channel(3);
+        assert_eq!(consumer.pop(), None);
+        assert_eq!(producer.push(1), Ok(()));
+        assert_eq!(producer.push(2), Ok(()));
+        assert_eq!(producer.push(3), Ok(()));
+        assert!(producer.is_full());
+        assert_eq!(producer.push(4), Err(Full(4)));
+
+        assert_eq!(consumer.peek(), Some(&1));
+        assert_eq!(consumer.pop(), Some(1));
+        assert_eq!(producer.push(4), Ok(()));
+        assert_eq!(consumer.len(), 3);
+        assert_eq!(consumer.by_ref().collect::<Vec<i32>>(), vec![2, 3, 4]);
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn test_slices_wrap_around() {
+        let (mut producer, mut consumer) = channel(5);
+        let mut out = [0; 8];
+        for round in 0..20 {
+            let items: Vec<u32> = (0..4).map(|i| round * 4 + i).collect();
+            assert_eq!(producer.push_slice(&items), 4);
+            // only one more fits
+            assert_eq!(producer.push_slice(&items), 1);
+
+            assert_eq!(consumer.pop_slice(&mut out[..3]), 3);
+            assert_eq!(&out[..3], &items[..3]);
+            assert_eq!(consumer.pop_slice(&mut out), 2);
+            assert_eq!(&out[..2], &[items[3], items[0]]);
+        }
+    }
+
+    #[test]
+    fn test_items_left_are_dropped() {
+        let item = Rc::new(0);
+        let (mut producer, mut consumer) = channel(4);
+        for _ in 0..3 {
+            producer.push(Rc::clone(&item)).unwrap();
+        }
+        drop(consumer.pop());
+        drop(producer);
+        assert_eq!(Rc::strong_count(&item), 3);
+        drop(consumer);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "the ring buffer must hold at least one item")]
+    fn test_no_capacity() {
+        let _ = channel::<i32>(0);
+    }
+
+    #[test]
+    fn test_two_threads() {
+        const ITEMS: u64 = 200_000;
+        let (mut producer, mut consumer) = channel(64);
+        thread::scope(|s| {
+            s.spawn(move || {
+                let mut next = 0;
+                while next < ITEMS {
+                    // alternate single pushes and batches
+                    let pushed = if next % 2 == 0 {
+                        producer.push(next).map_or(0, |()| 1)
+                    } else {
+                        let batch: Vec<u64> = (next..(next + 7).min(ITEMS)).collect();
+                        producer.push_slice(&batch) as u64
+                    };
+                    if pushed == 0 {
+                        thread::yield_now();
+                    }
+                    next += pushed;
+                }
+            });
+
+            let mut expected = 0;
+            let mut batch = [0; 5];
+            while expected < ITEMS {
+                let n = consumer.pop_slice(&mut batch);
+                for &item in &batch[..n] {
+                    assert_eq!(item, expected);
+                    expected += 1;
+                }
+                match consumer.pop() {
+                    Some(item) => {
+                        assert_eq!(item, expected);
+                        expected += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_model() {
+        let runs = model::check(2, || {
+            let (mut producer, mut consumer) = channel(2);
+            let child = model::spawn(move || {
+                for i in 0..3 {
+                    while producer.push(i).is_err() {
+                        model::yield_now();
+                    }
+                }
+            });
+            let mut popped = Vec::new();
+            while popped.len() < 3 {
+                match consumer.pop() {
+                    Some(item) => popped.push(item),
+                    None => model::yield_now(),
+                }
+            }
+            child.join();
+            assert_eq!(popped, vec![0, 1, 2]);
+        });
+        assert!(runs > 1);
+    }
+}
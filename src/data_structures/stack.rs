@@ -1,17 +1,30 @@
-use core::fmt::Debug;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::Add;
 
-#[derive(Debug)]
+const DEFAULT_CAPACITY: usize = 5;
+
+// A LIFO stack over a growable array of slots. `data[..top]` holds the items,
+// from the bottom to the top, and every slot above `top` is empty.
+//
+// Popped items are moved out of their slot, so `T` needs no bounds, and the
+// array doubles when it's full, so pushes take amortized O(1) time.
+#[derive(Clone, Debug)]
 pub struct Stack<T> {
     top: usize,
     data: Vec<Option<T>>,
 }
 
-impl<T: Clone + Debug> Stack<T> {
+impl<T> Stack<T> {
     pub fn new() -> Self {
-        Self {
-            top: 0,
-            data: vec![None; 5],
-        }
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    // Creates an empty stack that can hold `capacity` items before growing.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut data = Vec::with_capacity(capacity);
+        data.resize_with(capacity, || None);
+        Self { top: 0, data }
     }
 
     pub fn push(&mut self, item: T) {
@@ -28,36 +41,256 @@ impl<T: Clone + Debug> Stack<T> {
             return None;
         }
 
-        let item = self.data[self.top - 1].clone();
         self.top -= 1;
+        return self.data[self.top].take();
+    }
+
+    // Returns the item on top of the stack, without removing it.
+    pub fn peek(&self) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+        return self.data[self.top - 1].as_ref();
+    }
 
-        return item;
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        if self.is_empty() {
+            return None;
+        }
+        return self.data[self.top - 1].as_mut();
     }
 
-    fn increase_array_size(&mut self) {
-        let current_capacity = self.data.len();
-        let new_capacity = current_capacity + 5;
-        let mut new_data = vec![None; new_capacity];
+    pub fn len(&self) -> usize {
+        return self.top;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.top == 0;
+    }
 
-        for i in 0..current_capacity {
-            new_data[i] = self.data[i].clone();
+    // Returns how many items the stack can hold before growing.
+    pub fn capacity(&self) -> usize {
+        return self.data.len();
+    }
+
+    // Drops every item, keeping the capacity.
+    pub fn clear(&mut self) {
+        for slot in &mut self.data[..self.top] {
+            *slot = None;
         }
+        self.top = 0;
+    }
+
+    // Shrinks the capacity to the number of items.
+    pub fn shrink_to_fit(&mut self) {
+        self.data.truncate(self.top);
+        self.data.shrink_to_fit();
+    }
 
-        self.data = new_data;
+    // Returns an iterator over the items, from the top to the bottom, which
+    // is the order they would be popped in.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.data[..self.top].iter(),
+        }
+    }
+
+    fn increase_array_size(&mut self) {
+        let new_capacity = (2 * self.data.len()).max(1);
+        self.data.resize_with(new_capacity, || None);
     }
-    
+
     fn is_full(&self) -> bool {
         return self.top == self.data.len();
     }
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<T> for Stack<T> {
+    // Pushes the items in order, so the last one ends up on top.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut stack = Self::new();
+        stack.extend(iter);
+        stack
+    }
+}
+
+impl<T> Extend<T> for Stack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T> IntoIterator for Stack<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    // Consumes the stack, yielding the items from the top to the bottom.
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { stack: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Stack<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+// Iterator over the items of a stack, from the top to the bottom, created by
+// `Stack::iter`.
+#[derive(Clone, Debug)]
+pub struct Iter<'a, T> {
+    inner: std::slice::Iter<'a, Option<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.inner.next_back().map(|slot| slot.as_ref().unwrap())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|slot| slot.as_ref().unwrap())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+// Owning iterator that pops the items of a stack, created by
+// `Stack::into_iter`.
+#[derive(Clone, Debug)]
+pub struct IntoIter<T> {
+    stack: Stack<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.stack.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.stack.len(), Some(self.stack.len()))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+// A stack that keeps the aggregate of its items under an associative
+// operation, such as their minimum or their sum, answering it in O(1) time.
+//
+// Next to each item, it stores the aggregate of that item and every one below
+// it, so popping an item uncovers the aggregate of the rest. The operation is
+// applied from the bottom up, so it doesn't need to be commutative.
+#[derive(Clone)]
+pub struct AggregateStack<T, F> {
+    items: Stack<(T, T)>,
+    op: F,
+}
+
+impl<T: Clone, F: Fn(&T, &T) -> T> AggregateStack<T, F> {
+    pub fn new(op: F) -> Self {
+        Self {
+            items: Stack::new(),
+            op,
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        let aggregate = match self.items.peek() {
+            Some((_, below)) => (self.op)(below, &item),
+            None => item.clone(),
+        };
+        self.items.push((item, aggregate));
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        return self.items.pop().map(|(item, _)| item);
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        return self.items.peek().map(|(item, _)| item);
+    }
+
+    // Returns the aggregate of every item, from the bottom to the top, or
+    // `None` if the stack is empty.
+    pub fn aggregate(&self) -> Option<&T> {
+        return self.items.peek().map(|(_, aggregate)| aggregate);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.items.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.items.is_empty();
+    }
+}
+
+impl<T: Clone + Ord> AggregateStack<T, fn(&T, &T) -> T> {
+    // Creates a stack that keeps the minimum of its items.
+    pub fn min() -> Self {
+        Self::new(|a, b| std::cmp::min(a, b).clone())
+    }
+
+    // Creates a stack that keeps the maximum of its items.
+    pub fn max() -> Self {
+        Self::new(|a, b| std::cmp::max(a, b).clone())
+    }
+}
+
+impl<T: Clone + Add<Output = T>> AggregateStack<T, fn(&T, &T) -> T> {
+    // Creates a stack that keeps the sum of its items.
+    pub fn sum() -> Self {
+        Self::new(|a, b| a.clone() + b.clone())
+    }
+}
+
+impl<T: Clone, F: Fn(&T, &T) -> T> Extend<T> for AggregateStack<T, F> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
 
-    fn is_empty(&self) -> bool {
-        return self.top <= 0;
+impl<T: fmt::Debug, F> fmt::Debug for AggregateStack<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AggregateStack")
+            .field("items", &self.items.iter().map(|(item, _)| item).collect::<Vec<&T>>())
+            .field("aggregate", &self.items.peek().map(|(_, aggregate)| aggregate))
+            .finish()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_rng::XorShift;
+    use std::rc::Rc;
 
     #[test]
     fn test_create() {
@@ -69,7 +302,7 @@ mod tests {
     #[test]
     fn test_push() {
         let mut stack: Stack<i32> = Stack::new();
-        
+
         stack.push(1);
         stack.push(2);
         stack.push(3);
@@ -105,4 +338,126 @@ mod tests {
 
         assert!(!stack.is_empty());
     }
+
+    #[test]
+    fn test_peek() {
+        let mut stack: Stack<String> = Stack::new();
+        assert_eq!(stack.peek(), None);
+
+        stack.push("a".to_string());
+        stack.push("b".to_string());
+        assert_eq!(stack.peek().map(String::as_str), Some("b"));
+
+        stack.peek_mut().unwrap().push('!');
+        assert_eq!(stack.pop().as_deref(), Some("b!"));
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn test_geometric_growth() {
+        let mut stack = Stack::with_capacity(0);
+        let mut grown = 0;
+        for i in 0..1000 {
+            let capacity = stack.capacity();
+            stack.push(i);
+            if stack.capacity() != capacity {
+                grown += 1;
+            }
+        }
+        assert_eq!(stack.len(), 1000);
+        assert_eq!(grown, 11);
+    }
+
+    #[test]
+    fn test_popped_items_are_dropped() {
+        let item = Rc::new(1);
+        let mut stack = Stack::new();
+        stack.push(Rc::clone(&item));
+        stack.push(Rc::clone(&item));
+        assert_eq!(Rc::strong_count(&item), 3);
+
+        drop(stack.pop());
+        assert_eq!(Rc::strong_count(&item), 2);
+        stack.clear();
+        assert_eq!(Rc::strong_count(&item), 1);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut stack: Stack<i32> = (0..20).collect();
+        for _ in 0..15 {
+            stack.pop();
+        }
+        stack.shrink_to_fit();
+        assert_eq!(stack.capacity(), 5);
+
+        stack.push(5);
+        assert_eq!(stack.capacity(), 10);
+        assert_eq!(stack.pop(), Some(5));
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut stack: Stack<i32> = (1..=3).collect();
+        stack.extend(vec![4, 5]);
+
+        assert_eq!(stack.iter().copied().collect::<Vec<i32>>(), vec![5, 4, 3, 2, 1]);
+        assert_eq!(stack.iter().rev().copied().collect::<Vec<i32>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(stack.iter().len(), 5);
+        assert_eq!((&stack).into_iter().max(), Some(&5));
+        assert_eq!(stack.into_iter().collect::<Vec<i32>>(), vec![5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_aggregate_stack() {
+        let mut min = AggregateStack::min();
+        let mut max = AggregateStack::max();
+        let mut sum = AggregateStack::sum();
+        assert_eq!(min.aggregate(), None);
+
+        for item in [5, 3, 8, 1, 9] {
+            min.push(item);
+            max.push(item);
+            sum.push(item);
+        }
+        assert_eq!((min.aggregate(), max.aggregate(), sum.aggregate()), (Some(&1), Some(&9), Some(&26)));
+
+        assert_eq!(min.pop(), Some(9));
+        assert_eq!(min.pop(), Some(1));
+        assert_eq!(min.aggregate(), Some(&3));
+        assert_eq!(max.pop(), Some(9));
+        assert_eq!(max.aggregate(), Some(&8));
+        assert_eq!(min.peek(), Some(&8));
+        assert_eq!(min.len(), 3);
+    }
+
+    #[test]
+    fn test_aggregate_stack_order() {
+        // Concatenation isn't commutative: the aggregate reads bottom up.
+        let mut stack = AggregateStack::new(|a: &String, b: &String| format!("{}{}", a, b));
+        stack.extend(["a", "b", "c"].iter().map(|s| s.to_string()));
+        assert_eq!(stack.aggregate().map(String::as_str), Some("abc"));
+        stack.pop();
+        stack.push("d".to_string());
+        assert_eq!(stack.aggregate().map(String::as_str), Some("abd"));
+    }
+
+    #[test]
+    fn test_aggregate_stack_agrees_with_brute_force() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+
+        let mut stack = AggregateStack::min();
+        let mut expected: Vec<u64> = Vec::new();
+        for _ in 0..2000 {
+            if rng.below(3) < 2 {
+                let item = rng.below(100);
+                stack.push(item);
+                expected.push(item);
+            } else {
+                assert_eq!(stack.pop(), expected.pop());
+            }
+            assert_eq!(stack.aggregate(), expected.iter().min());
+        }
+    }
 }
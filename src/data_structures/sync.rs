@@ -0,0 +1,8 @@
+// The atomics used by the lock-free structures. Tests swap in those of the
+// model checker, which lets it switch threads before every atomic access.
+
+#[cfg(not(test))]
+pub(crate) use std::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize};
+
+#[cfg(test)]
+pub(crate) use super::model::{fence, AtomicBool, AtomicPtr, AtomicUsize};
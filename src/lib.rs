@@ -1,4 +1,10 @@
+#![allow(clippy::needless_return, clippy::needless_range_loop)]
+
 pub mod sorting;
 pub mod searching;
 pub mod misc;
 pub mod data_structures;
+pub mod strings;
+
+#[cfg(test)]
+pub(crate) mod test_rng;
@@ -0,0 +1,560 @@
+// Arithmetic expressions over integers and floats, parsed and evaluated with
+// stacks: the shunting-yard algorithm turns infix into postfix (reverse Polish
+// notation), and a second pass evaluates the postfix.
+//
+// Integers are `i64` and floats are `f64`. An operation on two integers stays
+// an integer, checked for overflow, and any float operand makes it a float.
+// Integer division truncates toward zero, as in Rust. Dividing by zero is an
+// error for both kinds, rather than an infinity or NaN for floats.
+
+use std::fmt;
+
+use crate::data_structures::stack::Stack;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+}
+
+impl Value {
+    pub fn as_f64(self) -> f64 {
+        match self {
+            Value::Int(n) => n as f64,
+            Value::Float(x) => x,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    // unary minus, written `neg` in postfix to tell it apart from `-`
+    Neg,
+}
+
+impl Operator {
+    // The precedence and associativity table, from loosest to tightest:
+    //
+    //     + -      1  left
+    //     * / %    2  left
+    //     neg      3  right
+    //     ^        4  right
+    //
+    // so `-2 ^ 2` is `-(2 ^ 2)` and `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)`.
+    pub fn precedence(self) -> u8 {
+        match self {
+            Operator::Add | Operator::Sub => 1,
+            Operator::Mul | Operator::Div | Operator::Rem => 2,
+            Operator::Neg => 3,
+            Operator::Pow => 4,
+        }
+    }
+
+    pub fn associativity(self) -> Associativity {
+        match self {
+            Operator::Neg | Operator::Pow => Associativity::Right,
+            _ => Associativity::Left,
+        }
+    }
+
+    pub fn arity(self) -> usize {
+        match self {
+            Operator::Neg => 1,
+            _ => 2,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Sub => "-",
+            Operator::Mul => "*",
+            Operator::Div => "/",
+            Operator::Rem => "%",
+            Operator::Pow => "^",
+            Operator::Neg => "neg",
+        }
+    }
+
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "+" => Some(Operator::Add),
+            "-" => Some(Operator::Sub),
+            "*" => Some(Operator::Mul),
+            "/" => Some(Operator::Div),
+            "%" => Some(Operator::Rem),
+            "^" => Some(Operator::Pow),
+            "neg" => Some(Operator::Neg),
+            _ => None,
+        }
+    }
+
+    fn apply_unary(self, x: Value) -> Result<Value, ExprError> {
+        match x {
+            Value::Int(n) => n.checked_neg().map(Value::Int).ok_or(ExprError::Overflow),
+            Value::Float(x) => Ok(Value::Float(-x)),
+        }
+    }
+
+    fn apply_binary(self, a: Value, b: Value) -> Result<Value, ExprError> {
+        if matches!(self, Operator::Div | Operator::Rem) && b.as_f64() == 0.0 {
+            return Err(ExprError::DivisionByZero);
+        }
+        let (a, b) = match (a, b) {
+            (Value::Int(a), Value::Int(b)) => return self.apply_int(a, b),
+            (a, b) => (a.as_f64(), b.as_f64()),
+        };
+        let result = match self {
+            Operator::Add => a + b,
+            Operator::Sub => a - b,
+            Operator::Mul => a * b,
+            Operator::Div => a / b,
+            Operator::Rem => a % b,
+            Operator::Pow => a.powf(b),
+            Operator::Neg => unreachable!("neg is unary"),
+        };
+        Ok(Value::Float(result))
+    }
+
+    fn apply_int(self, a: i64, b: i64) -> Result<Value, ExprError> {
+        let result = match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Sub => a.checked_sub(b),
+            Operator::Mul => a.checked_mul(b),
+            Operator::Div => a.checked_div(b),
+            Operator::Rem => a.checked_rem(b),
+            // a negative power of an integer is a fraction
+            Operator::Pow if b < 0 => return Ok(Value::Float((a as f64).powf(b as f64))),
+            Operator::Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+            Operator::Neg => unreachable!("neg is unary"),
+        };
+        result.map(Value::Int).ok_or(ExprError::Overflow)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Token {
+    Number(Value),
+    Operator(Operator),
+    LeftParen,
+    RightParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Operator(op) => write!(f, "{}", op.symbol()),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+        }
+    }
+}
+
+// Positions are byte offsets into the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExprError {
+    // a bracket with no partner, at the position of the bracket
+    Unbalanced { position: usize },
+    UnknownToken { position: usize, token: String },
+    DivisionByZero,
+    // an integer result, or an integer literal, that doesn't fit in an `i64`
+    Overflow,
+    // an operator without enough operands, or an empty expression
+    MissingOperand,
+    // operands left over once every operator has been applied
+    MissingOperator,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::Unbalanced { position } => write!(f, "unbalanced bracket at position {}", position),
+            ExprError::UnknownToken { position, token } => {
+                write!(f, "unknown token {:?} at position {}", token, position)
+            }
+            ExprError::DivisionByZero => write!(f, "division by zero"),
+            ExprError::Overflow => write!(f, "integer overflow"),
+            ExprError::MissingOperand => write!(f, "an operator is missing an operand"),
+            ExprError::MissingOperator => write!(f, "operands are missing an operator"),
+        }
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+// Splits infix text into tokens, each with its position. A `-` or `+` that
+// can't follow an operand is unary: `-` becomes `Neg` and `+` is dropped.
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let bytes = input.as_bytes();
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_digit() || c == b'.' {
+            while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                i += 1;
+            }
+            let number = parse_number(&input[start..i]).ok_or_else(|| ExprError::UnknownToken {
+                position: start,
+                token: input[start..i].to_string(),
+            })?;
+            tokens.push((start, Token::Number(number?)));
+            continue;
+        }
+
+        let after_operand = matches!(tokens.last(), Some((_, Token::Number(_) | Token::RightParen)));
+        let token = match c {
+            b'(' => Token::LeftParen,
+            b')' => Token::RightParen,
+            b'-' if !after_operand => Token::Operator(Operator::Neg),
+            b'+' if !after_operand => {
+                i += 1;
+                continue;
+            }
+            _ => match input.get(i..i + 1).and_then(Operator::from_symbol).filter(|op| op.arity() == 2) {
+                Some(op) => Token::Operator(op),
+                None => {
+                    let len = input[i..].chars().next().map_or(1, char::len_utf8);
+                    return Err(ExprError::UnknownToken {
+                        position: start,
+                        token: input[i..i + len].to_string(),
+                    });
+                }
+            },
+        };
+        tokens.push((start, token));
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+// Parses an integer or decimal literal. Returns `None` if it's malformed, and
+// `Some(Err(Overflow))` if it's an integer too large for an `i64`.
+fn parse_number(text: &str) -> Option<Result<Value, ExprError>> {
+    if text.contains('.') {
+        if text == "." || text.matches('.').count() > 1 {
+            return None;
+        }
+        return text.parse().ok().map(|x| Ok(Value::Float(x)));
+    }
+    Some(text.parse().map(Value::Int).map_err(|_| ExprError::Overflow))
+}
+
+// Converts an infix expression to postfix with the shunting-yard algorithm.
+// Time complexity: O(n).
+//
+// Operands go straight to the output. An operator waits on a stack until an
+// operator that binds no tighter arrives, or a closing parenthesis pops
+// everything down to its opening one. Only unbalanced parentheses and unknown
+// tokens are reported here; a missing operand shows up on evaluation.
+pub fn infix_to_postfix(input: &str) -> Result<Vec<Token>, ExprError> {
+    let mut output = Vec::new();
+    let mut operators: Stack<(usize, Token)> = Stack::new();
+
+    for (position, token) in tokenize(input)? {
+        match token {
+            Token::Number(_) => output.push(token),
+            // A prefix operator has no left operand to take from the stack.
+            Token::Operator(Operator::Neg) | Token::LeftParen => operators.push((position, token)),
+            Token::Operator(op) => {
+                while let Some(&(_, Token::Operator(top))) = operators.peek() {
+                    let binds_tighter = top.precedence() > op.precedence()
+                        || (top.precedence() == op.precedence() && op.associativity() == Associativity::Left);
+                    if !binds_tighter {
+                        break;
+                    }
+                    output.push(Token::Operator(top));
+                    operators.pop();
+                }
+                operators.push((position, token));
+            }
+            Token::RightParen => loop {
+                match operators.pop() {
+                    Some((_, Token::LeftParen)) => break,
+                    Some((_, top)) => output.push(top),
+                    None => return Err(ExprError::Unbalanced { position }),
+                }
+            },
+        }
+    }
+
+    while let Some((position, token)) = operators.pop() {
+        if token == Token::LeftParen {
+            return Err(ExprError::Unbalanced { position });
+        }
+        output.push(token);
+    }
+
+    Ok(output)
+}
+
+// Evaluates postfix tokens. Time complexity: O(n).
+//
+// A parenthesis has no place in postfix, so it's an unknown token, reported
+// at its index in `tokens`.
+pub fn evaluate_postfix(tokens: &[Token]) -> Result<Value, ExprError> {
+    let mut operands: Stack<Value> = Stack::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        match *token {
+            Token::Number(value) => operands.push(value),
+            Token::Operator(op) if op.arity() == 1 => {
+                let x = operands.pop().ok_or(ExprError::MissingOperand)?;
+                operands.push(op.apply_unary(x)?);
+            }
+            Token::Operator(op) => {
+                let b = operands.pop().ok_or(ExprError::MissingOperand)?;
+                let a = operands.pop().ok_or(ExprError::MissingOperand)?;
+                operands.push(op.apply_binary(a, b)?);
+            }
+            Token::LeftParen | Token::RightParen => {
+                return Err(ExprError::UnknownToken {
+                    position: index,
+                    token: token.to_string(),
+                });
+            }
+        }
+    }
+
+    let result = operands.pop().ok_or(ExprError::MissingOperand)?;
+    if !operands.is_empty() {
+        return Err(ExprError::MissingOperator);
+    }
+    Ok(result)
+}
+
+// Evaluates postfix text with whitespace between tokens, such as "3 4 + neg".
+pub fn evaluate_rpn(input: &str) -> Result<Value, ExprError> {
+    let mut tokens = Vec::new();
+    let mut rest = input;
+
+    while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+        let len = rest[start..].find(char::is_whitespace).unwrap_or(rest.len() - start);
+        let text = &rest[start..start + len];
+        let position = input.len() - rest.len() + start;
+        let unknown = || ExprError::UnknownToken {
+            position,
+            token: text.to_string(),
+        };
+
+        let token = match Operator::from_symbol(text) {
+            Some(op) => Token::Operator(op),
+            None if text.starts_with(|c: char| c.is_ascii_digit() || c == '.') => {
+                if !text.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+                    return Err(unknown());
+                }
+                Token::Number(parse_number(text).ok_or_else(unknown)??)
+            }
+            None => return Err(unknown()),
+        };
+        tokens.push(token);
+        rest = &rest[start + len..];
+    }
+
+    evaluate_postfix(&tokens)
+}
+
+// Evaluates an infix expression such as "2 * (3 + 4) ^ 2".
+pub fn evaluate(input: &str) -> Result<Value, ExprError> {
+    evaluate_postfix(&infix_to_postfix(input)?)
+}
+
+// Checks that every `(`, `[` and `{` in the input is closed by its partner in
+// the right order, ignoring every other character. Time complexity: O(n).
+//
+// A closing bracket that doesn't match the innermost open one is reported at
+// its own position, and a bracket left open at the end at the opener's.
+pub fn check_brackets(input: &str) -> Result<(), ExprError> {
+    let mut open: Stack<(usize, char)> = Stack::new();
+
+    for (position, c) in input.char_indices() {
+        let opener = match c {
+            '(' | '[' | '{' => {
+                open.push((position, c));
+                continue;
+            }
+            ')' => '(',
+            ']' => '[',
+            '}' => '{',
+            _ => continue,
+        };
+        match open.pop() {
+            Some((_, top)) if top == opener => {}
+            _ => return Err(ExprError::Unbalanced { position }),
+        }
+    }
+
+    match open.pop() {
+        Some((position, _)) => Err(ExprError::Unbalanced { position }),
+        None => Ok(()),
+    }
+}
+
+pub fn is_balanced(input: &str) -> bool {
+    check_brackets(input).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn postfix(input: &str) -> String {
+        let tokens = infix_to_postfix(input).unwrap();
+        tokens.iter().map(Token::to_string).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn test_infix_to_postfix() {
+        assert_eq!(postfix("3 + 4 * 2 / (1 - 5) ^ 2 ^ 3"), "3 4 2 * 1 5 - 2 3 ^ ^ / +");
+        assert_eq!(postfix("1 - 2 - 3"), "1 2 - 3 -");
+        assert_eq!(postfix("-2 ^ 2"), "2 2 ^ neg");
+        assert_eq!(postfix("2 ^ -3 ^ 2"), "2 3 2 ^ neg ^");
+        assert_eq!(postfix("-(1 + +2) * --3"), "1 2 + neg 3 neg neg *");
+        assert_eq!(postfix("1.5*(2)"), "1.5 2 *");
+        assert_eq!(postfix(""), "");
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("2 * (3 + 4) ^ 2"), Ok(Value::Int(98)));
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok(Value::Int(512)));
+        assert_eq!(evaluate("-2 ^ 2"), Ok(Value::Int(-4)));
+        assert_eq!(evaluate("7 / 2"), Ok(Value::Int(3)));
+        assert_eq!(evaluate("-7 % 3"), Ok(Value::Int(-1)));
+        assert_eq!(evaluate("7 / 2.0"), Ok(Value::Float(3.5)));
+        assert_eq!(evaluate("2 ^ -1"), Ok(Value::Float(0.5)));
+        assert_eq!(evaluate("(1.5 + .5) * 3"), Ok(Value::Float(6.0)));
+        assert_eq!(evaluate("3 + 4 * 2 / (1 - 5) ^ 2 ^ 3"), Ok(Value::Int(3)));
+        assert_eq!(evaluate("((((42))))"), Ok(Value::Int(42)));
+    }
+
+    #[test]
+    fn test_evaluate_rpn() {
+        assert_eq!(evaluate_rpn("3 4 + 2 *"), Ok(Value::Int(14)));
+        assert_eq!(evaluate_rpn("  5 1 2 + 4 * + 3 -  "), Ok(Value::Int(14)));
+        assert!(evaluate_rpn("2 neg 0.5 ^").unwrap().as_f64().is_nan());
+        assert_eq!(evaluate_rpn("1.5 neg"), Ok(Value::Float(-1.5)));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(evaluate("(1 + 2"), Err(ExprError::Unbalanced { position: 0 }));
+        assert_eq!(evaluate("1 + 2)"), Err(ExprError::Unbalanced { position: 5 }));
+        assert_eq!(evaluate("1 / (2 - 2)"), Err(ExprError::DivisionByZero));
+        assert_eq!(evaluate("1.0 % 0"), Err(ExprError::DivisionByZero));
+        assert_eq!(evaluate_rpn("1 0.0 /"), Err(ExprError::DivisionByZero));
+        assert_eq!(
+            evaluate("2 * x"),
+            Err(ExprError::UnknownToken {
+                position: 4,
+                token: "x".to_string()
+            })
+        );
+        assert_eq!(
+            evaluate("1 + 2.3.4"),
+            Err(ExprError::UnknownToken {
+                position: 4,
+                token: "2.3.4".to_string()
+            })
+        );
+        assert_eq!(
+            evaluate_rpn("1 2 add"),
+            Err(ExprError::UnknownToken {
+                position: 4,
+                token: "add".to_string()
+            })
+        );
+        assert_eq!(
+            evaluate_rpn("1 2x +"),
+            Err(ExprError::UnknownToken {
+                position: 2,
+                token: "2x".to_string()
+            })
+        );
+        assert_eq!(evaluate("1 +"), Err(ExprError::MissingOperand));
+        assert_eq!(evaluate(""), Err(ExprError::MissingOperand));
+        assert_eq!(evaluate("1 2"), Err(ExprError::MissingOperator));
+        assert_eq!(evaluate_rpn("1 2 3 +"), Err(ExprError::MissingOperator));
+        assert_eq!(
+            evaluate_postfix(&[Token::Number(Value::Int(1)), Token::LeftParen]),
+            Err(ExprError::UnknownToken {
+                position: 1,
+                token: "(".to_string()
+            })
+        );
+        assert_eq!(evaluate("9223372036854775807 + 1"), Err(ExprError::Overflow));
+        assert_eq!(evaluate("99999999999999999999"), Err(ExprError::Overflow));
+        assert_eq!(evaluate("-9223372036854775807 - 1"), Ok(Value::Int(i64::MIN)));
+        assert_eq!(evaluate("(-9223372036854775807 - 1) / -1"), Err(ExprError::Overflow));
+        assert_eq!(evaluate("2 ^ 64"), Err(ExprError::Overflow));
+        assert_eq!(ExprError::DivisionByZero.to_string(), "division by zero");
+    }
+
+    #[test]
+    fn test_check_brackets() {
+        assert_eq!(check_brackets("{a: [1, (2)], b: []}"), Ok(()));
+        assert_eq!(check_brackets(""), Ok(()));
+        assert_eq!(check_brackets("([)]"), Err(ExprError::Unbalanced { position: 2 }));
+        assert_eq!(check_brackets("(()"), Err(ExprError::Unbalanced { position: 0 }));
+        assert_eq!(check_brackets("x])"), Err(ExprError::Unbalanced { position: 1 }));
+        assert_eq!(check_brackets("é(é"), Err(ExprError::Unbalanced { position: 2 }));
+        assert!(is_balanced("[{()()}]"));
+        assert_eq!(
+            evaluate("1 + é"),
+            Err(ExprError::UnknownToken {
+                position: 4,
+                token: "é".to_string()
+            })
+        );
+        assert!(!is_balanced("}{"));
+    }
+
+    #[test]
+    fn test_is_balanced_agrees_with_depth_count() {
+        // With a single kind of bracket, a string is balanced exactly when the
+        // running depth never drops below zero and ends at zero.
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..1000 {
+            let len = rng.below(12) as usize;
+            let input: String = (0..len).map(|_| if rng.below(2) == 0 { '(' } else { ')' }).collect();
+            let mut depth = 0i32;
+            let mut valid = true;
+            for c in input.chars() {
+                depth += if c == '(' { 1 } else { -1 };
+                valid &= depth >= 0;
+            }
+            assert_eq!(is_balanced(&input), valid && depth == 0, "{}", input);
+        }
+    }
+}
@@ -0,0 +1,503 @@
+// Dense matrices and the matrix multiplication algorithms of CLRS chapter 4:
+// the textbook triple loop, a blocked (tiled) version, a cache-oblivious
+// recursive version and Strassen's algorithm.
+//
+// Every algorithm computes exactly the same products for integers, and they
+// only differ in their running time and memory traffic, so pick by size:
+// `multiply_blocked` and `multiply_recursive` for everyday sizes, and
+// `multiply_strassen` for large ones.
+//
+// Strassen's intermediate sums and differences can leave the range of an
+// integer type even when the product fits: they go negative for unsigned
+// types. So it computes modulo 2^bits with wrapping arithmetic, which is still
+// exact whenever the product fits. When it doesn't, the other algorithms
+// overflow like any integer arithmetic, while Strassen's wraps around.
+
+use std::fmt::Debug;
+use std::ops::{Add, Index, IndexMut, Mul, Range, Sub};
+
+// Element types a matrix can be multiplied over.
+pub trait Scalar: Copy + Debug + PartialEq + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> {
+    fn zero() -> Self;
+    fn one() -> Self;
+
+    // Arithmetic modulo 2^bits for integers, used by Strassen's algorithm.
+    // Other types keep the usual operators.
+    fn wrapping_add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn wrapping_sub(self, other: Self) -> Self {
+        self - other
+    }
+
+    fn wrapping_mul(self, other: Self) -> Self {
+        self * other
+    }
+}
+
+macro_rules! impl_scalar_int {
+    ($($t:ty)*) => ($(
+        impl Scalar for $t {
+            fn zero() -> Self {
+                0
+            }
+
+            fn one() -> Self {
+                1
+            }
+
+            fn wrapping_add(self, other: Self) -> Self {
+                <$t>::wrapping_add(self, other)
+            }
+
+            fn wrapping_sub(self, other: Self) -> Self {
+                <$t>::wrapping_sub(self, other)
+            }
+
+            fn wrapping_mul(self, other: Self) -> Self {
+                <$t>::wrapping_mul(self, other)
+            }
+        }
+    )*)
+}
+
+impl_scalar_int! { i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize }
+
+macro_rules! impl_scalar_float {
+    ($($t:ty)*) => ($(
+        impl Scalar for $t {
+            fn zero() -> Self {
+                0.0
+            }
+
+            fn one() -> Self {
+                1.0
+            }
+        }
+    )*)
+}
+
+impl_scalar_float! { f32 f64 }
+
+// Below this many multiply-adds, the recursive algorithm stops splitting.
+const RECURSIVE_BASE: usize = 16 * 16 * 16;
+
+// A `rows` x `cols` matrix, stored row-major.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+}
+
+impl<T: Scalar> Matrix<T> {
+    // Creates a matrix from its elements in row-major order.
+    //
+    // Panics if `data` doesn't hold `rows * cols` elements.
+    pub fn new(rows: usize, cols: usize, data: Vec<T>) -> Self {
+        assert_eq!(data.len(), rows * cols, "a {}x{} matrix needs {} elements", rows, cols, rows * cols);
+        Matrix { rows, cols, data }
+    }
+
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Matrix {
+            rows,
+            cols,
+            data: vec![T::zero(); rows * cols],
+        }
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut identity = Self::zeros(n, n);
+        for i in 0..n {
+            identity[(i, i)] = T::one();
+        }
+        identity
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    // Returns the elements in row-major order.
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut transposed = Self::zeros(self.cols, self.rows);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                transposed[(j, i)] = self[(i, j)];
+            }
+        }
+        transposed
+    }
+
+    // SQUARE-MATRIX-MULTIPLY: every element of the product is the dot product
+    // of a row of `self` and a column of `other`.
+    // Time complexity: O(n * m * p) for an n x m by m x p product.
+    //
+    // Panics if the dimensions don't match, like every multiplication below.
+    pub fn multiply_naive(&self, other: &Self) -> Self {
+        self.check_dimensions(other);
+        let mut product = Self::zeros(self.rows, other.cols);
+
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                let mut sum = T::zero();
+                for k in 0..self.cols {
+                    sum = sum + self[(i, k)] * other[(k, j)];
+                }
+                product[(i, j)] = sum;
+            }
+        }
+        product
+    }
+
+    // Multiplies tile by tile, with `block` x `block` tiles, so the tiles in
+    // use stay in the cache. Within a tile, the rows of `other` and of the
+    // product are walked contiguously. Time complexity: O(n * m * p).
+    //
+    // Panics if `block` is 0.
+    pub fn multiply_blocked(&self, other: &Self, block: usize) -> Self {
+        self.check_dimensions(other);
+        assert!(block > 0, "the blocks must not be empty");
+        self.blocked(other, block, |c, a, b| c + a * b)
+    }
+
+    // The loops of `multiply_blocked`, accumulating with `mul_add(c, a, b)`.
+    fn blocked(&self, other: &Self, block: usize, mul_add: impl Fn(T, T, T) -> T) -> Self {
+        let (n, m, p) = (self.rows, self.cols, other.cols);
+        let mut product = Self::zeros(n, p);
+
+        for ii in (0..n).step_by(block) {
+            for kk in (0..m).step_by(block) {
+                for jj in (0..p).step_by(block) {
+                    for i in ii..(ii + block).min(n) {
+                        for k in kk..(kk + block).min(m) {
+                            let a = self.data[i * m + k];
+                            let row = &other.data[k * p + jj..k * p + (jj + block).min(p)];
+                            let out = &mut product.data[i * p + jj..i * p + (jj + block).min(p)];
+                            for (c, &b) in out.iter_mut().zip(row) {
+                                *c = mul_add(*c, a, b);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        product
+    }
+
+    // Cache-oblivious multiplication: the largest of the three dimensions is
+    // halved recursively, so at some depth the subproblems fit in every level
+    // of the cache, whatever its size. Time complexity: O(n * m * p).
+    pub fn multiply_recursive(&self, other: &Self) -> Self {
+        self.check_dimensions(other);
+        let mut product = Self::zeros(self.rows, other.cols);
+        let ranges = (0..self.rows, 0..other.cols, 0..self.cols);
+        self.multiply_add(other, &mut product, ranges);
+        product
+    }
+
+    // Adds `self[rows, inner] * other[inner, cols]` to `product[rows, cols]`.
+    fn multiply_add(
+        &self,
+        other: &Self,
+        product: &mut Self,
+        (rows, cols, inner): (Range<usize>, Range<usize>, Range<usize>),
+    ) {
+        let (n, p, m) = (rows.len(), cols.len(), inner.len());
+
+        if n * p * m <= RECURSIVE_BASE {
+            let (width, product_width) = (other.cols, product.cols);
+            for i in rows {
+                let out = &mut product.data[i * product_width + cols.start..i * product_width + cols.end];
+                for k in inner.clone() {
+                    let a = self.data[i * self.cols + k];
+                    let row = &other.data[k * width + cols.start..k * width + cols.end];
+                    for (c, &b) in out.iter_mut().zip(row) {
+                        *c = *c + a * b;
+                    }
+                }
+            }
+            return;
+        }
+
+        if n >= p && n >= m {
+            let mid = rows.start + n / 2;
+            self.multiply_add(other, product, (rows.start..mid, cols.clone(), inner.clone()));
+            self.multiply_add(other, product, (mid..rows.end, cols, inner));
+        } else if p >= m {
+            let mid = cols.start + p / 2;
+            self.multiply_add(other, product, (rows.clone(), cols.start..mid, inner.clone()));
+            self.multiply_add(other, product, (rows, mid..cols.end, inner));
+        } else {
+            let mid = inner.start + m / 2;
+            self.multiply_add(other, product, (rows.clone(), cols.clone(), inner.start..mid));
+            self.multiply_add(other, product, (rows, cols, mid..inner.end));
+        }
+    }
+
+    // Strassen's algorithm: the product of two n x n matrices takes seven
+    // products of n/2 x n/2 matrices instead of eight, plus a few sums.
+    // Time complexity: O(n^lg 7) = O(n^2.81).
+    //
+    // Below `crossover`, the recursion falls back to `multiply_blocked`, which
+    // is faster on small matrices. Integers use wrapping arithmetic throughout,
+    // as explained at the top of the file. Rectangular matrices are padded with zeros
+    // to a square, and odd sizes get one more row and column of zeros.
+    //
+    // Panics if `crossover` is 0.
+    pub fn multiply_strassen(&self, other: &Self, crossover: usize) -> Self {
+        self.check_dimensions(other);
+        assert!(crossover > 0, "the crossover must be at least 1");
+
+        let size = self.rows.max(self.cols).max(other.cols);
+        let square = strassen(&self.padded(size), &other.padded(size), crossover);
+
+        let mut product = Self::zeros(self.rows, other.cols);
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                product[(i, j)] = square[(i, j)];
+            }
+        }
+        product
+    }
+
+    // Returns a `size` x `size` copy, with zeros below and on the right.
+    fn padded(&self, size: usize) -> Self {
+        if self.rows == size && self.cols == size {
+            return self.clone();
+        }
+        let mut padded = Self::zeros(size, size);
+        for i in 0..self.rows {
+            padded.data[i * size..i * size + self.cols].copy_from_slice(&self.data[i * self.cols..(i + 1) * self.cols]);
+        }
+        padded
+    }
+
+    // Returns the `size` x `size` block whose top left corner is at `(row, col)`.
+    fn block(&self, row: usize, col: usize, size: usize) -> Self {
+        let mut block = Self::zeros(size, size);
+        for i in 0..size {
+            let start = (row + i) * self.cols + col;
+            block.data[i * size..(i + 1) * size].copy_from_slice(&self.data[start..start + size]);
+        }
+        block
+    }
+
+    // Copies `block` so its top left corner is at `(row, col)`.
+    fn set_block(&mut self, row: usize, col: usize, block: &Self) {
+        for i in 0..block.rows {
+            let start = (row + i) * self.cols + col;
+            self.data[start..start + block.cols].copy_from_slice(&block.data[i * block.cols..(i + 1) * block.cols]);
+        }
+    }
+
+    fn zip_with(&self, other: &Self, f: impl Fn(T, T) -> T) -> Self {
+        Matrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().zip(&other.data).map(|(&a, &b)| f(a, b)).collect(),
+        }
+    }
+
+    fn check_dimensions(&self, other: &Self) {
+        assert_eq!(
+            self.cols, other.rows,
+            "can't multiply a {}x{} matrix by a {}x{} matrix",
+            self.rows, self.cols, other.rows, other.cols
+        );
+    }
+}
+
+// Strassen's algorithm over square matrices of the same size.
+fn strassen<T: Scalar>(a: &Matrix<T>, b: &Matrix<T>, crossover: usize) -> Matrix<T> {
+    let n = a.rows;
+    if n <= crossover {
+        return a.blocked(b, 64, |c, x, y| c.wrapping_add(x.wrapping_mul(y)));
+    }
+    if n % 2 == 1 {
+        let product = strassen(&a.padded(n + 1), &b.padded(n + 1), crossover);
+        return product.block(0, 0, n);
+    }
+
+    let h = n / 2;
+    let (a11, a12, a21, a22) = (a.block(0, 0, h), a.block(0, h, h), a.block(h, 0, h), a.block(h, h, h));
+    let (b11, b12, b21, b22) = (b.block(0, 0, h), b.block(0, h, h), b.block(h, 0, h), b.block(h, h, h));
+    let add = |x: &Matrix<T>, y: &Matrix<T>| x.zip_with(y, T::wrapping_add);
+    let sub = |x: &Matrix<T>, y: &Matrix<T>| x.zip_with(y, T::wrapping_sub);
+
+    let m1 = strassen(&add(&a11, &a22), &add(&b11, &b22), crossover);
+    let m2 = strassen(&add(&a21, &a22), &b11, crossover);
+    let m3 = strassen(&a11, &sub(&b12, &b22), crossover);
+    let m4 = strassen(&a22, &sub(&b21, &b11), crossover);
+    let m5 = strassen(&add(&a11, &a12), &b22, crossover);
+    let m6 = strassen(&sub(&a21, &a11), &add(&b11, &b12), crossover);
+    let m7 = strassen(&sub(&a12, &a22), &add(&b21, &b22), crossover);
+
+    let mut product = Matrix::zeros(n, n);
+    product.set_block(0, 0, &add(&sub(&add(&m1, &m4), &m5), &m7));
+    product.set_block(0, h, &add(&m3, &m5));
+    product.set_block(h, 0, &add(&m2, &m4));
+    product.set_block(h, h, &add(&add(&sub(&m1, &m2), &m3), &m6));
+    product
+}
+
+impl<T> Index<(usize, usize)> for Matrix<T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        assert!(row < self.rows && col < self.cols, "({}, {}) out of a {}x{} matrix", row, col, self.rows, self.cols);
+        &self.data[row * self.cols + col]
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Matrix<T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        assert!(row < self.rows && col < self.cols, "({}, {}) out of a {}x{} matrix", row, col, self.rows, self.cols);
+        &mut self.data[row * self.cols + col]
+    }
+}
+
+// `&a * &b` uses the cache-oblivious algorithm, which needs no tuning.
+impl<T: Scalar> Mul for &Matrix<T> {
+    type Output = Matrix<T>;
+
+    fn mul(self, other: &Matrix<T>) -> Matrix<T> {
+        self.multiply_recursive(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use std::time::Instant;
+
+    // Deterministic pseudo-random matrix with elements in `-range..range`.
+    fn matrix(seed: u64, rows: usize, cols: usize, range: i64) -> Matrix<i64> {
+        Matrix::new(rows, cols, XorShift::new(seed).values(rows * cols, range))
+    }
+
+    #[test]
+    fn test_small_product() {
+        let a = Matrix::new(2, 3, vec![1, 2, 3, 4, 5, 6]);
+        let b = Matrix::new(3, 2, vec![7, 8, 9, 10, 11, 12]);
+        let expected = Matrix::new(2, 2, vec![58, 64, 139, 154]);
+
+        assert_eq!(a.multiply_naive(&b), expected);
+        assert_eq!(a.multiply_blocked(&b, 2), expected);
+        assert_eq!(a.multiply_recursive(&b), expected);
+        assert_eq!(a.multiply_strassen(&b, 1), expected);
+        assert_eq!(&a * &b, expected);
+    }
+
+    #[test]
+    fn test_identity_and_transpose() {
+        let a = matrix(1, 5, 7, 10);
+        assert_eq!(&Matrix::identity(5) * &a, a);
+        assert_eq!(a.multiply_strassen(&Matrix::identity(7), 2), a);
+
+        let t = a.transpose();
+        assert_eq!((t.rows(), t.cols()), (7, 5));
+        assert_eq!(t[(6, 4)], a[(4, 6)]);
+        assert_eq!(t.transpose(), a);
+    }
+
+    #[test]
+    fn test_floats() {
+        let a = Matrix::new(2, 2, vec![0.5, 1.5, -2.0, 4.0]);
+        let b = Matrix::new(2, 2, vec![2.0, 0.0, 1.0, 0.25]);
+        let expected = Matrix::new(2, 2, vec![2.5, 0.375, 0.0, 1.0]);
+        assert_eq!(a.multiply_strassen(&b, 1), expected);
+        assert_eq!(a.multiply_recursive(&b), expected);
+    }
+
+    #[test]
+    fn test_strassen_intermediates_out_of_range() {
+        // `b12 - b22` and friends go negative on unsigned types.
+        let a = Matrix::<u32>::new(2, 2, vec![1, 2, 3, 4]);
+        let b = Matrix::new(2, 2, vec![5, 6, 7, 8]);
+        assert_eq!(a.multiply_strassen(&b, 1), a.multiply_naive(&b));
+        assert_eq!(a.multiply_strassen(&b, 1).as_slice(), &[19, 22, 43, 50]);
+
+        // `a11 + a22` is 200, which doesn't fit in an `i8`, but the product does.
+        let a = Matrix::<i8>::new(2, 2, vec![100, 0, 0, 100]);
+        assert_eq!(a.multiply_strassen(&Matrix::identity(2), 1), a);
+
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        for n in 1..9 {
+            let a = Matrix::new(n, n, (0..n * n).map(|_| rng.below(4) as u8).collect());
+            let b = Matrix::new(n, n, (0..n * n).map(|_| rng.below(4) as u8).collect());
+            assert_eq!(a.multiply_strassen(&b, 1), a.multiply_naive(&b), "n = {}", n);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "can't multiply a 2x3 matrix by a 2x3 matrix")]
+    fn test_dimension_mismatch() {
+        let a = matrix(1, 2, 3, 10);
+        a.multiply_naive(&a);
+    }
+
+    #[test]
+    #[should_panic(expected = "a 2x2 matrix needs 4 elements")]
+    fn test_wrong_number_of_elements() {
+        Matrix::new(2, 2, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_all_algorithms_agree() {
+        let shapes = [(1, 1, 1), (3, 5, 2), (7, 7, 7), (16, 16, 16), (33, 17, 40), (64, 50, 31), (1, 100, 1)];
+        for (seed, &(n, m, p)) in shapes.iter().enumerate() {
+            let a = matrix(seed as u64 + 1, n, m, 100);
+            let b = matrix(seed as u64 + 100, m, p, 100);
+            let expected = a.multiply_naive(&b);
+
+            for block in [1, 3, 8, 64] {
+                assert_eq!(a.multiply_blocked(&b, block), expected, "{}x{}x{}, block {}", n, m, p, block);
+            }
+            assert_eq!(a.multiply_recursive(&b), expected, "{}x{}x{}", n, m, p);
+            for crossover in [2, 5, 16] {
+                assert_eq!(a.multiply_strassen(&b, crossover), expected, "{}x{}x{}, crossover {}", n, m, p, crossover);
+            }
+        }
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture` to compare the
+    // running times of the algorithms.
+    #[test]
+    #[ignore]
+    fn bench_multiplication() {
+        for n in [128, 256, 512, 1024] {
+            let a = matrix(1, n, n, 1000);
+            let b = matrix(2, n, n, 1000);
+
+            let start = Instant::now();
+            let expected = a.multiply_naive(&b);
+            println!("{}x{} naive: {:?}", n, n, start.elapsed());
+
+            let start = Instant::now();
+            assert_eq!(a.multiply_blocked(&b, 64), expected);
+            println!("{}x{} blocked: {:?}", n, n, start.elapsed());
+
+            let start = Instant::now();
+            assert_eq!(a.multiply_recursive(&b), expected);
+            println!("{}x{} recursive: {:?}", n, n, start.elapsed());
+
+            for crossover in [32, 64, 128] {
+                let start = Instant::now();
+                assert_eq!(a.multiply_strassen(&b, crossover), expected);
+                println!("{}x{} strassen, crossover {}: {:?}", n, n, crossover, start.elapsed());
+            }
+        }
+    }
+}
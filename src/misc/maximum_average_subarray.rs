@@ -0,0 +1,169 @@
+use super::maximum_subarray::{MaxSubarray, MaxSubarrayError};
+
+// Finds a subarray of at least `k` elements with the greatest average, which
+// is returned as its `sum`, like `MaxSubarray::linear` does for sums.
+// Time complexity: O(n log(range / precision)).
+//
+// The answer is binary searched: some subarray of at least `k` elements has
+// an average of at least `x` iff subtracting `x` from every element leaves a
+// subarray with a non-negative sum, which prefix sums check in O(n).
+//
+// Floating point can't tell apart averages that are too close, so the search
+// is finished with exact steps: while some subarray beats the current one,
+// measured in integers, it becomes the current one. There are usually none.
+// The last step also breaks ties like `MaxSubarray`: the subarray ending first
+// wins, then the shortest one.
+//
+// Returns `Empty` if `arr` is empty, and `InvalidLength` if `k` is 0 or
+// greater than the length of `arr`.
+pub fn maximum_average_subarray(arr: &[i32], k: usize) -> Result<MaxSubarray<f64>, MaxSubarrayError> {
+    if arr.is_empty() {
+        return Err(MaxSubarrayError::Empty);
+    }
+    if k == 0 || k > arr.len() {
+        return Err(MaxSubarrayError::InvalidLength);
+    }
+
+    let mut prefix = vec![0i64; arr.len() + 1];
+    for i in 0..arr.len() {
+        prefix[i + 1] = prefix[i] + i64::from(arr[i]);
+    }
+
+    let mut low = f64::from(*arr.iter().min().unwrap());
+    let mut high = f64::from(*arr.iter().max().unwrap());
+    // the average of every subarray is at least the smallest element
+    let mut found = (0, k);
+    for _ in 0..100 {
+        let mid = low + (high - low) / 2.0;
+        if mid <= low || mid >= high {
+            break;
+        }
+        match best_excess_f64(&prefix, k, mid) {
+            Some(range) => {
+                low = mid;
+                found = range;
+            }
+            None => high = mid,
+        }
+    }
+
+    // Exact refinement: compare `sum / len` against the current average
+    // `p / q` as `q * sum - p * len`.
+    loop {
+        let p = i128::from(prefix[found.1] - prefix[found.0]);
+        let q = (found.1 - found.0) as i128;
+        let (excess, range) = best_excess_exact(&prefix, k, p, q);
+        found = range;
+        if excess <= 0 {
+            break;
+        }
+    }
+
+    let (start, end) = found;
+    Ok(MaxSubarray {
+        start,
+        end: end - 1,
+        sum: (prefix[end] - prefix[start]) as f64 / (end - start) as f64,
+    })
+}
+
+// Returns a range `start..end` of at least `k` elements whose elements sum to
+// at least `x * len`, if there's one.
+fn best_excess_f64(prefix: &[i64], k: usize, x: f64) -> Option<(usize, usize)> {
+    let shifted = |i: usize| prefix[i] as f64 - x * i as f64;
+    let mut min_start = 0;
+
+    for end in k..prefix.len() {
+        if shifted(end - k) < shifted(min_start) {
+            min_start = end - k;
+        }
+        if shifted(end) - shifted(min_start) >= 0.0 {
+            return Some((min_start, end));
+        }
+    }
+    None
+}
+
+// Returns the greatest `q * sum - p * len` of a range of at least `k`
+// elements, and the range, the one ending first and then the shortest on ties.
+fn best_excess_exact(prefix: &[i64], k: usize, p: i128, q: i128) -> (i128, (usize, usize)) {
+    let shifted = |i: usize| q * i128::from(prefix[i]) - p * i as i128;
+    let mut min_start = 0;
+    let mut best = (shifted(k) - shifted(0), (0, k));
+
+    for end in k..prefix.len() {
+        if shifted(end - k) <= shifted(min_start) {
+            min_start = end - k;
+        }
+        let excess = shifted(end) - shifted(min_start);
+        if excess > best.0 {
+            best = (excess, (min_start, end));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    // The greatest average as an exact fraction `(sum, len)`.
+    fn brute_force(arr: &[i32], k: usize) -> (i64, i64) {
+        let mut best = (arr[..k].iter().map(|&x| i64::from(x)).sum(), k as i64);
+        for start in 0..arr.len() {
+            for end in start + k..=arr.len() {
+                let sum: i64 = arr[start..end].iter().map(|&x| i64::from(x)).sum();
+                let len = (end - start) as i64;
+                if sum * best.1 > best.0 * len {
+                    best = (sum, len);
+                }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_maximum_average_subarray() {
+        let arr = [1, 12, -5, -6, 50, 3];
+        let summary = |k| maximum_average_subarray(&arr, k).map(|max| (max.start, max.end, max.sum));
+        assert_eq!(summary(4), Ok((1, 4, 12.75)));
+        assert_eq!(summary(1), Ok((4, 4, 50.0)));
+        assert_eq!(summary(2), Ok((4, 5, 26.5)));
+
+        let max = maximum_average_subarray(&[-3, -1, -2], 2).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (1, 2, -1.5));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert_eq!(maximum_average_subarray(&[1, 2, 3], 4), Err(MaxSubarrayError::InvalidLength));
+        assert_eq!(maximum_average_subarray(&[1, 2, 3], 0), Err(MaxSubarrayError::InvalidLength));
+        assert_eq!(maximum_average_subarray(&[], 1), Err(MaxSubarrayError::Empty));
+    }
+
+    #[test]
+    fn test_large_values() {
+        let max = i32::MAX;
+        let arr = [max - 1, max, max - 1, max, max - 1, max, max - 2];
+        let best = maximum_average_subarray(&arr, 2).unwrap();
+        assert_eq!((best.start, best.end), (1, 3));
+        assert_eq!(best.sum, (3.0 * max as f64 - 1.0) / 3.0);
+    }
+
+    #[test]
+    fn test_agrees_with_brute_force() {
+        for seed in 1..300 {
+            let len = seed as usize % 12 + 1;
+            let arr: Vec<i32> = XorShift::new(seed).values(len, 50);
+            let k = seed as usize % len + 1;
+
+            let MaxSubarray { start, end, sum: average } = maximum_average_subarray(&arr, k).unwrap();
+            let (sum, count) = brute_force(&arr, k);
+            let found: i64 = arr[start..=end].iter().map(|&x| i64::from(x)).sum();
+            assert!(end + 1 - start >= k);
+            assert_eq!(found * count, sum * (end + 1 - start) as i64, "{:?} k = {}", arr, k);
+            assert_eq!(average, found as f64 / (end + 1 - start) as f64);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::maximum_subarray::{MaxSubarray, MaxSubarrayError};
+
+// An exact product of `i32` values, which can grow far beyond any primitive
+// integer: a sign and a magnitude in base 2^32, least significant limb first.
+// Zero has no limbs and is never negative.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigProduct {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigProduct {
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // Returns the product if it fits in an `i128`.
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.magnitude.len() > 4 {
+            return None;
+        }
+        let magnitude = self.magnitude.iter().rev().fold(0u128, |acc, &limb| (acc << 32) | u128::from(limb));
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    // Returns `self * x`.
+    fn times(&self, x: i32) -> Self {
+        if x == 0 || self.is_zero() {
+            return BigProduct::from(0);
+        }
+
+        let factor = u64::from(x.unsigned_abs());
+        let mut magnitude = Vec::with_capacity(self.magnitude.len() + 1);
+        let mut carry = 0u64;
+        for &limb in &self.magnitude {
+            let value = u64::from(limb) * factor + carry;
+            magnitude.push(value as u32);
+            carry = value >> 32;
+        }
+        if carry > 0 {
+            magnitude.push(carry as u32);
+        }
+
+        BigProduct {
+            negative: self.negative != (x < 0),
+            magnitude,
+        }
+    }
+
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        self.magnitude
+            .len()
+            .cmp(&other.magnitude.len())
+            .then_with(|| self.magnitude.iter().rev().cmp(other.magnitude.iter().rev()))
+    }
+}
+
+impl From<i32> for BigProduct {
+    fn from(x: i32) -> Self {
+        let magnitude = if x == 0 { Vec::new() } else { vec![x.unsigned_abs()] };
+        BigProduct {
+            negative: x < 0,
+            magnitude,
+        }
+    }
+}
+
+impl Ord for BigProduct {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+        }
+    }
+}
+
+impl PartialOrd for BigProduct {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigProduct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off groups of 9 decimal digits, least significant first.
+        const BASE: u64 = 1_000_000_000;
+        let mut magnitude = self.magnitude.clone();
+        let mut groups = Vec::new();
+        while !magnitude.is_empty() {
+            let mut remainder = 0u64;
+            for limb in magnitude.iter_mut().rev() {
+                let value = (remainder << 32) | u64::from(*limb);
+                *limb = (value / BASE) as u32;
+                remainder = value % BASE;
+            }
+            groups.push(remainder);
+            while magnitude.last() == Some(&0) {
+                magnitude.pop();
+            }
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", groups[groups.len() - 1])?;
+        for group in groups.iter().rev().skip(1) {
+            write!(f, "{:09}", group)?;
+        }
+        Ok(())
+    }
+}
+
+// Finds a subarray with the greatest product, which is returned as its `sum`,
+// like `MaxSubarray::linear` does for sums.
+// Time complexity: O(n * d), where `d` is the number of limbs of the products.
+//
+// A negative element turns the smallest product ending before it into the
+// greatest one, so both the greatest and the smallest products ending at each
+// index are tracked. Either can also restart at the current element, which
+// covers zeros. Ties go to the subarray ending first, then to the shortest.
+//
+// Returns `Empty` if `arr` is empty.
+pub fn maximum_product_subarray(arr: &[i32]) -> Result<MaxSubarray<BigProduct>, MaxSubarrayError> {
+    if arr.is_empty() {
+        return Err(MaxSubarrayError::Empty);
+    }
+
+    // the greatest and the smallest products ending at the current index,
+    // with their start
+    let mut max = (BigProduct::from(arr[0]), 0);
+    let mut min = max.clone();
+    let mut best = (0, 0, max.0.clone());
+
+    for (i, &x) in arr.iter().enumerate().skip(1) {
+        let candidates = [(BigProduct::from(x), i), (max.0.times(x), max.1), (min.0.times(x), min.1)];
+
+        // the first candidate, the shortest, wins ties
+        let mut new_max = candidates[0].clone();
+        let mut new_min = candidates[0].clone();
+        for candidate in &candidates[1..] {
+            if candidate.0 > new_max.0 {
+                new_max = candidate.clone();
+            }
+            if candidate.0 < new_min.0 {
+                new_min = candidate.clone();
+            }
+        }
+        max = new_max;
+        min = new_min;
+
+        if max.0 > best.2 {
+            best = (max.1, i, max.0.clone());
+        }
+    }
+
+    let (start, end, sum) = best;
+    Ok(MaxSubarray { start, end, sum })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn brute_force(arr: &[i32]) -> i128 {
+        let mut best = i128::MIN;
+        for start in 0..arr.len() {
+            let mut product = 1i128;
+            for &x in &arr[start..] {
+                product *= i128::from(x);
+                best = best.max(product);
+            }
+        }
+        best
+    }
+
+    fn product(arr: &[i32]) -> i128 {
+        arr.iter().map(|&x| i128::from(x)).product()
+    }
+
+    #[test]
+    fn test_maximum_product_subarray() {
+        let MaxSubarray { start, end, sum: max } = maximum_product_subarray(&[2, 3, -2, 4]).unwrap();
+        assert_eq!((start, end, max.to_i128()), (0, 1, Some(6)));
+
+        let MaxSubarray { start, end, sum: max } = maximum_product_subarray(&[-2, 3, -4]).unwrap();
+        assert_eq!((start, end, max.to_i128()), (0, 2, Some(24)));
+
+        let MaxSubarray { start, end, sum: max } = maximum_product_subarray(&[-2, 0, -1]).unwrap();
+        assert_eq!((start, end, max.to_i128()), (1, 1, Some(0)));
+
+        let MaxSubarray { start, end, sum: max } = maximum_product_subarray(&[-3]).unwrap();
+        assert_eq!((start, end, max.to_i128()), (0, 0, Some(-3)));
+    }
+
+    #[test]
+    fn test_huge_product() {
+        let mut arr = vec![-7, 0];
+        arr.extend(std::iter::repeat_n(-2, 131));
+
+        // an odd number of negatives: one of the ends is left out
+        let MaxSubarray { start, end, sum: max } = maximum_product_subarray(&arr).unwrap();
+        assert_eq!((start, end), (2, 131));
+        assert_eq!(max.to_i128(), None);
+        assert_eq!(max.to_string(), "1361129467683753853853498429727072845824");
+    }
+
+    #[test]
+    fn test_big_product_ordering() {
+        let big = BigProduct::from(i32::MIN).times(i32::MIN).times(-3);
+        assert_eq!(big.to_i128(), Some(-3 * (1i128 << 62)));
+        assert!(big.is_negative());
+        assert!(big < BigProduct::from(i32::MIN));
+        assert!(BigProduct::from(0) > big);
+        assert_eq!(big.to_string(), (-3 * (1i128 << 62)).to_string());
+        assert_eq!(BigProduct::from(-5).times(0), BigProduct::from(0));
+    }
+
+    #[test]
+    fn test_empty_slice() {
+        assert_eq!(maximum_product_subarray(&[]), Err(MaxSubarrayError::Empty));
+    }
+
+    #[test]
+    fn test_agrees_with_brute_force() {
+        for seed in 1..300 {
+            let arr: Vec<i32> = XorShift::new(seed).values(seed as usize % 12 + 1, 4);
+            let MaxSubarray { start, end, sum: max } = maximum_product_subarray(&arr).unwrap();
+            assert_eq!(max.to_i128(), Some(brute_force(&arr)), "{:?}", arr);
+            assert_eq!(product(&arr[start..=end]), brute_force(&arr));
+        }
+    }
+}
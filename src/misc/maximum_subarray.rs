@@ -1,136 +1,311 @@
-// Using the divide and conquer method, this function returns a tuple containing
-// the indices that demarcate a maximum subarray, along with the sum of the values
-// in a maximum subarray.
-pub fn divide_conquer_find_maximum_subarray(arr: &[i32]) -> (i32, usize, usize) {
+use std::fmt;
+use std::num::Wrapping;
+
+// Numeric types a maximum subarray can be computed over.
+//
+// Integers use checked arithmetic, so a sum that doesn't fit in the type is
+// reported instead of silently wrapping around. Floats never overflow (they
+// saturate to infinity), and `Wrapping<T>` opts into wrapping arithmetic.
+pub trait SubarraySum: Copy + PartialOrd {
+    fn zero() -> Self;
+
+    // Returns `None` if the sum overflows.
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_subarray_sum_int {
+    ($($t:ty)*) => ($(
+        impl SubarraySum for $t {
+            fn zero() -> Self {
+                0
+            }
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+        }
+
+        impl SubarraySum for Wrapping<$t> {
+            fn zero() -> Self {
+                Wrapping(0)
+            }
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                Some(self + other)
+            }
+        }
+    )*)
+}
+
+impl_subarray_sum_int! { i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize }
+
+macro_rules! impl_subarray_sum_float {
+    ($($t:ty)*) => ($(
+        impl SubarraySum for $t {
+            fn zero() -> Self {
+                0.0
+            }
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                Some(self + other)
+            }
+        }
+    )*)
+}
+
+impl_subarray_sum_float! { f32 f64 }
+
+// The reasons a maximum subarray can't be computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxSubarrayError {
+    // The slice has no subarray at all.
+    Empty,
+    // The maximum sum doesn't fit in the element type.
+    Overflow,
+    // The length asked for leaves no subarray to choose from.
+    InvalidLength,
+}
+
+impl fmt::Display for MaxSubarrayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaxSubarrayError::Empty => write!(f, "an empty slice has no maximum subarray"),
+            MaxSubarrayError::Overflow => write!(f, "the maximum subarray sum overflows"),
+            MaxSubarrayError::InvalidLength => write!(f, "no subarray has the length asked for"),
+        }
+    }
+}
+
+impl std::error::Error for MaxSubarrayError {}
+
+// A maximum subarray `arr[start..=end]` (both indices are inclusive) and its
+// sum.
+//
+// Several subarrays may share the maximum sum. All the algorithms below break
+// ties the same way: the subarray that ends first wins, and among those
+// ending at the same index, the shortest one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxSubarray<T> {
+    pub start: usize,
+    pub end: usize,
+    pub sum: T,
+}
+
+impl<T: SubarraySum> MaxSubarray<T> {
+    // Kadane's algorithm. Time complexity: O(n).
+    //
+    // This function use an "trick", that is the following: when the
+    // `current_sum` stops being positive, it means that all the previous
+    // elements in the subarray have a sum less or equal to zero, and including
+    // any more elements from that subarray would not increase the sum.
+    //
+    // In this case, it is more beneficial to start a new subarray from the
+    // current position, and discard the previous subarray.
+    pub fn linear(arr: &[T]) -> Result<Self, MaxSubarrayError> {
+        let mut best: Option<Self> = None;
+        let mut current_sum = T::zero();
+        let mut current_start = 0;
+
+        for i in 0..arr.len() {
+            // Do the trick: start a new subarray at `i` if the previous one
+            // doesn't help.
+            if i == 0 || current_sum <= T::zero() {
+                current_sum = arr[i];
+                current_start = i;
+            } else {
+                // The running sum is positive here, so only a positive
+                // element can overflow it, and then the maximum overflows too.
+                current_sum = current_sum.checked_add(arr[i]).ok_or(MaxSubarrayError::Overflow)?;
+            }
+
+            // Keep track of the maximum sum seen so far.
+            if best.is_none_or(|b| current_sum > b.sum) {
+                best = Some(Self {
+                    start: current_start,
+                    end: i,
+                    sum: current_sum,
+                });
+            }
+        }
+
+        best.ok_or(MaxSubarrayError::Empty)
+    }
+
+    // Compares every subarray, thus having a running time of O(n^2). It's the
+    // reference the faster algorithms are tested against.
+    pub fn brute_force(arr: &[T]) -> Result<Self, MaxSubarrayError> {
+        let mut best: Option<Self> = None;
+
+        // Iterates through all possible ending indices of the subarray.
+        for j in 0..arr.len() {
+            let mut current_sum = T::zero();
+
+            // Iterates through all possible starting indices of the subarray,
+            // from the shortest subarray to the longest.
+            for i in (0..=j).rev() {
+                current_sum = match current_sum.checked_add(arr[i]) {
+                    Some(sum) => sum,
+                    // Adding a positive element overflowed, so this subarray
+                    // and thus the maximum don't fit in the type.
+                    None if arr[i] > T::zero() => return Err(MaxSubarrayError::Overflow),
+                    // The sum went below the smallest value, so every longer
+                    // subarray is smaller than the one starting after `i`.
+                    None => break,
+                };
+
+                if best.is_none_or(|b| current_sum > b.sum) {
+                    best = Some(Self {
+                        start: i,
+                        end: j,
+                        sum: current_sum,
+                    });
+                }
+            }
+        }
+
+        best.ok_or(MaxSubarrayError::Empty)
+    }
+
+    // Using the divide and conquer method, this function returns the maximum
+    // subarray. Time complexity: O(n log n).
+    pub fn divide_conquer(arr: &[T]) -> Result<Self, MaxSubarrayError> {
+        if arr.is_empty() {
+            return Err(MaxSubarrayError::Empty);
+        }
+        divide_conquer(arr, 0)
+    }
+
+    // Returns true if `self` should be preferred over `other`.
+    pub(super) fn is_better_than(&self, other: &Self) -> bool {
+        if self.sum != other.sum {
+            return self.sum > other.sum;
+        }
+        (self.end, std::cmp::Reverse(self.start)) < (other.end, std::cmp::Reverse(other.start))
+    }
+}
+
+// Divide and conquer over the non-empty `arr`, whose first element has index
+// `offset` in the original slice.
+fn divide_conquer<T: SubarraySum>(arr: &[T], offset: usize) -> Result<MaxSubarray<T>, MaxSubarrayError> {
     // Get array length.
     let n = arr.len();
 
     // Base case -> only one item in the array.
     if n == 1 {
-        return (arr[0], 0, 0);
+        return Ok(MaxSubarray {
+            start: offset,
+            end: offset,
+            sum: arr[0],
+        });
     }
 
     // Get the mid index (integer division -> 3 / 2 = 1 ).
     let mid = n / 2;
-    
+
     // Conquer by recursively finding maximum subarrays within the left and right subarrays.
-    let (left_sum, left_low, left_high) = divide_conquer_find_maximum_subarray(&arr[..mid]);
-    let (right_sum, right_low, right_high) = divide_conquer_find_maximum_subarray(&arr[mid..]);
+    let left = divide_conquer(&arr[..mid], offset)?;
+    let right = divide_conquer(&arr[mid..], offset + mid)?;
 
     // Find a maximum subarray that crosses the midpoint.
-    let (cross_sum, cross_low, cross_high) = find_maximum_crossing_subarray(arr, mid);
+    let cross = find_maximum_crossing_subarray(arr, mid, offset)?;
 
     // Any contiguous subarray `sub_arr[i..j]` of a `arr[low..high]` must lie
-    // in exactly one of the following places: 
+    // in exactly one of the following places:
     // 1. Entirely in the subarray `arr[low..mid`.
     // 2. Entirely in the subarray `arr[mid + 1..high]`.
     // 3. Crossing the midpoint.
-    // Thus, this if statement check where the contiguous subarray is. It's the "combine" step.
-    if left_sum >= right_sum && left_sum >= cross_sum {
-        return (left_sum, left_low, left_high);
-    } else if right_sum >= left_sum && right_sum >= cross_sum {
-        return (right_sum, right_low + mid, right_high + mid);
-    } else {
-        return (cross_sum, cross_low, cross_high);
+    // Thus, this picks the best of the three. It's the "combine" step.
+    let mut best = left;
+    for candidate in [right].into_iter().chain(cross) {
+        if candidate.is_better_than(&best) {
+            best = candidate;
+        }
     }
+    return Ok(best);
 }
 
-// Returns a tuple containing the indices demarcating a maximum subarray that
-// crosses the midpoint, along with the sum of the values in a maximum array.
+// Returns a maximum subarray that crosses the midpoint, i.e. a maximum suffix
+// of `arr[..mid]` followed by a maximum prefix of `arr[mid..]`, or `None` if
+// its sum is below the smallest value of the type (then it can't be the
+// maximum anyway).
+//
+// Both halves are scanned towards the midpoint with Kadane's trick, so every
+// running sum is the sum of an actual subarray and can only overflow if the
+// maximum does.
 //
-// It's a complementary function for `divide_conquer_find_maximum_subarray`.
-fn find_maximum_crossing_subarray(arr: &[i32], mid: usize) -> (i32, usize, usize) {
-    // Holds the greatest sum found in the left half
-    let (mut left_sum, mut left_index) = (i32::min_value(), mid);
-    let mut sum = 0;
-    for i in (0..mid).rev() {
-        sum += arr[i];
-        if sum > left_sum {
-            left_sum = sum;
+// It's a complementary function for `divide_conquer`.
+fn find_maximum_crossing_subarray<T: SubarraySum>(
+    arr: &[T],
+    mid: usize,
+    offset: usize,
+) -> Result<Option<MaxSubarray<T>>, MaxSubarrayError> {
+    // Holds the greatest sum of a suffix of the left half. The suffix is
+    // restarted whenever what comes before doesn't help, which also keeps it
+    // as short as possible.
+    let (mut left_sum, mut left_index) = (arr[0], 0);
+    for i in 1..mid {
+        if left_sum <= T::zero() {
+            left_sum = arr[i];
             left_index = i;
+        } else {
+            left_sum = left_sum.checked_add(arr[i]).ok_or(MaxSubarrayError::Overflow)?;
         }
     }
 
-    // Holds the greatest sum found in the right half.
-    let (mut right_sum, mut right_index) = (i32::min_value(), mid);
-    sum = 0;
-    for i in mid..arr.len() {
-        sum += arr[i];
-        if sum > right_sum {
-            right_sum = sum;
+    // Holds the greatest sum of a prefix of the right half, scanning it from
+    // its end, and keeping the prefix as short as possible.
+    let (mut right_sum, mut right_index) = (arr[arr.len() - 1], arr.len() - 1);
+    for i in (mid..arr.len() - 1).rev() {
+        if right_sum <= T::zero() {
+            right_sum = arr[i];
             right_index = i;
+        } else {
+            right_sum = arr[i].checked_add(right_sum).ok_or(MaxSubarrayError::Overflow)?;
         }
     }
 
-    (left_sum + right_sum, left_index, right_index)
+    match left_sum.checked_add(right_sum) {
+        Some(sum) => Ok(Some(MaxSubarray {
+            start: offset + left_index,
+            end: offset + right_index,
+            sum,
+        })),
+        None if right_sum > T::zero() => Err(MaxSubarrayError::Overflow),
+        None => Ok(None),
+    }
 }
 
-// This function use an "trick", that is the following: when the `current_sum`
-// becomes negative, it means that all the previous elements in the subarray have
-// a sum less than zero, and including any more elements from that subarray would
-// further decrease the sum instead of increasing it.
+// Thin wrapper over `MaxSubarray::divide_conquer`, returning the sum and the
+// indices that demarcate a maximum subarray.
 //
-// In this case, it is more beneficial to start a new subarray from the current
-// position, and discard the previous subarray with the negative sum.
-pub fn linear_find_maximum_subarray(arr: &[i32]) -> (usize, usize, i32) {
-    let mut max_sum = std::i32::MIN;
-    let mut left_index = 0;
-    let mut right_index = 0;
-    let mut current_sum = 0;
-
-    for i in 0..arr.len() {
-        // Keep track of the maximum sum seen so far.
-        current_sum += arr[i];
-        if current_sum > max_sum {
-            max_sum = current_sum;
-            right_index = i;
-        }
-        
-        // Do the trick: start a new array starting from `i + 1` (the next item),
-        // and discard the previous subarray.
-        if current_sum < 0 {
-            current_sum = 0;
-            left_index = i + 1;
-        }
-    }
+// Panics if `arr` is empty or if the maximum sum overflows.
+pub fn divide_conquer_find_maximum_subarray(arr: &[i32]) -> (i32, usize, usize) {
+    let max = MaxSubarray::divide_conquer(arr).unwrap_or_else(|e| panic!("{}", e));
+    (max.sum, max.start, max.end)
+}
 
-    (left_index, right_index, max_sum)
+// Thin wrapper over `MaxSubarray::linear`, returning the indices that
+// demarcate a maximum subarray and its sum.
+//
+// Panics if `arr` is empty or if the maximum sum overflows.
+pub fn linear_find_maximum_subarray(arr: &[i32]) -> (usize, usize, i32) {
+    let max = MaxSubarray::linear(arr).unwrap_or_else(|e| panic!("{}", e));
+    (max.start, max.end, max.sum)
 }
 
-// This function has the same signature as the others, but in this case,
-// the implementation compare every pair in the array, thus having a runnig time 
-// of O(n^2).
+// Thin wrapper over `MaxSubarray::brute_force`, with the same signature as
+// `linear_find_maximum_subarray`.
+//
+// Panics if `arr` is empty or if the maximum sum overflows.
 pub fn brute_force_find_maximum_subarray(arr: &[i32]) -> (usize, usize, i32) {
-    let mut max_sum = std::i32::MIN;
-    let mut left_index = 0;
-    let mut right_index = 0;
-
-    // Iterates through all possible starting indices of the subarray.
-    for i in 0..arr.len() {
-        let mut current_sum = 0;
-
-        // Iterates through all possible ending indices of the subarray,
-        // starting from the current starting index.
-        for j in i..arr.len() {
-
-            // Calculate the current sum of the subarray between the indices
-            // i and j by adding the current element to current_sum and compare
-            // it to the current maximum sum.
-            current_sum += arr[j];
-            if current_sum > max_sum {
-                max_sum = current_sum;
-                left_index = i;
-                right_index = j;
-            }
-        }
-    }
-
-    (left_index, right_index, max_sum)
+    let max = MaxSubarray::brute_force(arr).unwrap_or_else(|e| panic!("{}", e));
+    (max.start, max.end, max.sum)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_rng::XorShift;
 
     #[test]
     fn test_brute_force_max_subarray() {
@@ -185,4 +360,98 @@ mod tests {
         assert_eq!(start, 0);
         assert_eq!(sum, 1);
     }
+
+    #[test]
+    fn test_linear_start_of_best_run() {
+        // a later run must not move the start of the best subarray
+        let arr = [5, -10, 1];
+        assert_eq!(linear_find_maximum_subarray(&arr), (0, 0, 5));
+    }
+
+    #[test]
+    fn test_empty_slice() {
+        let arr: [i32; 0] = [];
+        assert_eq!(MaxSubarray::linear(&arr), Err(MaxSubarrayError::Empty));
+        assert_eq!(MaxSubarray::brute_force(&arr), Err(MaxSubarrayError::Empty));
+        assert_eq!(MaxSubarray::divide_conquer(&arr), Err(MaxSubarrayError::Empty));
+    }
+
+    #[test]
+    #[should_panic(expected = "an empty slice has no maximum subarray")]
+    fn test_wrapper_panics_on_empty_slice() {
+        linear_find_maximum_subarray(&[]);
+    }
+
+    #[test]
+    fn test_all_negative() {
+        let arr = [-3, -1, -2, -1];
+        let expected = MaxSubarray { start: 1, end: 1, sum: -1 };
+        assert_eq!(MaxSubarray::linear(&arr), Ok(expected));
+        assert_eq!(MaxSubarray::brute_force(&arr), Ok(expected));
+        assert_eq!(MaxSubarray::divide_conquer(&arr), Ok(expected));
+    }
+
+    #[test]
+    fn test_overflow_is_reported() {
+        let arr: [i8; 3] = [100, -1, 100];
+        assert_eq!(MaxSubarray::linear(&arr), Err(MaxSubarrayError::Overflow));
+        assert_eq!(MaxSubarray::brute_force(&arr), Err(MaxSubarrayError::Overflow));
+        assert_eq!(MaxSubarray::divide_conquer(&arr), Err(MaxSubarrayError::Overflow));
+    }
+
+    #[test]
+    fn test_negative_sums_below_the_minimum_are_not_an_overflow() {
+        // the sum of the whole slice is below `i8::MIN`, but the maximum fits
+        let arr: [i8; 5] = [-100, -100, 120, -100, -100];
+        let expected = MaxSubarray { start: 2, end: 2, sum: 120 };
+        assert_eq!(MaxSubarray::linear(&arr), Ok(expected));
+        assert_eq!(MaxSubarray::brute_force(&arr), Ok(expected));
+        assert_eq!(MaxSubarray::divide_conquer(&arr), Ok(expected));
+
+        let arr: [i8; 3] = [127, -100, -100];
+        assert_eq!(MaxSubarray::divide_conquer(&arr).unwrap().sum, 127);
+    }
+
+    #[test]
+    fn test_wrapping_arithmetic() {
+        let arr = [Wrapping(100i8), Wrapping(-1), Wrapping(100)];
+        // 100 - 1 + 100 wraps around to -57, so the best is a single 100
+        let max = MaxSubarray::linear(&arr).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (0, 0, Wrapping(100)));
+    }
+
+    #[test]
+    fn test_floats() {
+        let arr = [-0.5, 1.25, -0.25, 2.0, -3.0];
+        let max = MaxSubarray::divide_conquer(&arr).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (1, 3, 3.0));
+
+        let arr = [1.5f32, -2.0, 0.75];
+        assert_eq!(MaxSubarray::linear(&arr).unwrap().sum, 1.5);
+    }
+
+    #[test]
+    fn test_every_integer_width() {
+        macro_rules! check {
+            ($($t:ty)*) => ($(
+                let arr: [$t; 6] = [-2, 3, -1, 4, -10, 2];
+                let expected = MaxSubarray { start: 1, end: 3, sum: 6 as $t };
+                assert_eq!(MaxSubarray::linear(&arr), Ok(expected));
+                assert_eq!(MaxSubarray::brute_force(&arr), Ok(expected));
+                assert_eq!(MaxSubarray::divide_conquer(&arr), Ok(expected));
+            )*)
+        }
+        check! { i8 i16 i32 i64 i128 isize }
+    }
+
+    #[test]
+    fn test_algorithms_agree() {
+        for seed in 1..200 {
+            // small values make ties, and thus the tie breaking, frequent
+            let arr: Vec<i64> = XorShift::new(seed).values(seed as usize % 40 + 1, 4);
+            let expected = MaxSubarray::brute_force(&arr);
+            assert_eq!(MaxSubarray::linear(&arr), expected, "{:?}", arr);
+            assert_eq!(MaxSubarray::divide_conquer(&arr), expected, "{:?}", arr);
+        }
+    }
 }
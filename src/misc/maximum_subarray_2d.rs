@@ -0,0 +1,341 @@
+use super::maximum_subarray::{MaxSubarray, MaxSubarrayError, SubarraySum};
+
+// A maximum sum rectangle of a row-major matrix, spanning the rows
+// `top..=bottom` and the columns `left..=right` (all indices are inclusive),
+// and its sum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxRectangle<T> {
+    pub top: usize,
+    pub left: usize,
+    pub bottom: usize,
+    pub right: usize,
+    pub sum: T,
+}
+
+impl<T: SubarraySum> MaxRectangle<T> {
+    // Finds a maximum sum rectangle of the row-major `matrix` with `cols`
+    // columns. Time complexity: O(rows^2 * cols).
+    //
+    // For every pair of rows `top <= bottom`, the rows in between are
+    // compressed into a single row holding the sum of each column. A
+    // rectangle spanning exactly those rows is then a subarray of the
+    // compressed row, so the best one is found with Kadane's algorithm
+    // (`MaxSubarray::linear`). The compressed row is updated in O(cols) when
+    // `bottom` moves down.
+    //
+    // This is faster when the matrix has fewer rows than columns, so a tall
+    // matrix is better passed transposed.
+    pub fn kadane(matrix: &[T], cols: usize) -> Result<Self, MaxSubarrayError> {
+        let rows = dimensions(matrix, cols)?;
+        let mut best: Option<Self> = None;
+
+        for top in 0..rows {
+            let mut compressed = vec![ColumnSum::new(); cols];
+
+            for bottom in top..rows {
+                for (column, &value) in compressed.iter_mut().zip(&matrix[bottom * cols..(bottom + 1) * cols]) {
+                    column.add(value)?;
+                }
+
+                // Columns whose sum is below the smallest value of `T` can't be
+                // part of a maximum spanning these rows, so they split the
+                // compressed row in runs that are solved independently.
+                let mut start = 0;
+                while start < cols {
+                    let run: Vec<T> = compressed[start..].iter().map_while(ColumnSum::get).collect();
+                    if !run.is_empty() {
+                        let max = MaxSubarray::linear(&run)?;
+                        if best.is_none_or(|b| max.sum > b.sum) {
+                            best = Some(Self {
+                                top,
+                                left: start + max.start,
+                                bottom,
+                                right: start + max.end,
+                                sum: max.sum,
+                            });
+                        }
+                    }
+                    start += run.len() + 1;
+                }
+            }
+        }
+
+        best.ok_or(MaxSubarrayError::Empty)
+    }
+
+    // Compares every rectangle, summing each one from scratch, thus having a
+    // running time of O(rows^3 * cols^3). It's the reference `kadane` is
+    // tested against.
+    pub fn brute_force(matrix: &[T], cols: usize) -> Result<Self, MaxSubarrayError> {
+        let rows = dimensions(matrix, cols)?;
+        let mut best: Option<Self> = None;
+
+        for top in 0..rows {
+            for bottom in top..rows {
+                for left in 0..cols {
+                    for right in left..cols {
+                        let cells = (top..=bottom).flat_map(|row| &matrix[row * cols + left..=row * cols + right]);
+                        // a rectangle below the smallest value loses to any
+                        // single cell
+                        let sum = match exact_sum(cells.copied())? {
+                            Some(sum) => sum,
+                            None => continue,
+                        };
+
+                        if best.is_none_or(|b| sum > b.sum) {
+                            best = Some(Self {
+                                top,
+                                left,
+                                bottom,
+                                right,
+                                sum,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        best.ok_or(MaxSubarrayError::Empty)
+    }
+}
+
+// Returns the number of rows of the matrix.
+fn dimensions<T>(matrix: &[T], cols: usize) -> Result<usize, MaxSubarrayError> {
+    if matrix.is_empty() || cols == 0 {
+        return Err(MaxSubarrayError::Empty);
+    }
+    assert_eq!(matrix.len() % cols, 0, "the matrix has {} columns", cols);
+    Ok(matrix.len() / cols)
+}
+
+// The sum of a column over the rows from `top` down to the current one, which
+// may not fit in `T`.
+//
+// A sum that doesn't fit is kept exactly, as `sum` plus the `carry` values,
+// which all have the same sign as the overflow. Rows added later can bring it
+// back into range, and then `sum` absorbs the carry again.
+//
+// While the sum is below the smallest value of `T`, the column can be skipped:
+// a rectangle containing it is the column plus at most two rectangles on its
+// sides, each summing to at most the maximum `M`, so it sums to less than
+// `MIN + 2 * M`, which is less than `M` for any `M <= MAX`.
+#[derive(Clone)]
+struct ColumnSum<T> {
+    sum: T,
+    carry: Vec<T>,
+}
+
+impl<T: SubarraySum> ColumnSum<T> {
+    fn new() -> Self {
+        Self {
+            sum: T::zero(),
+            carry: Vec::new(),
+        }
+    }
+
+    // Takes O(1) amortized time, since each value is carried at most once.
+    fn add(&mut self, value: T) -> Result<(), MaxSubarrayError> {
+        self.sum = match self.sum.checked_add(value) {
+            Some(sum) => sum,
+            None => {
+                self.carry.push(self.sum);
+                value
+            }
+        };
+        // A carried value and a sum of the other sign always add up, so the
+        // carry only stays while the total is out of range.
+        while let Some(&carried) = self.carry.last() {
+            match carried.checked_add(self.sum) {
+                Some(sum) => {
+                    self.sum = sum;
+                    self.carry.pop();
+                }
+                None => break,
+            }
+        }
+        // this column alone is a rectangle that doesn't fit
+        if self.carry.first().is_some_and(|&carried| carried > T::zero()) {
+            return Err(MaxSubarrayError::Overflow);
+        }
+        Ok(())
+    }
+
+    // Returns the sum, or `None` if it's below the smallest value of `T`.
+    fn get(&self) -> Option<T> {
+        if self.carry.is_empty() {
+            Some(self.sum)
+        } else {
+            None
+        }
+    }
+}
+
+// Returns the sum of `values`, `None` if it's below the smallest value of `T`,
+// or `Overflow` if it's above the largest.
+//
+// The values are added in an order that keeps every partial sum in range
+// while the total is: a negative value while the sum is non-negative, and a
+// positive one otherwise. Once one sign runs out, the partial sums move
+// steadily toward the total.
+fn exact_sum<T: SubarraySum>(values: impl Iterator<Item = T>) -> Result<Option<T>, MaxSubarrayError> {
+    let (mut positive, mut negative): (Vec<T>, Vec<T>) = values.partition(|&value| value > T::zero());
+    let mut sum = T::zero();
+
+    loop {
+        let value = if sum >= T::zero() {
+            negative.pop().or_else(|| positive.pop())
+        } else {
+            positive.pop().or_else(|| negative.pop())
+        };
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(Some(sum)),
+        };
+        sum = match sum.checked_add(value) {
+            Some(sum) => sum,
+            None if value > T::zero() => return Err(MaxSubarrayError::Overflow),
+            None => return Ok(None),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn rectangle_sum(matrix: &[i64], cols: usize, r: &MaxRectangle<i64>) -> i64 {
+        let mut sum = 0;
+        for row in r.top..=r.bottom {
+            for col in r.left..=r.right {
+                sum += matrix[row * cols + col];
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn test_classic_example() {
+        let matrix = [
+            1, 2, -1, -4, -20, //
+            -8, -3, 4, 2, 1, //
+            3, 8, 10, 1, 3, //
+            -4, -1, 1, 7, -6,
+        ];
+        let expected = MaxRectangle {
+            top: 1,
+            left: 1,
+            bottom: 3,
+            right: 3,
+            sum: 29,
+        };
+        assert_eq!(MaxRectangle::kadane(&matrix, 5), Ok(expected));
+        assert_eq!(MaxRectangle::brute_force(&matrix, 5), Ok(expected));
+    }
+
+    #[test]
+    fn test_single_row_and_column() {
+        let row = [-2, 1, -3, 4, -1, 2, 1, -5];
+        let max = MaxRectangle::kadane(&row, 8).unwrap();
+        assert_eq!((max.left, max.right, max.sum), (3, 6, 6));
+
+        let max = MaxRectangle::kadane(&row, 1).unwrap();
+        assert_eq!((max.top, max.bottom, max.sum), (3, 6, 6));
+    }
+
+    #[test]
+    fn test_all_negative() {
+        let matrix = [-5, -3, -9, -2, -7, -4];
+        let max = MaxRectangle::kadane(&matrix, 3).unwrap();
+        assert_eq!((max.top, max.left, max.sum), (1, 0, -2));
+    }
+
+    #[test]
+    fn test_heatmap_hotspot() {
+        let heatmap = [
+            0.1, 0.0, 0.2, 0.1, //
+            0.0, 0.9, 0.8, -0.5, //
+            -0.3, 0.7, 0.9, 0.0, //
+            0.1, -0.2, 0.0, 0.1,
+        ];
+        let max = MaxRectangle::kadane(&heatmap, 4).unwrap();
+        assert_eq!((max.top, max.left, max.bottom, max.right), (0, 1, 2, 2));
+    }
+
+    #[test]
+    fn test_empty_matrix() {
+        let matrix: [i32; 0] = [];
+        assert_eq!(MaxRectangle::kadane(&matrix, 3), Err(MaxSubarrayError::Empty));
+        assert_eq!(MaxRectangle::brute_force(&[1, 2], 0), Err(MaxSubarrayError::Empty));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let matrix: [i8; 4] = [100, -128, 100, -128];
+        assert_eq!(MaxRectangle::kadane(&matrix, 2), Err(MaxSubarrayError::Overflow));
+        assert_eq!(MaxRectangle::brute_force(&matrix, 2), Err(MaxSubarrayError::Overflow));
+
+        // the column sums go below `i8::MIN`, but the maximum fits
+        let matrix: [i8; 6] = [-100, 50, -100, 50, 1, -100];
+        assert_eq!(MaxRectangle::kadane(&matrix, 2).unwrap().sum, 100);
+        assert_eq!(MaxRectangle::brute_force(&matrix, 2).unwrap().sum, 100);
+    }
+
+    #[test]
+    fn test_column_comes_back_into_range() {
+        // The middle column drops to -200 over the first two rows, below
+        // `i8::MIN`, but the third row brings it back to -100, and the whole
+        // matrix is the maximum.
+        let matrix: [i8; 9] = [55, -100, 55, 55, -100, 55, 0, 100, 0];
+        let expected = MaxRectangle {
+            top: 0,
+            left: 0,
+            bottom: 2,
+            right: 2,
+            sum: 120,
+        };
+        assert_eq!(MaxRectangle::kadane(&matrix, 3), Ok(expected));
+        assert_eq!(MaxRectangle::brute_force(&matrix, 3), Ok(expected));
+
+        let wide: Vec<i32> = matrix.iter().map(|&x| i32::from(x)).collect();
+        assert_eq!(MaxRectangle::kadane(&wide, 3).unwrap().sum, 120);
+    }
+
+    #[test]
+    fn test_narrow_type_agrees_with_wide_type() {
+        // Whenever the maximum fits in `i8`, it's the same as over `i64`,
+        // however far the column sums stray out of range on the way.
+        for seed in 1..300 {
+            let cols = seed as usize % 4 + 1;
+            let rows = seed as usize % 5 + 1;
+            let wide: Vec<i64> = XorShift::new(seed).values(rows * cols, 100);
+            let narrow: Vec<i8> = wide.iter().map(|&x| x as i8).collect();
+
+            let expected = MaxRectangle::brute_force(&wide, cols).unwrap().sum;
+            let result = if expected > i64::from(i8::MAX) {
+                Err(MaxSubarrayError::Overflow)
+            } else {
+                Ok(i64::from(expected as i8))
+            };
+            let kadane = MaxRectangle::kadane(&narrow, cols).map(|max| i64::from(max.sum));
+            let brute_force = MaxRectangle::brute_force(&narrow, cols).map(|max| i64::from(max.sum));
+            assert_eq!(kadane, result, "{:?}", narrow);
+            assert_eq!(brute_force, result, "{:?}", narrow);
+        }
+    }
+
+    #[test]
+    fn test_kadane_agrees_with_brute_force() {
+        for seed in 1..100 {
+            let cols = seed as usize % 6 + 1;
+            let rows = seed as usize % 5 + 1;
+            let matrix: Vec<i64> = XorShift::new(seed).values(rows * cols, 5);
+
+            let fast = MaxRectangle::kadane(&matrix, cols).unwrap();
+            let slow = MaxRectangle::brute_force(&matrix, cols).unwrap();
+            assert_eq!(fast.sum, slow.sum, "{:?}", matrix);
+            assert_eq!(rectangle_sum(&matrix, cols, &fast), fast.sum);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::thread;
+
+use super::maximum_subarray::MaxSubarray;
+use super::maximum_subarray_segment_tree::Node;
+
+// Below this many elements a chunk isn't worth a thread of its own.
+const MIN_CHUNK: usize = 1 << 16;
+
+// Parallel version of `divide_conquer_find_maximum_subarray`, returning the
+// sum and the indices that demarcate a maximum subarray, using up to
+// `threads` threads. Time complexity: O(n / threads + threads).
+//
+// Each chunk is summarized in a single pass by its total, its best prefix,
+// its best suffix and its best subarray. Two neighbouring summaries combine in
+// O(1), so the crossing sums are never recomputed. Ties are broken like the
+// sequential version, so both return the same subarray. Sums are `i64`, so
+// they can't overflow.
+//
+// Panics if `arr` is empty.
+pub fn parallel_find_maximum_subarray(arr: &[i32], threads: usize) -> (i64, usize, usize) {
+    let max = parallel(arr, threads, MIN_CHUNK);
+    (max.sum, max.start, max.end)
+}
+
+fn parallel(arr: &[i32], threads: usize, min_chunk: usize) -> MaxSubarray<i64> {
+    assert!(!arr.is_empty(), "an empty slice has no maximum subarray");
+    solve(arr, 0, threads.max(1), min_chunk.max(1)).best
+}
+
+// Summarizes `arr`, whose first element has index `offset`, with `threads`
+// threads including the current one.
+fn solve(arr: &[i32], offset: usize, threads: usize, min_chunk: usize) -> Node {
+    if threads == 1 || arr.len() < 2 * min_chunk {
+        return summarize(arr, offset);
+    }
+
+    // Split the threads in two, and the elements in proportion.
+    let left_threads = threads / 2;
+    let mid = arr.len() * left_threads / threads;
+    let (left, right) = arr.split_at(mid);
+
+    thread::scope(|scope| {
+        let left = scope.spawn(|| solve(left, offset, left_threads, min_chunk));
+        let right = solve(right, offset + mid, threads - left_threads, min_chunk);
+        left.join().unwrap().combine(&right)
+    })
+}
+
+// Summarizes the non-empty `arr` in one pass, breaking ties like `Node`.
+fn summarize(arr: &[i32], offset: usize) -> Node {
+    let first = i64::from(arr[0]);
+    let mut total = first;
+    let mut prefix = (first, offset);
+    let mut current = MaxSubarray {
+        start: offset,
+        end: offset,
+        sum: first,
+    };
+    let mut best = current;
+
+    for (i, &x) in arr.iter().enumerate().skip(1) {
+        let (index, x) = (offset + i, i64::from(x));
+        total += x;
+        if total > prefix.0 {
+            prefix = (total, index);
+        }
+
+        // Kadane's algorithm, which also yields the best suffix at the end.
+        if current.sum > 0 {
+            current.sum += x;
+        } else {
+            current.sum = x;
+            current.start = index;
+        }
+        current.end = index;
+        if current.sum > best.sum {
+            best = current;
+        }
+    }
+
+    // The run Kadane's algorithm ends with is a best suffix, and the shortest
+    // one, since a run restarts whenever what comes before sums to 0 or less.
+    Node {
+        total,
+        prefix,
+        suffix: (current.sum, current.start),
+        best,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::divide_conquer_find_maximum_subarray;
+    use super::*;
+    use crate::test_rng::XorShift;
+    use std::time::Instant;
+
+    fn sequential(arr: &[i32]) -> (i64, usize, usize) {
+        let (sum, start, end) = divide_conquer_find_maximum_subarray(arr);
+        (i64::from(sum), start, end)
+    }
+
+    #[test]
+    fn test_parallel() {
+        let arr = [-2, 1, -3, 4, -1, 2, 1, -5, 4];
+        assert_eq!(parallel_find_maximum_subarray(&arr, 4), (6, 3, 6));
+        for threads in 1..=9 {
+            let max = parallel(&arr, threads, 1);
+            assert_eq!((max.sum, max.start, max.end), (6, 3, 6));
+        }
+    }
+
+    #[test]
+    fn test_sums_beyond_i32() {
+        let arr = vec![i32::MAX; 10];
+        assert_eq!(parallel_find_maximum_subarray(&arr, 3), (10 * i64::from(i32::MAX), 0, 9));
+    }
+
+    #[test]
+    #[should_panic(expected = "an empty slice has no maximum subarray")]
+    fn test_empty_slice() {
+        parallel_find_maximum_subarray(&[], 4);
+    }
+
+    #[test]
+    fn test_agrees_with_sequential() {
+        for seed in 1..100 {
+            // small ranges make many ties
+            let arr: Vec<i32> = XorShift::new(seed).values(seed as usize * 7 % 200 + 1, seed as i64 % 5 + 1);
+            let expected = sequential(&arr);
+            for (threads, min_chunk) in [(1, 1), (2, 1), (3, 2), (8, 1), (5, 16)] {
+                let max = parallel(&arr, threads, min_chunk);
+                assert_eq!((max.sum, max.start, max.end), expected, "{:?} with {} threads", arr, threads);
+            }
+        }
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture` to compare the
+    // running times on 100 million elements.
+    #[test]
+    #[ignore]
+    fn bench_parallel_speedup() {
+        let arr: Vec<i32> = XorShift::new(42).values(100_000_000, 1000);
+        let threads = thread::available_parallelism().map_or(4, |n| n.get());
+
+        let start = Instant::now();
+        let expected = sequential(&arr);
+        println!("divide and conquer: {:?}", start.elapsed());
+
+        for threads in [1, 2, threads] {
+            let start = Instant::now();
+            let max = parallel_find_maximum_subarray(&arr, threads);
+            println!("parallel, {} threads: {:?}", threads, start.elapsed());
+            assert_eq!(max, expected);
+        }
+    }
+}
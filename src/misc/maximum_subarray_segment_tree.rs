@@ -0,0 +1,249 @@
+use super::maximum_subarray::MaxSubarray;
+
+// Summary of a segment `arr[low..=high]`, enough to combine two neighbouring
+// segments like `find_maximum_crossing_subarray` combines two halves.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Node {
+    pub(super) total: i64,
+    // greatest sum of a prefix and its last index, the shortest on ties
+    pub(super) prefix: (i64, usize),
+    // greatest sum of a suffix and its first index, the shortest on ties
+    pub(super) suffix: (i64, usize),
+    pub(super) best: MaxSubarray<i64>,
+}
+
+impl Node {
+    pub(super) fn leaf(index: usize, value: i32) -> Self {
+        let value = i64::from(value);
+        Node {
+            total: value,
+            prefix: (value, index),
+            suffix: (value, index),
+            best: MaxSubarray {
+                start: index,
+                end: index,
+                sum: value,
+            },
+        }
+    }
+
+    // Combines the summaries of `arr[a..=b]` (self) and `arr[b + 1..=c]`.
+    pub(super) fn combine(&self, right: &Node) -> Node {
+        let (right_prefix, right_end) = right.prefix;
+        let prefix = if self.total + right_prefix > self.prefix.0 {
+            (self.total + right_prefix, right_end)
+        } else {
+            self.prefix
+        };
+
+        let (left_suffix, left_start) = self.suffix;
+        let suffix = if left_suffix + right.total > right.suffix.0 {
+            (left_suffix + right.total, left_start)
+        } else {
+            right.suffix
+        };
+
+        // A maximum crossing subarray is a maximum suffix of the left segment
+        // followed by a maximum prefix of the right one, and taking the
+        // shortest of both breaks ties like `MaxSubarray` does.
+        let cross = MaxSubarray {
+            start: left_start,
+            end: right_end,
+            sum: left_suffix + right_prefix,
+        };
+        let mut best = self.best;
+        for candidate in [right.best, cross] {
+            if candidate.is_better_than(&best) {
+                best = candidate;
+            }
+        }
+
+        Node {
+            total: self.total + right.total,
+            prefix,
+            suffix,
+            best,
+        }
+    }
+}
+
+// Segment tree answering "maximum subarray of `arr[low..high]`" queries while
+// the elements change.
+//
+// Every node stores the total, the best prefix, the best suffix and the best
+// subarray of its segment, so two children are combined in constant time.
+// Elements are `i32` and sums are `i64`, so nothing overflows.
+#[derive(Clone, Debug)]
+pub struct MaxSubarraySegmentTree {
+    len: usize,
+    // `nodes[1]` is the root, and the children of `nodes[i]` are
+    // `nodes[2 * i]` and `nodes[2 * i + 1]`.
+    nodes: Vec<Option<Node>>,
+}
+
+impl MaxSubarraySegmentTree {
+    // Builds the tree in O(n).
+    pub fn new(arr: &[i32]) -> Self {
+        let mut tree = MaxSubarraySegmentTree {
+            len: arr.len(),
+            nodes: vec![None; 4 * arr.len().max(1)],
+        };
+        if !arr.is_empty() {
+            tree.build(1, 0, arr.len() - 1, arr);
+        }
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Sets `arr[index]` to `value`. Time complexity: O(log n).
+    //
+    // Panics if `index` is out of bounds.
+    pub fn update(&mut self, index: usize, value: i32) {
+        assert!(index < self.len, "index {} out of bounds for length {}", index, self.len);
+        self.update_node(1, 0, self.len - 1, index, value);
+    }
+
+    // Returns a maximum subarray of `arr[low..high]`, with indices relative to
+    // the whole array, or `None` if the range is empty.
+    // Time complexity: O(log n).
+    //
+    // Panics if `high` is greater than the length.
+    pub fn query(&self, low: usize, high: usize) -> Option<MaxSubarray<i64>> {
+        assert!(high <= self.len, "range end {} out of bounds for length {}", high, self.len);
+        if low >= high {
+            return None;
+        }
+        Some(self.query_node(1, 0, self.len - 1, low, high - 1).best)
+    }
+
+    fn build(&mut self, node: usize, low: usize, high: usize, arr: &[i32]) {
+        if low == high {
+            self.nodes[node] = Some(Node::leaf(low, arr[low]));
+            return;
+        }
+        let mid = (low + high) / 2;
+        self.build(2 * node, low, mid, arr);
+        self.build(2 * node + 1, mid + 1, high, arr);
+        self.pull(node);
+    }
+
+    fn update_node(&mut self, node: usize, low: usize, high: usize, index: usize, value: i32) {
+        if low == high {
+            self.nodes[node] = Some(Node::leaf(index, value));
+            return;
+        }
+        let mid = (low + high) / 2;
+        if index <= mid {
+            self.update_node(2 * node, low, mid, index, value);
+        } else {
+            self.update_node(2 * node + 1, mid + 1, high, index, value);
+        }
+        self.pull(node);
+    }
+
+    // Recomputes `node` from its children.
+    fn pull(&mut self, node: usize) {
+        let left = self.nodes[2 * node].unwrap();
+        let right = self.nodes[2 * node + 1].unwrap();
+        self.nodes[node] = Some(left.combine(&right));
+    }
+
+    // Summary of `arr[from..=to]`, which overlaps the segment `low..=high` of
+    // `node`.
+    fn query_node(&self, node: usize, low: usize, high: usize, from: usize, to: usize) -> Node {
+        if from <= low && high <= to {
+            return self.nodes[node].unwrap();
+        }
+        let mid = (low + high) / 2;
+        if to <= mid {
+            return self.query_node(2 * node, low, mid, from, to);
+        }
+        if from > mid {
+            return self.query_node(2 * node + 1, mid + 1, high, from, to);
+        }
+        let left = self.query_node(2 * node, low, mid, from, to);
+        let right = self.query_node(2 * node + 1, mid + 1, high, from, to);
+        left.combine(&right)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::brute_force_find_maximum_subarray;
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn expected(arr: &[i32], low: usize, high: usize) -> (usize, usize, i64) {
+        let (start, end, sum) = brute_force_find_maximum_subarray(&arr[low..high]);
+        (low + start, low + end, i64::from(sum))
+    }
+
+    #[test]
+    fn test_query() {
+        let arr = [-2, 1, -3, 4, -1, 2, 1, -5, 4];
+        let tree = MaxSubarraySegmentTree::new(&arr);
+
+        let max = tree.query(0, 9).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (3, 6, 6));
+        let max = tree.query(0, 3).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (1, 1, 1));
+        let max = tree.query(6, 9).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (8, 8, 4));
+        assert_eq!(tree.query(4, 4), None);
+    }
+
+    #[test]
+    fn test_update() {
+        let mut tree = MaxSubarraySegmentTree::new(&[-2, 1, -3, 4, -1, 2, 1, -5, 4]);
+        tree.update(7, 5);
+        let max = tree.query(0, 9).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (3, 8, 15));
+
+        tree.update(3, -10);
+        let max = tree.query(0, 9).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (5, 8, 12));
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let tree = MaxSubarraySegmentTree::new(&[]);
+        assert!(tree.is_empty());
+        assert_eq!(tree.query(0, 0), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "index 3 out of bounds for length 3")]
+    fn test_update_out_of_bounds() {
+        MaxSubarraySegmentTree::new(&[1, 2, 3]).update(3, 0);
+    }
+
+    #[test]
+    fn test_agrees_with_brute_force() {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        for len in [1, 2, 3, 7, 16, 33] {
+            let mut arr: Vec<i32> = (0..len).map(|_| rng.value(6)).collect();
+            let mut tree = MaxSubarraySegmentTree::new(&arr);
+            assert_eq!(tree.len(), len);
+
+            for _ in 0..200 {
+                if rng.next().is_multiple_of(3) {
+                    let index = rng.below(len as u64) as usize;
+                    arr[index] = rng.value(6);
+                    tree.update(index, arr[index]);
+                } else {
+                    let low = rng.below(len as u64) as usize;
+                    let high = low + 1 + rng.below((len - low) as u64) as usize;
+                    let max = tree.query(low, high).unwrap();
+                    assert_eq!((max.start, max.end, max.sum), expected(&arr, low, high), "{:?}", arr);
+                }
+            }
+        }
+    }
+}
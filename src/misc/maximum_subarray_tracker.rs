@@ -0,0 +1,313 @@
+use super::maximum_subarray::MaxSubarray;
+use super::maximum_subarray_segment_tree::Node;
+
+// The largest window whose sums can't overflow an `i64`: 2^32 values of at
+// most 2^31 in magnitude.
+const MAX_WINDOW: u64 = 1 << 32;
+
+// Online maximum subarray: values are pushed one at a time, and the best
+// subarray so far is available after each of them. Indices count the values
+// pushed since the tracker was created, starting at 0.
+//
+// Like the other `i32` variants, sums are `i64`. A window holds at most 2^32
+// values, so its sums always fit. Without a window, the running sum keeps
+// growing, and `push` panics if it leaves the range of `i64`, which takes more
+// than 2^32 pushes of values near `i32::MAX`.
+#[derive(Clone, Debug)]
+pub struct MaxSubarrayTracker {
+    len: usize,
+    state: State,
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    // Kadane's algorithm, one value at a time: the best subarray ending at the
+    // last value, and the best one overall.
+    Unbounded {
+        current: Option<MaxSubarray<i64>>,
+        best: Option<MaxSubarray<i64>>,
+    },
+    // The last `size` values, in a queue made of two stacks so the summary of
+    // the whole window is kept up to date in amortized constant time.
+    //
+    // `front` holds the oldest values, its top being the oldest one, and each
+    // entry summarizes its value and every newer value of `front`. `back`
+    // holds the newest values, and `back_summary` summarizes all of them.
+    Window {
+        size: usize,
+        front: Vec<Node>,
+        back: Vec<Node>,
+        back_summary: Option<Node>,
+    },
+}
+
+impl MaxSubarrayTracker {
+    // Creates a tracker over every value pushed.
+    pub fn new() -> Self {
+        MaxSubarrayTracker {
+            len: 0,
+            state: State::Unbounded {
+                current: None,
+                best: None,
+            },
+        }
+    }
+
+    // Creates a tracker over the last `size` values pushed.
+    //
+    // Panics if `size` is 0 or greater than 2^32.
+    pub fn with_window(size: usize) -> Self {
+        assert!(size > 0, "the window must hold at least one value");
+        assert!(size as u64 <= MAX_WINDOW, "the window can hold at most {} values", MAX_WINDOW);
+        MaxSubarrayTracker {
+            len: 0,
+            state: State::Window {
+                size,
+                front: Vec::new(),
+                back: Vec::new(),
+                back_summary: None,
+            },
+        }
+    }
+
+    // Returns the number of values pushed so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Returns the size of the sliding window, if any.
+    pub fn window(&self) -> Option<usize> {
+        match self.state {
+            State::Unbounded { .. } => None,
+            State::Window { size, .. } => Some(size),
+        }
+    }
+
+    // Consumes one value. Time complexity: O(1), amortized with a window.
+    //
+    // Panics without a window if the sum of the current run overflows.
+    pub fn push(&mut self, value: i32) {
+        let index = self.len;
+        self.len += 1;
+
+        match &mut self.state {
+            State::Unbounded { current, best } => {
+                let value = i64::from(value);
+                // Same trick as `MaxSubarray::linear`: restart at this value
+                // unless the running sum is positive.
+                *current = Some(match *current {
+                    Some(run) if run.sum > 0 => MaxSubarray {
+                        start: run.start,
+                        end: index,
+                        sum: run.sum.checked_add(value).expect("the maximum subarray sum overflows an i64"),
+                    },
+                    _ => MaxSubarray {
+                        start: index,
+                        end: index,
+                        sum: value,
+                    },
+                });
+
+                let run = current.unwrap();
+                if best.is_none_or(|b| run.sum > b.sum) {
+                    *best = Some(run);
+                }
+            }
+            State::Window {
+                size,
+                front,
+                back,
+                back_summary,
+            } => {
+                let leaf = Node::leaf(index, value);
+                back.push(leaf);
+                *back_summary = Some(match back_summary {
+                    Some(summary) => summary.combine(&leaf),
+                    None => leaf,
+                });
+
+                if front.len() + back.len() > *size {
+                    if front.is_empty() {
+                        // Move the values to `front`, from the newest to the
+                        // oldest, summarizing each with the newer ones.
+                        let mut summary: Option<Node> = None;
+                        while let Some(leaf) = back.pop() {
+                            let node = match summary {
+                                Some(newer) => leaf.combine(&newer),
+                                None => leaf,
+                            };
+                            front.push(node);
+                            summary = Some(node);
+                        }
+                        *back_summary = None;
+                    }
+                    front.pop();
+                }
+            }
+        }
+    }
+
+    // Returns the maximum subarray of the values tracked so far, or `None` if
+    // there are none.
+    pub fn best(&self) -> Option<MaxSubarray<i64>> {
+        match &self.state {
+            State::Unbounded { best, .. } => *best,
+            State::Window {
+                front, back_summary, ..
+            } => match (front.last(), back_summary) {
+                (Some(older), Some(newer)) => Some(older.combine(newer).best),
+                (Some(older), None) => Some(older.best),
+                (None, Some(newer)) => Some(newer.best),
+                (None, None) => None,
+            },
+        }
+    }
+}
+
+impl Default for MaxSubarrayTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Extend<i32> for MaxSubarrayTracker {
+    fn extend<I: IntoIterator<Item = i32>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+// Lets a chunk of values be consumed with `tracker.extend(&chunk)`.
+impl<'a> Extend<&'a i32> for MaxSubarrayTracker {
+    fn extend<I: IntoIterator<Item = &'a i32>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl FromIterator<i32> for MaxSubarrayTracker {
+    fn from_iter<I: IntoIterator<Item = i32>>(iter: I) -> Self {
+        let mut tracker = Self::new();
+        tracker.extend(iter);
+        tracker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::brute_force_find_maximum_subarray;
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn summary(max: Option<MaxSubarray<i64>>) -> Option<(usize, usize, i64)> {
+        max.map(|m| (m.start, m.end, m.sum))
+    }
+
+    fn expected(arr: &[i32], offset: usize) -> Option<(usize, usize, i64)> {
+        if arr.is_empty() {
+            return None;
+        }
+        let (start, end, sum) = brute_force_find_maximum_subarray(arr);
+        Some((offset + start, offset + end, i64::from(sum)))
+    }
+
+    #[test]
+    fn test_unbounded() {
+        let mut tracker = MaxSubarrayTracker::new();
+        assert_eq!(tracker.best(), None);
+
+        tracker.extend([-2, 1, -3, 4]);
+        assert_eq!(summary(tracker.best()), Some((3, 3, 4)));
+        tracker.extend(&[-1, 2, 1, -5, 4]);
+        assert_eq!(summary(tracker.best()), Some((3, 6, 6)));
+        assert_eq!(tracker.len(), 9);
+        assert_eq!(tracker.window(), None);
+    }
+
+    #[test]
+    fn test_window() {
+        let mut tracker = MaxSubarrayTracker::with_window(3);
+        tracker.extend([5, -1, -1]);
+        assert_eq!(summary(tracker.best()), Some((0, 0, 5)));
+
+        // the 5 leaves the window
+        tracker.push(2);
+        assert_eq!(summary(tracker.best()), Some((3, 3, 2)));
+        tracker.push(3);
+        assert_eq!(summary(tracker.best()), Some((3, 4, 5)));
+        assert_eq!(tracker.window(), Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "the window must hold at least one value")]
+    fn test_empty_window() {
+        MaxSubarrayTracker::with_window(0);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    #[should_panic(expected = "the window can hold at most 4294967296 values")]
+    fn test_window_too_large() {
+        MaxSubarrayTracker::with_window((1 << 32) + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "the maximum subarray sum overflows an i64")]
+    fn test_unbounded_overflow() {
+        // Reaching this sum takes about 2^32 pushes of `i32::MAX`.
+        let run = MaxSubarray {
+            start: 0,
+            end: 0,
+            sum: i64::MAX - 1,
+        };
+        let mut tracker = MaxSubarrayTracker {
+            len: 1,
+            state: State::Unbounded {
+                current: Some(run),
+                best: Some(run),
+            },
+        };
+        tracker.push(i32::MAX);
+    }
+
+    #[test]
+    fn test_from_iterator() {
+        let tracker: MaxSubarrayTracker = [-3, -1, -2].into_iter().collect();
+        assert_eq!(summary(tracker.best()), Some((1, 1, -1)));
+    }
+
+    #[test]
+    fn test_unbounded_agrees_with_brute_force() {
+        let arr: Vec<i32> = XorShift::new(7).values(300, 10);
+        let mut tracker = MaxSubarrayTracker::default();
+        for (i, &value) in arr.iter().enumerate() {
+            tracker.push(value);
+            assert_eq!(summary(tracker.best()), expected(&arr[..=i], 0));
+        }
+    }
+
+    #[test]
+    fn test_window_agrees_with_brute_force() {
+        for size in [1, 2, 3, 5, 16, 50] {
+            let arr: Vec<i32> = XorShift::new(size as u64).values(200, 10);
+            let mut tracker = MaxSubarrayTracker::with_window(size);
+
+            // pushed in chunks of varying sizes, like a feed of batches
+            let mut pushed = 0;
+            for chunk in arr.chunks(7) {
+                for (i, &value) in chunk.iter().enumerate() {
+                    tracker.push(value);
+                    let end = pushed + i + 1;
+                    let start = end.saturating_sub(size);
+                    assert_eq!(summary(tracker.best()), expected(&arr[start..end], start), "window {}", size);
+                }
+                pushed += chunk.len();
+            }
+            assert_eq!(tracker.len(), arr.len());
+        }
+    }
+}
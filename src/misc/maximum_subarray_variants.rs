@@ -0,0 +1,377 @@
+use std::collections::{BinaryHeap, VecDeque};
+
+use super::maximum_subarray::MaxSubarray;
+
+// The variants below take `i32` elements like the `*_find_maximum_subarray`
+// wrappers, and add them up as `i64`, which can't overflow for any slice that
+// fits in memory. They return `None` when no subarray qualifies, e.g. for an
+// empty slice.
+
+// Widens the elements, so sums and differences of prefix sums never overflow.
+fn widen(arr: &[i32]) -> Vec<i64> {
+    arr.iter().map(|&x| i64::from(x)).collect()
+}
+
+// Finds a maximum subarray of `arr` seen as a circular array, where the last
+// element is followed by the first one. Time complexity: O(n).
+//
+// A subarray either doesn't wrap around, and Kadane's algorithm finds it, or
+// it wraps around, and then its complement is an ordinary subarray in the
+// middle: the best wrapping subarray is the total minus a minimum subarray,
+// i.e. Kadane's algorithm over the negated elements.
+//
+// A subarray that wraps around is returned with `start > end`, meaning
+// `arr[start..]` followed by `arr[..=end]`.
+pub fn circular_maximum_subarray(arr: &[i32]) -> Option<MaxSubarray<i64>> {
+    let wide = widen(arr);
+    let n = wide.len();
+    let straight = MaxSubarray::linear(&wide).ok()?;
+
+    let negated: Vec<i64> = wide.iter().map(|&x| -x).collect();
+    let min = MaxSubarray::linear(&negated).ok()?;
+
+    // The complement of the whole array is empty, which isn't a subarray.
+    if min.start == 0 && min.end == n - 1 {
+        return Some(straight);
+    }
+
+    let total: i64 = wide.iter().sum();
+    let wrapping = total + min.sum;
+    if wrapping > straight.sum {
+        return Some(MaxSubarray {
+            start: (min.end + 1) % n,
+            end: (min.start + n - 1) % n,
+            sum: wrapping,
+        });
+    }
+    Some(straight)
+}
+
+// Finds a maximum subarray whose length is between `min_len` and `max_len`,
+// inclusive. Time complexity: O(n).
+//
+// The sum of `arr[start..end]` is `prefix[end] - prefix[start]`, so for every
+// `end` the best `start` is the one with the smallest prefix sum among
+// `end - max_len..=end - min_len`. That window slides right by one at every
+// step, and a deque of candidate starts with increasing prefix sums gives its
+// minimum in amortized constant time.
+//
+// Ties are broken like `MaxSubarray`: the subarray ending first wins, then the
+// shortest one.
+//
+// Panics unless `1 <= min_len <= max_len`.
+pub fn bounded_length_maximum_subarray(arr: &[i32], min_len: usize, max_len: usize) -> Option<MaxSubarray<i64>> {
+    assert!(
+        1 <= min_len && min_len <= max_len,
+        "invalid length bounds {}..={}",
+        min_len,
+        max_len
+    );
+    let n = arr.len();
+
+    let mut prefix = vec![0i64; n + 1];
+    for i in 0..n {
+        prefix[i + 1] = prefix[i] + i64::from(arr[i]);
+    }
+
+    let mut best: Option<MaxSubarray<i64>> = None;
+    let mut starts: VecDeque<usize> = VecDeque::new();
+
+    for end in min_len..=n {
+        // The start `end - min_len` just became long enough. Older starts
+        // with a prefix sum at least as large give longer subarrays that
+        // aren't better, so they are dropped.
+        let candidate = end - min_len;
+        while starts.back().is_some_and(|&back| prefix[back] >= prefix[candidate]) {
+            starts.pop_back();
+        }
+        starts.push_back(candidate);
+
+        // The starts before `end - max_len` are too far away for good.
+        while starts.front().is_some_and(|&front| front + max_len < end) {
+            starts.pop_front();
+        }
+
+        let start = starts[0];
+        let sum = prefix[end] - prefix[start];
+        if best.is_none_or(|b| sum > b.sum) {
+            best = Some(MaxSubarray { start, end: end - 1, sum });
+        }
+    }
+
+    best
+}
+
+// Returns the maximum sum of a non-empty subarray after deleting at most one
+// of its elements (the subarray must keep at least one element).
+// Time complexity: O(n).
+//
+// Kadane's algorithm is run with two running sums ending at every index: the
+// best one without a deletion, and the best one with exactly one deletion,
+// which either deletes the current element or extends an earlier deletion.
+pub fn maximum_subarray_one_deletion(arr: &[i32]) -> Option<i64> {
+    let wide = widen(arr);
+    let (&first, rest) = wide.split_first()?;
+
+    let mut kept = first;
+    // Deleting the only element leaves nothing, so there's no such subarray
+    // ending at the first index yet.
+    let mut deleted: Option<i64> = None;
+    let mut best = first;
+
+    for &x in rest {
+        deleted = Some(match deleted {
+            Some(deleted) => (deleted + x).max(kept),
+            None => kept,
+        });
+        kept = x.max(kept + x);
+
+        best = best.max(kept).max(deleted.unwrap());
+    }
+
+    Some(best)
+}
+
+// Returns the `k` largest sums among all the `n * (n + 1) / 2` subarrays of
+// `arr`, in decreasing order (fewer if there aren't that many subarrays).
+// Time complexity: O(n log n + k log(n + k)).
+//
+// For a fixed `end`, the best subarray starts at the smallest prefix sum
+// before it, which a sparse table finds in constant time. A heap holds the
+// best subarray of every `end` over a range of allowed starts. Popping the
+// best one splits its range in two around the start just used, so the next
+// best subarray with the same `end` is among the two new ranges.
+pub fn k_largest_subarray_sums(arr: &[i32], k: usize) -> Vec<i64> {
+    let n = arr.len();
+    let mut prefix = vec![0i64; n + 1];
+    for i in 0..n {
+        prefix[i + 1] = prefix[i] + i64::from(arr[i]);
+    }
+    let table = SparseArgMin::new(&prefix[..n]);
+
+    // (sum, end, first allowed start, last allowed start, best start)
+    let mut heap = BinaryHeap::new();
+    let push = |heap: &mut BinaryHeap<(i64, usize, usize, usize, usize)>, end: usize, low: usize, high: usize| {
+        let start = table.arg_min(low, high);
+        heap.push((prefix[end] - prefix[start], end, low, high, start));
+    };
+    for end in 1..=n {
+        push(&mut heap, end, 0, end - 1);
+    }
+
+    let mut sums = Vec::with_capacity(k.min(n * (n + 1) / 2));
+    while sums.len() < k {
+        let (sum, end, low, high, start) = match heap.pop() {
+            Some(top) => top,
+            None => break,
+        };
+        sums.push(sum);
+
+        if low < start {
+            push(&mut heap, end, low, start - 1);
+        }
+        if start < high {
+            push(&mut heap, end, start + 1, high);
+        }
+    }
+
+    sums
+}
+
+// Sparse table answering "index of the minimum of `values[low..=high]`" in
+// constant time, after an O(n log n) construction.
+struct SparseArgMin<'a> {
+    values: &'a [i64],
+    // `levels[j][i]` is the index of the minimum of `values[i..i + 2^j]`.
+    levels: Vec<Vec<usize>>,
+}
+
+impl<'a> SparseArgMin<'a> {
+    fn new(values: &'a [i64]) -> Self {
+        let mut levels = vec![(0..values.len()).collect::<Vec<usize>>()];
+        let mut width = 1;
+
+        while 2 * width <= values.len() {
+            let previous = &levels[levels.len() - 1];
+            let level = (0..=values.len() - 2 * width)
+                .map(|i| min_index(values, previous[i], previous[i + width]))
+                .collect();
+            levels.push(level);
+            width *= 2;
+        }
+
+        SparseArgMin { values, levels }
+    }
+
+    fn arg_min(&self, low: usize, high: usize) -> usize {
+        let j = (high - low + 1).ilog2() as usize;
+        let level = &self.levels[j];
+        min_index(self.values, level[low], level[high + 1 - (1 << j)])
+    }
+}
+
+fn min_index(values: &[i64], a: usize, b: usize) -> usize {
+    if values[b] < values[a] {
+        b
+    } else {
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    // The sum of `len` elements starting at `start`, wrapping around.
+    fn circular_sum(arr: &[i32], start: usize, len: usize) -> i64 {
+        (start..start + len).map(|i| i64::from(arr[i % arr.len()])).sum()
+    }
+
+    fn brute_force_circular(arr: &[i32]) -> Option<i64> {
+        let n = arr.len();
+        (0..n)
+            .flat_map(|start| (1..=n).map(move |len| circular_sum(arr, start, len)))
+            .max()
+    }
+
+    fn brute_force_bounded_length(arr: &[i32], min_len: usize, max_len: usize) -> Option<i64> {
+        let mut best = None;
+        for start in 0..arr.len() {
+            for end in start + min_len..=(start + max_len).min(arr.len()) {
+                let sum = circular_sum(arr, start, end - start);
+                best = best.max(Some(sum));
+            }
+        }
+        best
+    }
+
+    fn brute_force_one_deletion(arr: &[i32]) -> Option<i64> {
+        let mut best = None;
+        for start in 0..arr.len() {
+            for end in start..arr.len() {
+                let sum = circular_sum(arr, start, end - start + 1);
+                best = best.max(Some(sum));
+                if end > start {
+                    for deleted in start..=end {
+                        best = best.max(Some(sum - i64::from(arr[deleted])));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    fn brute_force_k_largest(arr: &[i32], k: usize) -> Vec<i64> {
+        let mut sums = Vec::new();
+        for start in 0..arr.len() {
+            for end in start..arr.len() {
+                sums.push(circular_sum(arr, start, end - start + 1));
+            }
+        }
+        sums.sort_unstable_by(|a, b| b.cmp(a));
+        sums.truncate(k);
+        sums
+    }
+
+    #[test]
+    fn test_circular() {
+        let max = circular_maximum_subarray(&[5, -3, 5]).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (2, 0, 10));
+
+        let max = circular_maximum_subarray(&[-2, 1, -3, 4, -1, 2, 1, -5]).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (3, 6, 6));
+
+        let max = circular_maximum_subarray(&[-3, -2, -3]).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (1, 1, -2));
+
+        assert_eq!(circular_maximum_subarray(&[]), None);
+    }
+
+    #[test]
+    fn test_circular_agrees_with_brute_force() {
+        for seed in 1..200 {
+            let arr: Vec<i32> = XorShift::new(seed).values(seed as usize % 9 + 1, 6);
+            let max = circular_maximum_subarray(&arr).unwrap();
+            assert_eq!(Some(max.sum), brute_force_circular(&arr), "{:?}", arr);
+
+            let len = (max.end + arr.len() - max.start) % arr.len() + 1;
+            assert_eq!(circular_sum(&arr, max.start, len), max.sum);
+        }
+    }
+
+    #[test]
+    fn test_bounded_length() {
+        let arr = [-2, 1, -3, 4, -1, 2, 1, -5];
+        let max = bounded_length_maximum_subarray(&arr, 1, 8).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (3, 6, 6));
+
+        let max = bounded_length_maximum_subarray(&arr, 1, 2).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (3, 3, 4));
+
+        let max = bounded_length_maximum_subarray(&arr, 2, 3).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (3, 5, 5));
+
+        let max = bounded_length_maximum_subarray(&arr, 6, 7).unwrap();
+        assert_eq!((max.start, max.end, max.sum), (1, 6, 4));
+
+        assert_eq!(bounded_length_maximum_subarray(&arr, 9, 10), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid length bounds 3..=2")]
+    fn test_bounded_length_invalid_bounds() {
+        bounded_length_maximum_subarray(&[1, 2, 3], 3, 2);
+    }
+
+    #[test]
+    fn test_bounded_length_agrees_with_brute_force() {
+        for seed in 1..200 {
+            let arr: Vec<i32> = XorShift::new(seed).values(seed as usize % 10 + 1, 6);
+            let min_len = seed as usize % 4 + 1;
+            let max_len = min_len + seed as usize % 3;
+
+            let max = bounded_length_maximum_subarray(&arr, min_len, max_len);
+            assert_eq!(max.map(|m| m.sum), brute_force_bounded_length(&arr, min_len, max_len), "{:?}", arr);
+            if let Some(max) = max {
+                let len = max.end - max.start + 1;
+                assert!(min_len <= len && len <= max_len);
+                assert_eq!(circular_sum(&arr, max.start, len), max.sum);
+            }
+        }
+    }
+
+    #[test]
+    fn test_one_deletion() {
+        assert_eq!(maximum_subarray_one_deletion(&[1, -2, 0, 3]), Some(4));
+        assert_eq!(maximum_subarray_one_deletion(&[1, -2, -2, 3]), Some(3));
+        assert_eq!(maximum_subarray_one_deletion(&[-1, -1, -1, -1]), Some(-1));
+        assert_eq!(maximum_subarray_one_deletion(&[-7]), Some(-7));
+        assert_eq!(maximum_subarray_one_deletion(&[]), None);
+    }
+
+    #[test]
+    fn test_one_deletion_agrees_with_brute_force() {
+        for seed in 1..200 {
+            let arr: Vec<i32> = XorShift::new(seed).values(seed as usize % 9 + 1, 6);
+            assert_eq!(maximum_subarray_one_deletion(&arr), brute_force_one_deletion(&arr), "{:?}", arr);
+        }
+    }
+
+    #[test]
+    fn test_k_largest() {
+        assert_eq!(k_largest_subarray_sums(&[3, -1, 2], 3), vec![4, 3, 2]);
+        assert_eq!(k_largest_subarray_sums(&[1, 2], 10), vec![3, 2, 1]);
+        assert_eq!(k_largest_subarray_sums(&[1, 2], 0), Vec::<i64>::new());
+        assert_eq!(k_largest_subarray_sums(&[], 3), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_k_largest_agrees_with_brute_force() {
+        for seed in 1..100 {
+            let arr: Vec<i32> = XorShift::new(seed).values(seed as usize % 12 + 1, 8);
+            let k = seed as usize % 30;
+            assert_eq!(k_largest_subarray_sums(&arr, k), brute_force_k_largest(&arr, k), "{:?}", arr);
+        }
+    }
+}
@@ -1,7 +1,32 @@
+pub mod expression;
+pub mod matrix;
+mod maximum_average_subarray;
+mod maximum_product_subarray;
 mod maximum_subarray;
+mod maximum_subarray_2d;
+mod maximum_subarray_parallel;
+mod maximum_subarray_segment_tree;
+mod maximum_subarray_tracker;
+mod maximum_subarray_variants;
+pub mod trading;
 
+pub use self::maximum_average_subarray::maximum_average_subarray;
+pub use self::maximum_product_subarray::{maximum_product_subarray, BigProduct};
 pub use self::maximum_subarray::{
     divide_conquer_find_maximum_subarray,
     linear_find_maximum_subarray,
-    brute_force_find_maximum_subarray
+    brute_force_find_maximum_subarray,
+    MaxSubarray,
+    MaxSubarrayError,
+    SubarraySum
+};
+pub use self::maximum_subarray_2d::MaxRectangle;
+pub use self::maximum_subarray_parallel::parallel_find_maximum_subarray;
+pub use self::maximum_subarray_segment_tree::MaxSubarraySegmentTree;
+pub use self::maximum_subarray_tracker::MaxSubarrayTracker;
+pub use self::maximum_subarray_variants::{
+    bounded_length_maximum_subarray,
+    circular_maximum_subarray,
+    k_largest_subarray_sums,
+    maximum_subarray_one_deletion
 };
@@ -0,0 +1,385 @@
+// Buy-low/sell-high problems over a series of daily prices, the motivating
+// example of the maximum subarray problem: buying on day `i` and selling on
+// day `j` earns the sum of the price changes between them.
+//
+// Prices are `i32` like the other maximum subarray functions, and profits are
+// `i64`, so they can't overflow. Buying and selling happen at the day's price,
+// and at most one share is held at any time.
+
+use super::maximum_subarray::MaxSubarray;
+
+// A single round trip: buy on day `buy`, sell on the later day `sell`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trade {
+    pub buy: usize,
+    pub sell: usize,
+    // the price difference, before any fee
+    pub profit: i64,
+}
+
+// The trades of an optimal strategy, in chronological order, and the total
+// profit after fees.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub profit: i64,
+    pub trades: Vec<Trade>,
+}
+
+// Returns the price change from every day to the next one, so `deltas[i]` is
+// `prices[i + 1] - prices[i]`.
+pub fn price_deltas(prices: &[i32]) -> Vec<i64> {
+    prices.windows(2).map(|w| i64::from(w[1]) - i64::from(w[0])).collect()
+}
+
+// Returns the most profitable single trade, or `None` if no trade makes a
+// profit. Time complexity: O(n).
+//
+// A trade from day `buy` to day `sell` earns `deltas[buy..sell]`, so the best
+// one is a maximum subarray of the deltas.
+pub fn single_transaction(prices: &[i32]) -> Option<Trade> {
+    let max = MaxSubarray::linear(&price_deltas(prices)).ok()?;
+    if max.sum <= 0 {
+        return None;
+    }
+    Some(Trade {
+        buy: max.start,
+        sell: max.end + 1,
+        profit: max.sum,
+    })
+}
+
+// Returns an optimal strategy with any number of trades, which is to hold the
+// share exactly over every rising stretch of days. Time complexity: O(n).
+pub fn unlimited_transactions(prices: &[i32]) -> Plan {
+    let mut plan = Plan::default();
+    let mut day = 0;
+
+    while day + 1 < prices.len() {
+        if prices[day + 1] <= prices[day] {
+            day += 1;
+            continue;
+        }
+        let buy = day;
+        while day + 1 < prices.len() && prices[day + 1] > prices[day] {
+            day += 1;
+        }
+        plan.push(buy, day, prices);
+    }
+
+    plan
+}
+
+// Returns an optimal strategy with at most `k` trades.
+// Time complexity: O(n * k), using O(n * k) memory to recover the trades.
+//
+// `profit[t][i]` is the best profit by the end of day `i` with at most `t`
+// trades. Either nothing is sold on day `i`, or a trade bought on some day
+// `j < i` is sold, after at most `t - 1` trades ending by day `j`:
+//
+//     profit[t][i] = max(profit[t][i - 1], prices[i] + max(profit[t - 1][j] - prices[j]))
+//
+// and the inner maximum is carried along `i`.
+pub fn at_most_k_transactions(prices: &[i32], k: usize) -> Plan {
+    let n = prices.len();
+    // Each trade needs two days of its own, so beyond `n / 2` the limit
+    // doesn't matter.
+    if k >= n / 2 {
+        return unlimited_transactions(prices);
+    }
+
+    let price = |day: usize| i64::from(prices[day]);
+    let mut profit = vec![vec![0i64; n]; k + 1];
+    // the day a share sold on day `i` was bought, if it was
+    let mut bought = vec![vec![None; n]; k + 1];
+
+    for t in 1..=k {
+        // best `profit[t - 1][j] - prices[j]` so far, and its day `j`
+        let mut best_buy = (profit[t - 1][0] - price(0), 0);
+
+        for i in 1..n {
+            profit[t][i] = profit[t][i - 1];
+            let sold = best_buy.0 + price(i);
+            if sold > profit[t][i] {
+                profit[t][i] = sold;
+                bought[t][i] = Some(best_buy.1);
+            }
+
+            let buy = profit[t - 1][i] - price(i);
+            if buy > best_buy.0 {
+                best_buy = (buy, i);
+            }
+        }
+    }
+
+    // Walk back from the last day, recovering the trades.
+    let mut trades = Vec::new();
+    let (mut t, mut day) = (k, n.saturating_sub(1));
+    while t > 0 && day > 0 {
+        match bought[t][day] {
+            Some(buy) => {
+                trades.push((buy, day));
+                t -= 1;
+                day = buy;
+            }
+            None => day -= 1,
+        }
+    }
+
+    let mut plan = Plan::default();
+    for &(buy, sell) in trades.iter().rev() {
+        plan.push(buy, sell, prices);
+    }
+    plan
+}
+
+// Returns an optimal strategy with any number of trades, where after selling
+// on day `i` nothing can be bought before day `i + cooldown + 1`.
+// Time complexity: O(n).
+pub fn with_cooldown(prices: &[i32], cooldown: usize) -> Plan {
+    unlimited_with_rules(prices, cooldown, 0)
+}
+
+// Returns an optimal strategy with any number of trades, where every trade
+// costs a `fee`, subtracted from the total profit. Time complexity: O(n).
+pub fn with_fee(prices: &[i32], fee: i64) -> Plan {
+    unlimited_with_rules(prices, 0, fee)
+}
+
+// State machine over the days: `free[i]` is the best profit at the end of day
+// `i` without a share, and `held[i]` the best one holding a share.
+//
+//     held[i] = max(held[i - 1], free[i - 1 - cooldown] - prices[i])
+//     free[i] = max(free[i - 1], held[i - 1] + prices[i] - fee)
+//
+// Ties keep the current position, so the plan has as few trades as possible.
+fn unlimited_with_rules(prices: &[i32], cooldown: usize, fee: i64) -> Plan {
+    let n = prices.len();
+    if n == 0 {
+        return Plan::default();
+    }
+    let price = |day: usize| i64::from(prices[day]);
+
+    let mut free = vec![0i64; n];
+    let mut held = vec![-price(0); n];
+    // whether the share was sold, or bought, on that very day
+    let mut sold_on = vec![false; n];
+    let mut bought_on = vec![true; n];
+
+    for i in 1..n {
+        // the profit before the last possible sale that allows buying today
+        let before = if i > cooldown { free[i - 1 - cooldown] } else { 0 };
+
+        held[i] = held[i - 1];
+        bought_on[i] = false;
+        if before - price(i) > held[i] {
+            held[i] = before - price(i);
+            bought_on[i] = true;
+        }
+
+        free[i] = free[i - 1];
+        if held[i - 1] + price(i) - fee > free[i] {
+            free[i] = held[i - 1] + price(i) - fee;
+            sold_on[i] = true;
+        }
+    }
+
+    // Walk back from the last day without a share.
+    let mut trades = Vec::new();
+    let mut day = n - 1;
+    loop {
+        if !sold_on[day] {
+            if day == 0 {
+                break;
+            }
+            day -= 1;
+            continue;
+        }
+
+        let sell = day;
+        day -= 1;
+        while !bought_on[day] {
+            day -= 1;
+        }
+        trades.push((day, sell));
+
+        if day <= cooldown {
+            break;
+        }
+        day -= 1 + cooldown;
+    }
+
+    let mut plan = Plan::default();
+    for &(buy, sell) in trades.iter().rev() {
+        plan.push(buy, sell, prices);
+    }
+    plan.profit -= fee * plan.trades.len() as i64;
+    plan
+}
+
+impl Plan {
+    // Appends a trade after the existing ones. Selling and buying back on the
+    // same day is the same as keeping the share, so such trades are merged.
+    fn push(&mut self, buy: usize, sell: usize, prices: &[i32]) {
+        let profit = i64::from(prices[sell]) - i64::from(prices[buy]);
+        self.profit += profit;
+        match self.trades.last_mut() {
+            Some(last) if last.sell == buy => {
+                last.sell = sell;
+                last.profit += profit;
+            }
+            _ => self.trades.push(Trade { buy, sell, profit }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    // Deterministic pseudo-random prices in `1..=range`.
+    fn prices(seed: u64, len: usize, range: u64) -> Vec<i32> {
+        let mut rng = XorShift::new(seed);
+        (0..len).map(|_| (rng.below(range) + 1) as i32).collect()
+    }
+
+    // Tries every action on every day: the best profit from `day` on, with or
+    // without a share, `trades` trades left to start, and `wait` days before
+    // buying is allowed.
+    fn brute_force(prices: &[i32], day: usize, holding: bool, trades: usize, wait: usize, rules: (usize, i64)) -> i64 {
+        if day == prices.len() {
+            return if holding { i64::MIN / 2 } else { 0 };
+        }
+        let (cooldown, fee) = rules;
+        let price = i64::from(prices[day]);
+        let mut best = brute_force(prices, day + 1, holding, trades, wait.saturating_sub(1), rules);
+
+        if holding {
+            let sold = price - fee + brute_force(prices, day + 1, false, trades, cooldown, rules);
+            best = best.max(sold);
+        } else if trades > 0 && wait == 0 {
+            let bought = -price + brute_force(prices, day + 1, true, trades - 1, 0, rules);
+            best = best.max(bought);
+        }
+        best
+    }
+
+    // Checks that the plan is feasible and that its profit adds up.
+    fn check(plan: &Plan, prices: &[i32], k: usize, cooldown: usize, fee: i64) {
+        assert!(plan.trades.len() <= k);
+        let mut free_from = 0;
+        let mut profit = 0;
+        for trade in &plan.trades {
+            assert!(free_from <= trade.buy && trade.buy < trade.sell, "{:?}", plan);
+            assert_eq!(trade.profit, i64::from(prices[trade.sell]) - i64::from(prices[trade.buy]));
+            profit += trade.profit - fee;
+            free_from = trade.sell + cooldown + 1;
+        }
+        assert_eq!(plan.profit, profit);
+    }
+
+    #[test]
+    fn test_price_deltas() {
+        assert_eq!(price_deltas(&[3, 5, 4, 4]), vec![2, -1, 0]);
+        assert_eq!(price_deltas(&[7]), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_single_transaction() {
+        let prices = [7, 1, 5, 3, 6, 4];
+        let trade = Trade { buy: 1, sell: 4, profit: 5 };
+        assert_eq!(single_transaction(&prices), Some(trade));
+        assert_eq!(single_transaction(&[7, 6, 4, 3, 1]), None);
+        assert_eq!(single_transaction(&[]), None);
+    }
+
+    #[test]
+    fn test_clrs_example() {
+        // the stock prices of figure 4.1 of CLRS
+        let prices = [100, 113, 110, 85, 105, 102, 86, 63, 81, 101, 94, 106, 101, 79, 94, 90, 97];
+        let trade = single_transaction(&prices).unwrap();
+        assert_eq!((trade.buy, trade.sell, trade.profit), (7, 11, 43));
+    }
+
+    #[test]
+    fn test_at_most_k_transactions() {
+        let prices = [3, 2, 6, 5, 0, 3];
+        let plan = at_most_k_transactions(&prices, 2);
+        assert_eq!(plan.profit, 7);
+        assert_eq!(
+            plan.trades,
+            vec![Trade { buy: 1, sell: 2, profit: 4 }, Trade { buy: 4, sell: 5, profit: 3 }]
+        );
+
+        let plan = at_most_k_transactions(&prices, 1);
+        assert_eq!(plan.profit, 4);
+        assert_eq!(at_most_k_transactions(&prices, 0), Plan::default());
+    }
+
+    #[test]
+    fn test_unlimited_transactions() {
+        let plan = unlimited_transactions(&[7, 1, 5, 3, 6, 4]);
+        assert_eq!(plan.profit, 7);
+        assert_eq!(plan.trades.len(), 2);
+        assert_eq!(unlimited_transactions(&[1, 2, 3, 4, 5]).trades, vec![Trade { buy: 0, sell: 4, profit: 4 }]);
+    }
+
+    #[test]
+    fn test_with_cooldown() {
+        let plan = with_cooldown(&[1, 2, 3, 0, 2], 1);
+        assert_eq!(plan.profit, 3);
+        assert_eq!(
+            plan.trades,
+            vec![Trade { buy: 0, sell: 1, profit: 1 }, Trade { buy: 3, sell: 4, profit: 2 }]
+        );
+    }
+
+    #[test]
+    fn test_with_fee() {
+        let plan = with_fee(&[1, 3, 2, 8, 4, 9], 2);
+        assert_eq!(plan.profit, 8);
+        assert_eq!(
+            plan.trades,
+            vec![Trade { buy: 0, sell: 3, profit: 7 }, Trade { buy: 4, sell: 5, profit: 5 }]
+        );
+        assert_eq!(with_fee(&[5, 6], 3), Plan::default());
+    }
+
+    #[test]
+    fn test_single_transaction_agrees_with_brute_force() {
+        for seed in 1..100 {
+            let prices = prices(seed, seed as usize % 10 + 1, 20);
+            let expected = brute_force(&prices, 0, false, 1, 0, (0, 0));
+            assert_eq!(single_transaction(&prices).map_or(0, |t| t.profit), expected, "{:?}", prices);
+        }
+    }
+
+    #[test]
+    fn test_at_most_k_agrees_with_brute_force() {
+        for seed in 1..150 {
+            let prices = prices(seed, seed as usize % 11 + 1, 20);
+            let k = seed as usize % 4;
+            let plan = at_most_k_transactions(&prices, k);
+            check(&plan, &prices, k, 0, 0);
+            assert_eq!(plan.profit, brute_force(&prices, 0, false, k, 0, (0, 0)), "{:?} k = {}", prices, k);
+        }
+    }
+
+    #[test]
+    fn test_cooldown_and_fee_agree_with_brute_force() {
+        for seed in 1..150 {
+            let prices = prices(seed, seed as usize % 11 + 1, 20);
+            let n = prices.len();
+
+            let cooldown = seed as usize % 3;
+            let plan = with_cooldown(&prices, cooldown);
+            check(&plan, &prices, n, cooldown, 0);
+            assert_eq!(plan.profit, brute_force(&prices, 0, false, n, 0, (cooldown, 0)), "{:?}", prices);
+
+            let fee = seed as i64 % 5;
+            let plan = with_fee(&prices, fee);
+            check(&plan, &prices, n, 0, fee);
+            assert_eq!(plan.profit, brute_force(&prices, 0, false, n, 0, (0, fee)), "{:?}", prices);
+        }
+    }
+}
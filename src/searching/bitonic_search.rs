@@ -0,0 +1,76 @@
+/// Searches `target` in a bitonic slice, i.e. strictly increasing and then
+/// strictly decreasing, such as `[1, 4, 9, 7, 2]`, and returns its index.
+///
+/// The peak is found by binary search, then each monotone half is searched
+/// with a binary search of its own.
+/// Time complexity: O(log n).
+pub fn bitonic_search<T: Ord>(arr: &[T], target: T) -> Option<usize> {
+    if arr.is_empty() {
+        return None;
+    }
+    let peak = bitonic_peak(arr);
+
+    if let Ok(i) = arr[..=peak].binary_search(&target) {
+        return Some(i);
+    }
+
+    // the decreasing half is sorted for the reversed order
+    let decreasing = &arr[peak + 1..];
+    return decreasing
+        .binary_search_by(|probe| target.cmp(probe))
+        .ok()
+        .map(|i| i + peak + 1);
+}
+
+// Index of the maximum of a non-empty bitonic slice.
+fn bitonic_peak<T: Ord>(arr: &[T]) -> usize {
+    let (mut low, mut high) = (0, arr.len() - 1);
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if arr[mid] < arr[mid + 1] {
+            // still going up
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    return low;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::linear_search;
+    use super::*;
+
+    #[test]
+    fn test_bitonic_search_agrees_with_linear_search() {
+        let arrays: [&[i32]; 6] = [
+            &[1, 4, 9, 7, 2],
+            &[1, 2, 3, 4, 5],
+            &[5, 4, 3, 2, 1],
+            &[3],
+            &[0, 10, 8, 6, 4, 2, 1],
+            &[-5, -1, 2, 4, 8, 16, 15],
+        ];
+        for arr in arrays {
+            for target in -6..18 {
+                assert_eq!(bitonic_search(arr, target), linear_search(arr, target), "{:?}", arr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bitonic_peak() {
+        assert_eq!(bitonic_peak(&[1, 4, 9, 7, 2]), 2);
+        assert_eq!(bitonic_peak(&[1, 2, 3]), 2);
+        assert_eq!(bitonic_peak(&[3, 2, 1]), 0);
+    }
+
+    #[test]
+    fn test_edge_case_empty() {
+        let arr: Vec<i32> = vec![];
+        assert_eq!(bitonic_search(&arr, 0), None);
+    }
+}
@@ -0,0 +1,99 @@
+use super::SearchOutcome;
+
+/// Finds the point where `f` reaches its minimum over `[low, high]`, within
+/// `tolerance`. `f` must be unimodal on the interval.
+///
+/// Like a ternary search, but the two probes split the interval following the
+/// golden ratio. After dropping one side, the remaining probe is exactly at
+/// one of the golden points of the new interval, so every iteration costs a
+/// single evaluation of `f` and shrinks the interval by a factor of ~0.618.
+/// Time complexity: O(log((high - low) / tolerance)) evaluations of `f`.
+pub fn golden_section_search_min<F: Fn(f64) -> f64>(low: f64, high: f64, tolerance: f64, f: F) -> SearchOutcome<f64> {
+    golden_section_search(low, high, tolerance, f, |a, b| a < b)
+}
+
+/// Finds the point where `f` reaches its maximum over `[low, high]`, within
+/// `tolerance`.
+/// Time complexity: O(log((high - low) / tolerance)) evaluations of `f`.
+pub fn golden_section_search_max<F: Fn(f64) -> f64>(low: f64, high: f64, tolerance: f64, f: F) -> SearchOutcome<f64> {
+    golden_section_search(low, high, tolerance, f, |a, b| a > b)
+}
+
+// `better(a, b)` returns true if `a` is closer to the extremum than `b`.
+fn golden_section_search<F, B>(low: f64, high: f64, tolerance: f64, f: F, better: B) -> SearchOutcome<f64>
+where
+    F: Fn(f64) -> f64,
+    B: Fn(f64, f64) -> bool,
+{
+    // 1 / phi
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+
+    let (mut a, mut b) = if low <= high { (low, high) } else { (high, low) };
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let (mut fc, mut fd) = (f(c), f(d));
+    let mut iterations = 0;
+
+    while b - a > tolerance {
+        let width = b - a;
+        iterations += 1;
+
+        if better(fc, fd) {
+            // the extremum is in [a, d], and `c` becomes its upper probe
+            b = d;
+            d = c;
+            fd = fc;
+            c = b - ratio * (b - a);
+            fc = f(c);
+        } else {
+            // the extremum is in [c, b], and `d` becomes its lower probe
+            a = c;
+            c = d;
+            fc = fd;
+            d = a + ratio * (b - a);
+            fd = f(d);
+        }
+
+        // the floating point resolution has been reached
+        if b - a >= width {
+            break;
+        }
+    }
+
+    SearchOutcome {
+        value: a + (b - a) / 2.0,
+        iterations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_of_parabola() {
+        let res = golden_section_search_min(-10.0, 10.0, 1e-6, |x| (x - 2.5) * (x - 2.5) + 1.0);
+        assert!((res.value - 2.5).abs() < 1e-6);
+
+        // a width of 20 shrunk by ~0.618 per iteration down to 1e-6
+        assert_eq!(res.iterations, 35);
+    }
+
+    #[test]
+    fn test_max_of_sine() {
+        let res = golden_section_search_max(0.0, 3.0, 1e-6, f64::sin);
+        assert!((res.value - std::f64::consts::FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_extremum_at_the_bound() {
+        let res = golden_section_search_min(1.0, 5.0, 1e-9, |x| x);
+        assert!((res.value - 1.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_reversed_bounds_and_zero_tolerance() {
+        let res = golden_section_search_min(4.0, -4.0, 0.0, |x| (x + 1.0).abs());
+        assert!((res.value + 1.0).abs() < 1e-12);
+    }
+}
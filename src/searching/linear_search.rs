@@ -1,6 +1,6 @@
 pub fn linear_search<T: PartialEq>(arr: &[T], target: T) -> Option<usize> {
 
-    if arr.len() == 0 {
+    if arr.is_empty() {
         return None;
     }
 
@@ -1,3 +1,36 @@
 mod linear_search;
+mod ternary_search;
+mod golden_section_search;
+mod predicate_search;
+mod rotated_search;
+mod bitonic_search;
+mod peak_finding;
+mod staircase_search;
+mod sorted_intersection;
+mod sorted_set_iterators;
 
 pub use self::linear_search::linear_search;
+pub use self::ternary_search::{ternary_search_max, ternary_search_min};
+pub use self::golden_section_search::{golden_section_search_max, golden_section_search_min};
+pub use self::predicate_search::{binary_search_predicate, binary_search_predicate_f64};
+pub use self::rotated_search::{rotated_search, rotation_point};
+pub use self::bitonic_search::bitonic_search;
+pub use self::peak_finding::{peak_1d, peak_2d};
+pub use self::staircase_search::staircase_search;
+pub use self::sorted_intersection::{
+    baeza_yates_intersection,
+    galloping_intersection,
+    galloping_intersection_u32,
+    merge_intersection,
+    merge_intersection_u32,
+    multi_intersection
+};
+pub use self::sorted_set_iterators::{difference, union, Difference, Union};
+
+/// The value found by a search over a function, along with the number of
+/// iterations the search needed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchOutcome<T> {
+    pub value: T,
+    pub iterations: usize,
+}
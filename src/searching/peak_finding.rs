@@ -0,0 +1,134 @@
+/// Returns the index of a peak, i.e. an element that is not smaller than its
+/// neighbours. Every non-empty slice has at least one.
+///
+/// If `arr[mid] < arr[mid + 1]`, walking up from `mid + 1` must end at a peak
+/// before falling off the right end, so the right half always holds one.
+/// Time complexity: O(log n).
+pub fn peak_1d<T: PartialOrd>(arr: &[T]) -> Option<usize> {
+    if arr.is_empty() {
+        return None;
+    }
+
+    let (mut low, mut high) = (0, arr.len() - 1);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if arr[mid] < arr[mid + 1] {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    return Some(low);
+}
+
+/// Returns the `(row, column)` of a peak of a row-major matrix with `cols`
+/// columns, i.e. an element not smaller than its four neighbours.
+///
+/// Takes the maximum of the middle column. If a horizontal neighbour is
+/// larger, the half on its side holds a peak for the same reason as in
+/// `peak_1d`, and the maximum of the middle column also keeps any peak found
+/// there from being beaten by a neighbour in the dropped half.
+/// Time complexity: O(rows * log(cols)).
+pub fn peak_2d<T: PartialOrd>(matrix: &[T], cols: usize) -> Option<(usize, usize)> {
+    if matrix.is_empty() || cols == 0 {
+        return None;
+    }
+    assert_eq!(matrix.len() % cols, 0, "the matrix has {} columns", cols);
+    let rows = matrix.len() / cols;
+    let at = |row: usize, col: usize| &matrix[row * cols + col];
+
+    let (mut low, mut high) = (0, cols - 1);
+    loop {
+        let mid = low + (high - low) / 2;
+
+        // row of the maximum of the middle column
+        let mut row = 0;
+        for r in 1..rows {
+            if at(r, mid) > at(row, mid) {
+                row = r;
+            }
+        }
+
+        if mid > low && at(row, mid - 1) > at(row, mid) {
+            high = mid - 1;
+        } else if mid < high && at(row, mid + 1) > at(row, mid) {
+            low = mid + 1;
+        } else {
+            return Some((row, mid));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    fn is_peak_1d(arr: &[i32], i: usize) -> bool {
+        (i == 0 || arr[i - 1] <= arr[i]) && (i + 1 == arr.len() || arr[i + 1] <= arr[i])
+    }
+
+    fn is_peak_2d(matrix: &[i32], cols: usize, (row, col): (usize, usize)) -> bool {
+        let rows = matrix.len() / cols;
+        let value = matrix[row * cols + col];
+        let neighbours = [
+            (row > 0).then(|| matrix[(row - 1) * cols + col]),
+            (row + 1 < rows).then(|| matrix[(row + 1) * cols + col]),
+            (col > 0).then(|| matrix[row * cols + col - 1]),
+            (col + 1 < cols).then(|| matrix[row * cols + col + 1]),
+        ];
+        neighbours.iter().flatten().all(|&n| n <= value)
+    }
+
+    // Deterministic pseudo-random values.
+    fn values(seed: u64, len: usize) -> Vec<i32> {
+        let mut rng = XorShift::new(seed);
+        (0..len).map(|_| rng.below(50) as i32).collect()
+    }
+
+    #[test]
+    fn test_peak_1d() {
+        assert_eq!(peak_1d(&[1, 3, 20, 4, 1, 0]), Some(2));
+        assert_eq!(peak_1d(&[1, 2, 3]), Some(2));
+        assert_eq!(peak_1d(&[3, 2, 1]), Some(0));
+        assert_eq!(peak_1d::<i32>(&[]), None);
+    }
+
+    #[test]
+    fn test_peak_1d_random() {
+        for seed in 1..50 {
+            let arr = values(seed, seed as usize % 17 + 1);
+            let i = peak_1d(&arr).unwrap();
+            assert!(is_peak_1d(&arr, i), "{:?} at {}", arr, i);
+        }
+    }
+
+    #[test]
+    fn test_peak_2d() {
+        let matrix = [
+            10, 8, 10, 10, //
+            14, 13, 12, 11, //
+            15, 9, 11, 21, //
+            16, 17, 19, 20,
+        ];
+        let peak = peak_2d(&matrix, 4).unwrap();
+        assert!(is_peak_2d(&matrix, 4, peak));
+    }
+
+    #[test]
+    fn test_peak_2d_random() {
+        for seed in 1..50 {
+            let cols = seed as usize % 7 + 1;
+            let rows = seed as usize % 5 + 1;
+            let matrix = values(seed, rows * cols);
+            let peak = peak_2d(&matrix, cols).unwrap();
+            assert!(is_peak_2d(&matrix, cols, peak), "{:?} at {:?}", matrix, peak);
+        }
+    }
+
+    #[test]
+    fn test_peak_2d_empty() {
+        assert_eq!(peak_2d::<i32>(&[], 3), None);
+    }
+}
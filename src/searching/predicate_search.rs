@@ -0,0 +1,127 @@
+use super::SearchOutcome;
+
+/// Returns the smallest `x` in `[low, high]` for which `predicate(x)` holds,
+/// or `None` if it holds nowhere. Also known as "binary search on the
+/// answer".
+///
+/// `predicate` must be monotone on the range: once it holds, it holds for
+/// every larger value.
+/// Time complexity: O(log n) evaluations of `predicate`.
+pub fn binary_search_predicate<P: Fn(i64) -> bool>(low: i64, high: i64, predicate: P) -> SearchOutcome<Option<i64>> {
+    // the answer is in `[low, high + 1]`, where `high + 1` means "none", so
+    // i128 keeps it from overflowing
+    let (mut low, mut high) = (low as i128, high as i128 + 1);
+    let end = high;
+    let mut iterations = 0;
+
+    while low < high {
+        iterations += 1;
+
+        let mid = low + (high - low) / 2;
+        if predicate(mid as i64) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    let value = if low < end { Some(low as i64) } else { None };
+    SearchOutcome { value, iterations }
+}
+
+/// Returns the smallest `x` in `[low, high]`, within `tolerance`, for which
+/// `predicate(x)` holds, or `None` if it doesn't even hold at `high`.
+///
+/// `predicate` must be monotone on the range. The search stops when the
+/// bracket is narrower than `tolerance` or when the floating point resolution
+/// is reached, and returns the upper end of the bracket, which satisfies the
+/// predicate.
+/// Time complexity: O(log((high - low) / tolerance)) evaluations of `predicate`.
+pub fn binary_search_predicate_f64<P: Fn(f64) -> bool>(
+    low: f64,
+    high: f64,
+    tolerance: f64,
+    predicate: P,
+) -> SearchOutcome<Option<f64>> {
+    let mut iterations = 1;
+    if !predicate(high) {
+        return SearchOutcome { value: None, iterations };
+    }
+
+    let (mut low, mut high) = (low, high);
+    while high - low > tolerance {
+        let mid = low + (high - low) / 2.0;
+        if mid <= low || mid >= high {
+            break;
+        }
+        iterations += 1;
+
+        if predicate(mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    SearchOutcome {
+        value: Some(high),
+        iterations,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_true() {
+        let res = binary_search_predicate(0, 100, |x| x * x >= 200);
+        assert_eq!(res.value, Some(15));
+        assert!(res.iterations <= 7);
+    }
+
+    #[test]
+    fn test_no_and_all_true() {
+        assert_eq!(binary_search_predicate(0, 100, |_| false).value, None);
+        assert_eq!(binary_search_predicate(0, 100, |_| true).value, Some(0));
+        assert_eq!(binary_search_predicate(5, 4, |_| true).value, None);
+    }
+
+    #[test]
+    fn test_full_range() {
+        let res = binary_search_predicate(i64::MIN, i64::MAX, |x| x >= -7);
+        assert_eq!(res.value, Some(-7));
+        assert!(res.iterations <= 65);
+
+        assert_eq!(binary_search_predicate(i64::MIN, i64::MAX, |x| x == i64::MAX).value, Some(i64::MAX));
+    }
+
+    #[test]
+    fn test_lower_bound_in_sorted_slice() {
+        let arr = [1, 3, 3, 5, 8, 13];
+        for target in 0..15 {
+            let res = binary_search_predicate(0, arr.len() as i64 - 1, |i| arr[i as usize] >= target);
+            let expected = arr.iter().position(|&x| x >= target).map(|i| i as i64);
+            assert_eq!(res.value, expected);
+        }
+    }
+
+    #[test]
+    fn test_square_root() {
+        let res = binary_search_predicate_f64(0.0, 2.0, 1e-12, |x| x * x >= 2.0);
+        assert!((res.value.unwrap() - std::f64::consts::SQRT_2).abs() < 1e-11);
+    }
+
+    #[test]
+    fn test_f64_never_true() {
+        let res = binary_search_predicate_f64(0.0, 1.0, 1e-6, |x| x > 1.0);
+        assert_eq!(res.value, None);
+        assert_eq!(res.iterations, 1);
+    }
+
+    #[test]
+    fn test_f64_zero_tolerance_terminates() {
+        let res = binary_search_predicate_f64(0.0, 1.0, 0.0, |x| x >= 0.3);
+        assert!((res.value.unwrap() - 0.3).abs() < 1e-15);
+    }
+}
@@ -0,0 +1,119 @@
+/// Returns the number of positions a sorted slice has been rotated to the
+/// right: the index right after the one place where the elements decrease, or
+/// 0 if they never do. It holds a smallest element, but with duplicates not
+/// necessarily the first one: `[1, 1, 2, 1]` gives 3, not 0.
+///
+/// Binary search on the fact that the elements before the rotation point are
+/// all greater or equal to the ones after it. With duplicates, `arr[mid] ==
+/// arr[high]` tells nothing about the side of the rotation point, so the
+/// range can only shrink by one, and the worst case degrades to O(n).
+/// Time complexity: O(log n) for distinct elements.
+pub fn rotation_point<T: Ord>(arr: &[T]) -> usize {
+    if arr.is_empty() {
+        return 0;
+    }
+
+    let (mut low, mut high) = (0, arr.len() - 1);
+    while low < high {
+        let mid = low + (high - low) / 2;
+
+        if arr[mid] > arr[high] {
+            // the drop is after `mid`
+            low = mid + 1;
+        } else if arr[mid] < arr[high] {
+            // `arr[mid..=high]` is sorted, so the drop is at `mid` or before
+            high = mid;
+        } else {
+            // `arr[high]` might be the first element after the drop
+            if arr[high - 1] > arr[high] {
+                return high;
+            }
+            high -= 1;
+        }
+    }
+
+    return low;
+}
+
+/// Searches `target` in a sorted slice that has been rotated, e.g.
+/// `[4, 5, 6, 1, 2, 3]`, and returns its index.
+/// Time complexity: O(log n) for distinct elements.
+pub fn rotated_search<T: Ord>(arr: &[T], target: T) -> Option<usize> {
+    let pivot = rotation_point(arr);
+
+    // both `arr[pivot..]` and `arr[..pivot]` are sorted, and every element of
+    // the second one is at least as large as the ones of the first one
+    let (offset, part) = match arr.last() {
+        Some(last) if target > *last => (0, &arr[..pivot]),
+        _ => (pivot, &arr[pivot..]),
+    };
+
+    return part.binary_search(&target).ok().map(|i| i + offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::linear_search;
+    use super::*;
+
+    fn rotate(sorted: &[i32], k: usize) -> Vec<i32> {
+        let mut arr = sorted.to_vec();
+        arr.rotate_right(k);
+        arr
+    }
+
+    #[test]
+    fn test_rotation_point() {
+        let sorted = [1, 2, 3, 4, 5, 6, 7];
+        for k in 0..sorted.len() {
+            assert_eq!(rotation_point(&rotate(&sorted, k)), k);
+        }
+        assert_eq!(rotation_point::<i32>(&[]), 0);
+    }
+
+    #[test]
+    fn test_rotation_point_with_duplicates() {
+        let sorted = [1, 1, 1, 2, 2, 3, 3, 3];
+        for k in 0..sorted.len() {
+            let arr = rotate(&sorted, k);
+            let expected = (0..arr.len()).find(|&i| i > 0 && arr[i - 1] > arr[i]).unwrap_or(0);
+            assert_eq!(rotation_point(&arr), expected, "{:?}", arr);
+        }
+        assert_eq!(rotation_point(&[2, 2, 2, 1, 2]), 3);
+        assert_eq!(rotation_point(&[2, 1, 2, 2, 2]), 1);
+        // the minimum also comes first, before the drop
+        assert_eq!(rotation_point(&[1, 1, 2, 1]), 3);
+    }
+
+    #[test]
+    fn test_rotated_search_agrees_with_linear_search() {
+        let sorted = [2, 3, 5, 7, 11, 13, 17, 19];
+        for k in 0..sorted.len() {
+            let arr = rotate(&sorted, k);
+            for target in 0..22 {
+                assert_eq!(rotated_search(&arr, target), linear_search(&arr, target));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotated_search_with_duplicates() {
+        let sorted = [1, 1, 2, 2, 2, 3, 5, 5];
+        for k in 0..sorted.len() {
+            let arr = rotate(&sorted, k);
+            for target in 0..7 {
+                let res = rotated_search(&arr, target);
+                assert_eq!(res.is_some(), linear_search(&arr, target).is_some());
+                if let Some(i) = res {
+                    assert_eq!(arr[i], target);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_edge_case_empty() {
+        let arr: Vec<i32> = vec![];
+        assert_eq!(rotated_search(&arr, 1), None);
+    }
+}
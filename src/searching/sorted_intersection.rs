@@ -0,0 +1,282 @@
+// All the functions in this file take strictly increasing slices (sorted
+// sets, such as posting lists) and return their intersection in increasing
+// order.
+
+/// Intersects two sorted sets by walking both of them like the merge step of
+/// merge sort.
+/// Time complexity: O(n + m).
+pub fn merge_intersection<T: Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i] < b[j] {
+            i += 1;
+        } else if a[i] > b[j] {
+            j += 1;
+        } else {
+            result.push(a[i].clone());
+            i += 1;
+            j += 1;
+        }
+    }
+
+    return result;
+}
+
+/// Intersects two sorted sets by looking up every element of the smaller one
+/// in the larger one with a galloping (exponential) search that starts where
+/// the previous lookup ended.
+///
+/// This wins over `merge_intersection` when the sets have very different
+/// sizes, e.g. a rare term against a frequent one.
+/// Time complexity: O(n * log(m / n)), where `n` is the size of the smaller set.
+pub fn galloping_intersection<T: Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::new();
+    let mut start = 0;
+
+    for x in small {
+        start = gallop(large, start, x);
+        if start == large.len() {
+            break;
+        }
+        if large[start] == *x {
+            result.push(x.clone());
+            start += 1;
+        }
+    }
+
+    return result;
+}
+
+/// Intersects two sorted sets with Baeza-Yates' adaptive algorithm: the
+/// median of the smaller set is searched in the larger one, which splits both
+/// sets in two independent subproblems.
+///
+/// The number of comparisons adapts to how the sets interleave, and it is
+/// close to optimal both for sets of similar and of very different sizes.
+/// Time complexity: O(n * log(m / n)) in the worst case.
+pub fn baeza_yates_intersection<T: Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+    let mut result = Vec::new();
+    baeza_yates(a, b, &mut result);
+    return result;
+}
+
+fn baeza_yates<T: Ord + Clone>(a: &[T], b: &[T], result: &mut Vec<T>) {
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+
+    let mid = small.len() / 2;
+    let median = &small[mid];
+    let position = large.partition_point(|x| x < median);
+
+    // the left halves hold the elements smaller than the median, so they are
+    // solved first to keep the result sorted
+    baeza_yates(&small[..mid], &large[..position], result);
+
+    if position < large.len() && large[position] == *median {
+        result.push(median.clone());
+        baeza_yates(&small[mid + 1..], &large[position + 1..], result);
+    } else {
+        baeza_yates(&small[mid + 1..], &large[position..], result);
+    }
+}
+
+/// Intersects any number of sorted sets. The sets are processed from the
+/// smallest to the largest, so the candidates shrink as fast as possible, and
+/// each step uses `galloping_intersection` since the candidates are usually
+/// far fewer than the elements of the next set.
+pub fn multi_intersection<T: Ord + Clone>(sets: &[&[T]]) -> Vec<T> {
+    if sets.is_empty() {
+        return Vec::new();
+    }
+
+    let mut order: Vec<&[T]> = sets.to_vec();
+    order.sort_by_key(|set| set.len());
+
+    let mut result = order[0].to_vec();
+    for set in &order[1..] {
+        if result.is_empty() {
+            break;
+        }
+        result = galloping_intersection(&result, set);
+    }
+
+    return result;
+}
+
+/// Intersects two sorted sets of `u32`, comparing them by blocks of 8.
+///
+/// Every element of a block of `a` is compared against every element of a
+/// block of `b` without branching, which the compiler turns into vector
+/// instructions, and the block with the smaller last element moves forward.
+/// The elements left over once a set has less than a block are merged one by
+/// one.
+/// Time complexity: O(n + m).
+pub fn merge_intersection_u32(a: &[u32], b: &[u32]) -> Vec<u32> {
+    const BLOCK: usize = 8;
+    let mut result = Vec::with_capacity(a.len().min(b.len()));
+    let (mut i, mut j) = (0, 0);
+
+    while i + BLOCK <= a.len() && j + BLOCK <= b.len() {
+        let block_a: &[u32; BLOCK] = a[i..i + BLOCK].try_into().unwrap();
+        let block_b: &[u32; BLOCK] = b[j..j + BLOCK].try_into().unwrap();
+
+        for &x in block_a {
+            let found = block_b.iter().fold(false, |found, &y| found | (x == y));
+            if found {
+                result.push(x);
+            }
+        }
+
+        // Blocks that overlap are compared exactly once, like intervals in a
+        // merge, so no common element is reported twice.
+        let (last_a, last_b) = (block_a[BLOCK - 1], block_b[BLOCK - 1]);
+        if last_a <= last_b {
+            i += BLOCK;
+        }
+        if last_b <= last_a {
+            j += BLOCK;
+        }
+    }
+
+    result.extend(merge_intersection(&a[i..], &b[j..]));
+    return result;
+}
+
+/// Galloping intersection of two sorted sets of `u32`. Once the gallop has
+/// bracketed an element, the last step counts the smaller elements of a small
+/// window without branching instead of running a binary search.
+/// Time complexity: O(n * log(m / n)), where `n` is the size of the smaller set.
+pub fn galloping_intersection_u32(a: &[u32], b: &[u32]) -> Vec<u32> {
+    const WINDOW: usize = 16;
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let mut result = Vec::with_capacity(small.len());
+    let mut start = 0;
+
+    for &x in small {
+        // gallop until the window ending at `start + step` holds `x`
+        let mut step = WINDOW;
+        while start + step < large.len() && large[start + step - 1] < x {
+            start += step;
+            step *= 2;
+        }
+
+        let end = (start + step).min(large.len());
+        let window = &large[start..end];
+        if window.len() <= WINDOW {
+            start += window.iter().map(|&y| (y < x) as usize).sum::<usize>();
+        } else {
+            start += window.partition_point(|&y| y < x);
+        }
+
+        if start == large.len() {
+            break;
+        }
+        if large[start] == x {
+            result.push(x);
+            start += 1;
+        }
+    }
+
+    return result;
+}
+
+// Returns the first index at or after `start` whose element is not smaller
+// than `x`, probing `start + 1`, `start + 3`, `start + 7`, ... before a binary
+// search of the last bracket.
+pub(crate) fn gallop<T: Ord>(arr: &[T], start: usize, x: &T) -> usize {
+    let mut low = start;
+    let mut step = 1;
+
+    while low + step <= arr.len() && arr[low + step - 1] < *x {
+        low += step;
+        step *= 2;
+    }
+
+    let high = (low + step).min(arr.len());
+    return low + arr[low..high].partition_point(|y| y < x);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_rng::XorShift;
+    use std::collections::BTreeSet;
+
+    // Deterministic pseudo-random sorted set with roughly `density` percent of
+    // the values below `limit`.
+    fn set(seed: u64, limit: u32, density: u64) -> Vec<u32> {
+        let mut rng = XorShift::new(seed);
+        (0..limit).filter(|_| rng.below(100) < density).collect()
+    }
+
+    fn reference(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let a: BTreeSet<u32> = a.iter().copied().collect();
+        let b: BTreeSet<u32> = b.iter().copied().collect();
+        a.intersection(&b).copied().collect()
+    }
+
+    #[test]
+    fn test_small_example() {
+        let a = [1, 3, 5, 7, 9, 11];
+        let b = [2, 3, 4, 9, 10, 11, 12];
+        let expected = vec![3, 9, 11];
+        assert_eq!(merge_intersection(&a, &b), expected);
+        assert_eq!(galloping_intersection(&a, &b), expected);
+        assert_eq!(baeza_yates_intersection(&a, &b), expected);
+        assert_eq!(merge_intersection_u32(&a, &b), expected);
+        assert_eq!(galloping_intersection_u32(&a, &b), expected);
+    }
+
+    #[test]
+    fn test_all_intersections_agree_with_btree_set() {
+        let cases = [(1, 2, 1000, 50, 50), (3, 4, 5000, 1, 60), (5, 6, 3000, 90, 95), (7, 8, 200, 0, 30)];
+        for (seed_a, seed_b, limit, density_a, density_b) in cases {
+            let a = set(seed_a, limit, density_a);
+            let b = set(seed_b, limit, density_b);
+            let expected = reference(&a, &b);
+
+            assert_eq!(merge_intersection(&a, &b), expected);
+            assert_eq!(galloping_intersection(&a, &b), expected);
+            assert_eq!(galloping_intersection(&b, &a), expected);
+            assert_eq!(baeza_yates_intersection(&a, &b), expected);
+            assert_eq!(merge_intersection_u32(&a, &b), expected);
+            assert_eq!(merge_intersection_u32(&b, &a), expected);
+            assert_eq!(galloping_intersection_u32(&a, &b), expected);
+        }
+    }
+
+    #[test]
+    fn test_multi_intersection() {
+        let a = set(11, 2000, 60);
+        let b = set(12, 2000, 40);
+        let c = set(13, 2000, 5);
+        let expected = reference(&reference(&a, &b), &c);
+        assert_eq!(multi_intersection(&[&a, &b, &c]), expected);
+        assert_eq!(multi_intersection(&[&c, &a, &b]), expected);
+        assert_eq!(multi_intersection(&[&a[..]]), a);
+        assert_eq!(multi_intersection::<u32>(&[]), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_disjoint_and_empty() {
+        let even: Vec<u32> = (0..100).map(|x| 2 * x).collect();
+        let odd: Vec<u32> = (0..100).map(|x| 2 * x + 1).collect();
+        assert!(merge_intersection_u32(&even, &odd).is_empty());
+        assert!(galloping_intersection(&even, &[]).is_empty());
+        assert!(baeza_yates_intersection(&[], &odd).is_empty());
+    }
+
+    #[test]
+    fn test_gallop() {
+        let arr = [1, 3, 5, 7, 9, 11, 13];
+        assert_eq!(gallop(&arr, 0, &0), 0);
+        assert_eq!(gallop(&arr, 0, &8), 4);
+        assert_eq!(gallop(&arr, 2, &5), 2);
+        assert_eq!(gallop(&arr, 3, &14), 7);
+    }
+}
@@ -0,0 +1,139 @@
+use std::cmp::Ordering;
+
+use super::sorted_intersection::gallop;
+
+/// Returns an iterator over the elements of the sorted sets `a` or `b`, in
+/// increasing order and without duplicates.
+pub fn union<'a, T: Ord>(a: &'a [T], b: &'a [T]) -> Union<'a, T> {
+    Union { a, b }
+}
+
+/// Returns an iterator over the elements of the sorted set `a` that are not
+/// in the sorted set `b`, in increasing order.
+pub fn difference<'a, T: Ord>(a: &'a [T], b: &'a [T]) -> Difference<'a, T> {
+    Difference { a, b }
+}
+
+/// Lazy union of two sorted sets, created by `union`.
+/// Time complexity: O(n + m) for the whole iteration.
+#[derive(Clone, Debug)]
+pub struct Union<'a, T> {
+    a: &'a [T],
+    b: &'a [T],
+}
+
+impl<'a, T: Ord> Iterator for Union<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let ordering = match (self.a.first(), self.b.first()) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(x), Some(y)) => x.cmp(y),
+        };
+
+        // an element in both sets is returned once, from `a`
+        let (item, rest) = match ordering {
+            Ordering::Greater => self.b.split_first().unwrap(),
+            _ => self.a.split_first().unwrap(),
+        };
+        match ordering {
+            Ordering::Less => self.a = rest,
+            Ordering::Greater => self.b = rest,
+            Ordering::Equal => {
+                self.a = rest;
+                self.b = &self.b[1..];
+            }
+        }
+
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (n, m) = (self.a.len(), self.b.len());
+        (n.max(m), Some(n + m))
+    }
+}
+
+/// Lazy difference of two sorted sets, created by `difference`.
+///
+/// Elements of `b` are skipped with a galloping search, so a small `a`
+/// against a large `b` costs O(n * log(m / n)) instead of O(n + m).
+#[derive(Clone, Debug)]
+pub struct Difference<'a, T> {
+    a: &'a [T],
+    b: &'a [T],
+}
+
+impl<'a, T: Ord> Iterator for Difference<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            let (item, rest) = self.a.split_first()?;
+            self.a = rest;
+
+            // drop the elements of `b` that are smaller than `item`
+            let skip = gallop(self.b, 0, item);
+            self.b = &self.b[skip..];
+
+            match self.b.first() {
+                Some(x) if x == item => self.b = &self.b[1..],
+                _ => return Some(item),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.a.len().saturating_sub(self.b.len()), Some(self.a.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_union() {
+        let a = [1, 3, 5, 7];
+        let b = [2, 3, 4, 8, 9];
+        let res: Vec<i32> = union(&a, &b).copied().collect();
+        assert_eq!(res, vec![1, 2, 3, 4, 5, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_difference() {
+        let a = [1, 3, 5, 7, 9];
+        let b = [0, 3, 4, 9, 10];
+        let res: Vec<i32> = difference(&a, &b).copied().collect();
+        assert_eq!(res, vec![1, 5, 7]);
+
+        let res: Vec<i32> = difference(&b, &a).copied().collect();
+        assert_eq!(res, vec![0, 4, 10]);
+    }
+
+    #[test]
+    fn test_empty_sets() {
+        let empty: [i32; 0] = [];
+        let a = [1, 2];
+        assert_eq!(union(&empty, &a).count(), 2);
+        assert_eq!(union(&a, &empty).count(), 2);
+        assert_eq!(difference(&a, &empty).count(), 2);
+        assert_eq!(difference(&empty, &a).count(), 0);
+    }
+
+    #[test]
+    fn test_agrees_with_btree_set() {
+        let a: Vec<u32> = (0..300).filter(|x| x % 3 == 0 || x % 7 == 1).collect();
+        let b: Vec<u32> = (0..300).filter(|x| x % 5 == 0).collect();
+        let (set_a, set_b): (BTreeSet<u32>, BTreeSet<u32>) = (a.iter().copied().collect(), b.iter().copied().collect());
+
+        let expected: Vec<u32> = set_a.union(&set_b).copied().collect();
+        assert_eq!(union(&a, &b).copied().collect::<Vec<u32>>(), expected);
+
+        let expected: Vec<u32> = set_a.difference(&set_b).copied().collect();
+        assert_eq!(difference(&a, &b).copied().collect::<Vec<u32>>(), expected);
+    }
+}
@@ -0,0 +1,71 @@
+/// Searches `target` in a row-major matrix with `cols` columns where every
+/// row and every column is sorted in increasing order, and returns its
+/// `(row, column)`.
+///
+/// Starts at the top-right corner: if the current element is larger than the
+/// target, so is the rest of its column, and if it is smaller, so is the rest
+/// of its row. Each step drops a row or a column.
+/// Time complexity: O(rows + cols).
+pub fn staircase_search<T: Ord>(matrix: &[T], cols: usize, target: T) -> Option<(usize, usize)> {
+    if matrix.is_empty() || cols == 0 {
+        return None;
+    }
+    assert_eq!(matrix.len() % cols, 0, "the matrix has {} columns", cols);
+    let rows = matrix.len() / cols;
+
+    let (mut row, mut col) = (0, cols - 1);
+    loop {
+        let current = &matrix[row * cols + col];
+
+        if *current == target {
+            return Some((row, col));
+        } else if *current > target {
+            if col == 0 {
+                return None;
+            }
+            col -= 1;
+        } else {
+            row += 1;
+            if row == rows {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::linear_search;
+    use super::*;
+
+    #[test]
+    fn test_staircase_search_agrees_with_linear_search() {
+        let cols = 4;
+        let matrix = [
+            1, 4, 7, 11, //
+            2, 5, 8, 12, //
+            3, 6, 9, 16, //
+            10, 13, 14, 17, //
+            18, 21, 23, 26,
+        ];
+        for target in 0..28 {
+            let expected = linear_search(&matrix, target).map(|i| (i / cols, i % cols));
+            assert_eq!(staircase_search(&matrix, cols, target), expected);
+        }
+    }
+
+    #[test]
+    fn test_single_row_and_column() {
+        let row = [1, 3, 5, 7];
+        assert_eq!(staircase_search(&row, 4, 5), Some((0, 2)));
+        assert_eq!(staircase_search(&row, 4, 4), None);
+        assert_eq!(staircase_search(&row, 1, 7), Some((3, 0)));
+        assert_eq!(staircase_search(&row, 1, 8), None);
+    }
+
+    #[test]
+    fn test_edge_case_empty() {
+        let matrix: Vec<i32> = vec![];
+        assert_eq!(staircase_search(&matrix, 3, 1), None);
+    }
+}
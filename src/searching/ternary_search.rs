@@ -0,0 +1,116 @@
+use super::SearchOutcome;
+
+/// Finds the position of the maximum of `f` over `[low, high]`.
+///
+/// `f` must be strictly unimodal on the range, i.e. strictly increasing and
+/// then strictly decreasing (either part may be empty). Each iteration
+/// evaluates `f` at two points splitting the range in thirds and drops the
+/// third that can't hold the maximum.
+/// Time complexity: O(log n) evaluations of `f`.
+pub fn ternary_search_max<T: PartialOrd, F: Fn(i64) -> T>(low: i64, high: i64, f: F) -> Option<SearchOutcome<i64>> {
+    ternary_search(low, high, f, |a, b| a < b)
+}
+
+/// Finds the position of the minimum of `f` over `[low, high]`, where `f` is
+/// strictly decreasing and then strictly increasing.
+/// Time complexity: O(log n) evaluations of `f`.
+pub fn ternary_search_min<T: PartialOrd, F: Fn(i64) -> T>(low: i64, high: i64, f: F) -> Option<SearchOutcome<i64>> {
+    ternary_search(low, high, f, |a, b| a > b)
+}
+
+// `worse(a, b)` returns true if `a` is farther from the extremum than `b`.
+fn ternary_search<T, F, W>(low: i64, high: i64, f: F, worse: W) -> Option<SearchOutcome<i64>>
+where
+    F: Fn(i64) -> T,
+    W: Fn(&T, &T) -> bool,
+{
+    if low > high {
+        return None;
+    }
+
+    // i128 keeps `high - low` from overflowing on extreme ranges
+    let (mut low, mut high) = (low as i128, high as i128);
+    let mut iterations = 0;
+
+    while high - low > 2 {
+        iterations += 1;
+
+        let third = (high - low) / 3;
+        let m1 = low + third;
+        let m2 = high - third;
+
+        // The extremum can't be on the side of the worse probe: if `f(m1)` is
+        // worse, then `m1` is still before the extremum, and vice versa.
+        if worse(&f(m1 as i64), &f(m2 as i64)) {
+            low = m1 + 1;
+        } else {
+            high = m2 - 1;
+        }
+    }
+
+    // at most three candidates are left
+    let mut best = low;
+    for x in (low + 1)..=high {
+        if worse(&f(best as i64), &f(x as i64)) {
+            best = x;
+        }
+    }
+
+    Some(SearchOutcome {
+        value: best as i64,
+        iterations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_of_unimodal_slice() {
+        let arr = [1, 3, 8, 12, 15, 14, 9, 4, 2];
+        let res = ternary_search_max(0, arr.len() as i64 - 1, |i| arr[i as usize]).unwrap();
+        assert_eq!(res.value, 4);
+    }
+
+    #[test]
+    fn test_max_at_the_bounds() {
+        let increasing = [1, 2, 3, 4, 5, 6, 7];
+        let res = ternary_search_max(0, 6, |i| increasing[i as usize]).unwrap();
+        assert_eq!(res.value, 6);
+
+        let decreasing = [7, 6, 5, 4, 3, 2, 1];
+        let res = ternary_search_max(0, 6, |i| decreasing[i as usize]).unwrap();
+        assert_eq!(res.value, 0);
+    }
+
+    #[test]
+    fn test_min_of_parabola() {
+        let res = ternary_search_min(-1_000_000, 1_000_000, |x| (x - 1234) * (x - 1234)).unwrap();
+        assert_eq!(res.value, 1234);
+
+        // every iteration drops a third of the range
+        assert!(res.iterations <= 36);
+    }
+
+    #[test]
+    fn test_every_peak_position() {
+        for n in 1..20 {
+            for peak in 0..n {
+                let f = |i: i64| -(i - peak).abs();
+                assert_eq!(ternary_search_max(0, n - 1, f).unwrap().value, peak);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extreme_range() {
+        let res = ternary_search_max(i64::MIN, i64::MAX, |x| -((x as i128) - 42).abs()).unwrap();
+        assert_eq!(res.value, 42);
+    }
+
+    #[test]
+    fn test_empty_range() {
+        assert_eq!(ternary_search_max(5, 4, |x| x), None);
+    }
+}
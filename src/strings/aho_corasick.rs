@@ -0,0 +1,700 @@
+use std::collections::VecDeque;
+
+// Identifier of the root state, i.e. the empty prefix. Unanchored searches
+// fall back to it whenever no pattern prefix is alive anymore.
+const ROOT: usize = 0;
+
+/// Selects which matches the automaton reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchKind {
+    /// Report every occurrence of every pattern, including overlapping ones.
+    Overlapping,
+    /// Report non-overlapping matches, preferring the match that starts first
+    /// and, among those, the pattern that was given first to the builder.
+    LeftmostFirst,
+    /// Report non-overlapping matches, preferring the match that starts first
+    /// and, among those, the longest one.
+    LeftmostLongest,
+}
+
+/// A single occurrence of a pattern in the haystack. `start..end` is the
+/// half-open byte range of the occurrence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Errors reported while building an automaton.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The pattern with the given index is empty. Empty patterns would match
+    /// at every position, so they are rejected.
+    EmptyPattern(usize),
+}
+
+/// Configures and builds an `AhoCorasick` automaton.
+#[derive(Clone, Debug)]
+pub struct AhoCorasickBuilder {
+    match_kind: MatchKind,
+    ascii_case_insensitive: bool,
+    dense: bool,
+}
+
+impl AhoCorasickBuilder {
+    pub fn new() -> Self {
+        Self {
+            match_kind: MatchKind::LeftmostFirst,
+            ascii_case_insensitive: false,
+            dense: false,
+        }
+    }
+
+    pub fn match_kind(&mut self, kind: MatchKind) -> &mut Self {
+        self.match_kind = kind;
+        self
+    }
+
+    /// When enabled, ASCII letters match regardless of their case. Non-ASCII
+    /// bytes are always compared exactly.
+    pub fn ascii_case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.ascii_case_insensitive = yes;
+        self
+    }
+
+    /// When enabled, the failure links are compiled away into a full
+    /// `states x 256` transition table, so every input byte costs exactly
+    /// one lookup. This trades memory for speed.
+    pub fn dense(&mut self, yes: bool) -> &mut Self {
+        self.dense = yes;
+        self
+    }
+
+    pub fn build<I, P>(&self, patterns: I) -> Result<AhoCorasick, BuildError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        let mut byte_map = [0u8; 256];
+        for (i, b) in byte_map.iter_mut().enumerate() {
+            *b = if self.ascii_case_insensitive {
+                (i as u8).to_ascii_lowercase()
+            } else {
+                i as u8
+            };
+        }
+
+        // Build the trie. Every state keeps its outgoing edges sorted by byte.
+        let mut states = vec![State::new(0)];
+        let mut pattern_lens = Vec::new();
+        for (id, pattern) in patterns.into_iter().enumerate() {
+            let pattern = pattern.as_ref();
+            if pattern.is_empty() {
+                return Err(BuildError::EmptyPattern(id));
+            }
+
+            let mut current = ROOT;
+            for &byte in pattern {
+                let byte = byte_map[byte as usize];
+                current = match states[current].find(byte) {
+                    Some(next) => next,
+                    None => {
+                        let next = states.len();
+                        states.push(State::new(states[current].depth + 1));
+                        states[current].add(byte, next);
+                        next
+                    }
+                };
+            }
+            states[current].matches.push(id);
+            pattern_lens.push(pattern.len());
+        }
+
+        // Compute the failure links breadth first, so the failure state of a
+        // node (which is always shallower) is final before its children need it.
+        // The match list of every state is extended with the matches of its
+        // failure state, so a state knows about every pattern ending there.
+        let mut queue = VecDeque::new();
+        for i in 0..states[ROOT].edges.len() {
+            let child = states[ROOT].edges[i].1;
+            states[child].fail = ROOT;
+            queue.push_back(child);
+        }
+        while let Some(current) = queue.pop_front() {
+            for i in 0..states[current].edges.len() {
+                let (byte, child) = states[current].edges[i];
+                let mut fail = states[current].fail;
+                let target = loop {
+                    if let Some(next) = states[fail].find(byte) {
+                        break next;
+                    }
+                    if fail == ROOT {
+                        break ROOT;
+                    }
+                    fail = states[fail].fail;
+                };
+                states[child].fail = target;
+                let inherited = states[target].matches.clone();
+                states[child].matches.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        // All matches of a state end at the same position, so the longest one
+        // starts first. Sorting by length (and then by pattern order) puts the
+        // preferred match of both leftmost semantics at the head of the list.
+        for state in states.iter_mut() {
+            state.matches.sort_by(|&a, &b| pattern_lens[b].cmp(&pattern_lens[a]).then(a.cmp(&b)));
+        }
+
+        let mut automaton = AhoCorasick {
+            kind: self.match_kind,
+            byte_map,
+            states,
+            pattern_lens,
+            dense: None,
+        };
+
+        if self.dense {
+            let mut table = vec![ROOT; automaton.states.len() * 256];
+            for state in 0..automaton.states.len() {
+                for byte in 0..256 {
+                    table[state * 256 + byte] = automaton.follow(state, byte as u8);
+                }
+            }
+            automaton.dense = Some(table);
+        }
+
+        Ok(automaton)
+    }
+}
+
+impl Default for AhoCorasickBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct State {
+    edges: Vec<(u8, usize)>,
+    fail: usize,
+    depth: usize,
+    matches: Vec<usize>,
+}
+
+impl State {
+    fn new(depth: usize) -> Self {
+        Self {
+            edges: Vec::new(),
+            fail: ROOT,
+            depth,
+            matches: Vec::new(),
+        }
+    }
+
+    fn find(&self, byte: u8) -> Option<usize> {
+        self.edges
+            .binary_search_by_key(&byte, |&(b, _)| b)
+            .ok()
+            .map(|i| self.edges[i].1)
+    }
+
+    fn add(&mut self, byte: u8, next: usize) {
+        let i = self.edges.partition_point(|&(b, _)| b < byte);
+        self.edges.insert(i, (byte, next));
+    }
+}
+
+/// The outcome of looking for one leftmost match, see `find_leftmost_at`.
+enum Leftmost {
+    Found(Match),
+    NotFound,
+    // The end of the available input was reached while the answer still
+    // depends on bytes not seen yet. The search has to resume at this offset.
+    Incomplete(usize),
+}
+
+/// A multi-pattern matcher that finds all patterns of a set in a single pass
+/// over the haystack.
+///
+/// The automaton is a trie of the patterns where each state has a failure
+/// link to the state of its longest proper suffix that is also in the trie.
+/// Scanning a haystack of length `n` for overlapping matches takes
+/// `O(n + number of matches)` time regardless of the number of patterns.
+#[derive(Clone, Debug)]
+pub struct AhoCorasick {
+    kind: MatchKind,
+    byte_map: [u8; 256],
+    states: Vec<State>,
+    pattern_lens: Vec<usize>,
+    dense: Option<Vec<usize>>,
+}
+
+impl AhoCorasick {
+    /// Builds a leftmost-first, case sensitive automaton.
+    pub fn new<I, P>(patterns: I) -> Result<Self, BuildError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        AhoCorasickBuilder::new().build(patterns)
+    }
+
+    pub fn match_kind(&self) -> MatchKind {
+        self.kind
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.pattern_lens.len()
+    }
+
+    pub fn is_dense(&self) -> bool {
+        self.dense.is_some()
+    }
+
+    /// Returns the first match according to the configured match kind. For
+    /// overlapping automatons this is the match that ends first.
+    pub fn find<H: AsRef<[u8]>>(&self, haystack: H) -> Option<Match> {
+        self.find_iter(haystack.as_ref()).next()
+    }
+
+    pub fn is_match<H: AsRef<[u8]>>(&self, haystack: H) -> bool {
+        self.find(haystack).is_some()
+    }
+
+    /// Iterates over the matches in `haystack` according to the configured
+    /// match kind.
+    pub fn find_iter<'a, 'h>(&'a self, haystack: &'h [u8]) -> FindIter<'a, 'h> {
+        FindIter {
+            automaton: self,
+            haystack,
+            position: 0,
+            state: ROOT,
+            pending: 0,
+        }
+    }
+
+    /// Creates a searcher that can be fed the haystack in chunks. Matches
+    /// that straddle chunk boundaries are reported with absolute offsets.
+    pub fn stream(&self) -> StreamSearcher<'_> {
+        StreamSearcher {
+            automaton: self,
+            state: ROOT,
+            offset: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    // Transition function of the automaton, following failure links until
+    // some state has an edge for `byte`. The root never fails.
+    fn follow(&self, mut state: usize, byte: u8) -> usize {
+        loop {
+            if let Some(next) = self.states[state].find(byte) {
+                return next;
+            }
+            if state == ROOT {
+                return ROOT;
+            }
+            state = self.states[state].fail;
+        }
+    }
+
+    fn next_state(&self, state: usize, byte: u8) -> usize {
+        let byte = self.byte_map[byte as usize];
+        match &self.dense {
+            Some(table) => table[state * 256 + byte as usize],
+            None => self.follow(state, byte),
+        }
+    }
+
+    fn match_at(&self, pattern: usize, end: usize) -> Match {
+        Match {
+            pattern,
+            start: end - self.pattern_lens[pattern],
+            end,
+        }
+    }
+
+    // Returns true if `candidate` should replace `best` as the leftmost match.
+    fn is_better(&self, candidate: &Match, best: &Match) -> bool {
+        if candidate.start != best.start {
+            return candidate.start < best.start;
+        }
+        match self.kind {
+            MatchKind::LeftmostLongest => candidate.end > best.end,
+            _ => candidate.pattern < best.pattern,
+        }
+    }
+
+    // Finds the leftmost match starting at or after `at`.
+    //
+    // The state reached after reading `haystack[..=i]` is the longest suffix
+    // of the input that is still a prefix of some pattern, i.e. the earliest
+    // starting candidate that can still turn into a match. Therefore, once a
+    // match has been found, scanning only has to continue while that earliest
+    // candidate starts no later than the match found so far.
+    //
+    // When `eof` is false the haystack may continue, so reaching its end with
+    // a live candidate means the answer is not known yet.
+    fn find_leftmost_at(&self, haystack: &[u8], at: usize, eof: bool) -> Leftmost {
+        let mut best: Option<Match> = None;
+        let mut state = ROOT;
+
+        for i in at..haystack.len() {
+            state = self.next_state(state, haystack[i]);
+
+            // The match list is sorted by preference, so only its head can
+            // improve the current best among the matches ending here.
+            if let Some(&pattern) = self.states[state].matches.first() {
+                let candidate = self.match_at(pattern, i + 1);
+                if best.is_none_or(|b| self.is_better(&candidate, &b)) {
+                    best = Some(candidate);
+                }
+            }
+
+            if let Some(b) = best {
+                if i + 1 - self.states[state].depth > b.start {
+                    return Leftmost::Found(b);
+                }
+            }
+        }
+
+        if !eof {
+            return Leftmost::Incomplete(haystack.len() - self.states[state].depth);
+        }
+        match best {
+            Some(b) => Leftmost::Found(b),
+            None => Leftmost::NotFound,
+        }
+    }
+}
+
+/// Iterator over the matches of an `AhoCorasick` automaton in a haystack.
+#[derive(Debug)]
+pub struct FindIter<'a, 'h> {
+    automaton: &'a AhoCorasick,
+    haystack: &'h [u8],
+    position: usize,
+    state: usize,
+    // Index of the next match to report from the current state, only used by
+    // overlapping searches.
+    pending: usize,
+}
+
+impl<'a, 'h> Iterator for FindIter<'a, 'h> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        let ac = self.automaton;
+
+        if ac.kind != MatchKind::Overlapping {
+            if self.position > self.haystack.len() {
+                return None;
+            }
+            return match ac.find_leftmost_at(self.haystack, self.position, true) {
+                Leftmost::Found(m) => {
+                    self.position = m.end;
+                    Some(m)
+                }
+                _ => {
+                    self.position = self.haystack.len() + 1;
+                    None
+                }
+            };
+        }
+
+        loop {
+            let matches = &ac.states[self.state].matches;
+            if self.pending < matches.len() {
+                let m = ac.match_at(matches[self.pending], self.position);
+                self.pending += 1;
+                return Some(m);
+            }
+            if self.position >= self.haystack.len() {
+                return None;
+            }
+            self.state = ac.next_state(self.state, self.haystack[self.position]);
+            self.position += 1;
+            self.pending = 0;
+        }
+    }
+}
+
+/// Incremental searcher created by `AhoCorasick::stream`.
+///
+/// Overlapping searches keep only the automaton state between chunks. The
+/// leftmost semantics may need to look ahead past a candidate match before
+/// deciding on it, so they keep the undecided tail of the input buffered; the
+/// tail is never longer than the longest pattern.
+#[derive(Debug)]
+pub struct StreamSearcher<'a> {
+    automaton: &'a AhoCorasick,
+    state: usize,
+    // Absolute offset of the first byte of `buffer` (leftmost semantics) or
+    // number of bytes consumed so far (overlapping semantics).
+    offset: usize,
+    buffer: Vec<u8>,
+}
+
+impl<'a> StreamSearcher<'a> {
+    /// Feeds the next chunk of the haystack and returns every match that can
+    /// be decided with the input seen so far.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Match> {
+        let ac = self.automaton;
+        let mut found = Vec::new();
+
+        if ac.kind == MatchKind::Overlapping {
+            for &byte in chunk {
+                self.state = ac.next_state(self.state, byte);
+                self.offset += 1;
+                for &pattern in &ac.states[self.state].matches {
+                    found.push(ac.match_at(pattern, self.offset));
+                }
+            }
+            return found;
+        }
+
+        self.buffer.extend_from_slice(chunk);
+        self.drain(false, &mut found);
+        found
+    }
+
+    /// Signals the end of the input and returns the matches that were still
+    /// waiting for more data.
+    pub fn finish(&mut self) -> Vec<Match> {
+        let mut found = Vec::new();
+        if self.automaton.kind != MatchKind::Overlapping {
+            self.drain(true, &mut found);
+            self.offset += self.buffer.len();
+            self.buffer.clear();
+        }
+        found
+    }
+
+    fn drain(&mut self, eof: bool, found: &mut Vec<Match>) {
+        let mut at = 0;
+        loop {
+            match self.automaton.find_leftmost_at(&self.buffer, at, eof) {
+                Leftmost::Found(m) => {
+                    found.push(Match {
+                        pattern: m.pattern,
+                        start: m.start + self.offset,
+                        end: m.end + self.offset,
+                    });
+                    at = m.end;
+                }
+                Leftmost::NotFound => {
+                    at = self.buffer.len();
+                    break;
+                }
+                Leftmost::Incomplete(keep) => {
+                    at = keep;
+                    break;
+                }
+            }
+        }
+
+        // Everything before `at` has been decided and is no longer needed.
+        self.buffer.drain(..at);
+        self.offset += at;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(kind: MatchKind, patterns: &[&str]) -> AhoCorasick {
+        AhoCorasickBuilder::new().match_kind(kind).build(patterns).unwrap()
+    }
+
+    fn spans(ac: &AhoCorasick, haystack: &str) -> Vec<(usize, usize, usize)> {
+        ac.find_iter(haystack.as_bytes())
+            .map(|m| (m.pattern, m.start, m.end))
+            .collect()
+    }
+
+    // Reference implementation: every occurrence of every pattern.
+    fn naive_overlapping(patterns: &[&str], haystack: &str) -> Vec<(usize, usize, usize)> {
+        let mut out = Vec::new();
+        for end in 1..=haystack.len() {
+            for (id, p) in patterns.iter().enumerate() {
+                if p.len() <= end && &haystack[end - p.len()..end] == *p {
+                    out.push((id, end - p.len(), end));
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_overlapping_classic_example() {
+        let patterns = ["he", "she", "his", "hers"];
+        let ac = build(MatchKind::Overlapping, &patterns);
+        let mut res = spans(&ac, "ushers");
+        res.sort();
+        assert_eq!(res, vec![(0, 2, 4), (1, 1, 4), (3, 2, 6)]);
+    }
+
+    #[test]
+    fn test_overlapping_matches_naive() {
+        let patterns = ["a", "ab", "bab", "bc", "bca", "c", "caa"];
+        let haystack = "abccababcabcaabcbcaacab";
+        for dense in [false, true] {
+            let ac = AhoCorasickBuilder::new()
+                .match_kind(MatchKind::Overlapping)
+                .dense(dense)
+                .build(patterns)
+                .unwrap();
+            let mut res = spans(&ac, haystack);
+            res.sort_by_key(|&(id, start, end)| (end, id, start));
+            let mut expected = naive_overlapping(&patterns, haystack);
+            expected.sort_by_key(|&(id, start, end)| (end, id, start));
+            assert_eq!(res, expected);
+        }
+    }
+
+    #[test]
+    fn test_leftmost_first_prefers_earlier_pattern() {
+        let ac = build(MatchKind::LeftmostFirst, &["Samwise", "Sam"]);
+        assert_eq!(spans(&ac, "Samwise"), vec![(0, 0, 7)]);
+
+        let ac = build(MatchKind::LeftmostFirst, &["Sam", "Samwise"]);
+        assert_eq!(spans(&ac, "Samwise"), vec![(0, 0, 3)]);
+    }
+
+    #[test]
+    fn test_leftmost_longest_prefers_longer_match() {
+        let ac = build(MatchKind::LeftmostLongest, &["Sam", "Samwise"]);
+        assert_eq!(spans(&ac, "Samwise"), vec![(1, 0, 7)]);
+    }
+
+    #[test]
+    fn test_leftmost_prefers_earlier_start() {
+        // "bcd" ends first, but "abcde" starts first.
+        let ac = build(MatchKind::LeftmostFirst, &["bcd", "abcde"]);
+        assert_eq!(spans(&ac, "xabcdex"), vec![(1, 1, 6)]);
+
+        // "abcdz" never completes, so the later starting "bcd" wins.
+        let ac = build(MatchKind::LeftmostFirst, &["bcd", "abcdz"]);
+        assert_eq!(spans(&ac, "xabcdex"), vec![(0, 2, 5)]);
+    }
+
+    // Reference implementation of the leftmost semantics: try every start
+    // position and pick the preferred pattern matching there.
+    fn naive_leftmost(kind: MatchKind, patterns: &[&str], haystack: &str) -> Vec<(usize, usize, usize)> {
+        let mut out = Vec::new();
+        let mut start = 0;
+        while start < haystack.len() {
+            let mut best: Option<(usize, usize)> = None;
+            for (id, p) in patterns.iter().enumerate() {
+                if !haystack[start..].starts_with(p) {
+                    continue;
+                }
+                best = match best {
+                    Some((_, len)) if kind == MatchKind::LeftmostLongest && p.len() <= len => best,
+                    Some(_) if kind == MatchKind::LeftmostFirst => best,
+                    _ => Some((id, p.len())),
+                };
+            }
+            match best {
+                Some((id, len)) => {
+                    out.push((id, start, start + len));
+                    start += len;
+                }
+                None => start += 1,
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_leftmost_matches_naive() {
+        let pattern_sets: [&[&str]; 4] = [
+            &["b", "ab"],
+            &["a", "ab", "bab", "bc", "bca", "c", "caa"],
+            &["abcd", "bc", "c", "abc"],
+            &["cab", "abcab", "b", "bcabc"],
+        ];
+        let haystack = "abccababcabcaabcbcaacabcabcabcdab";
+        for patterns in pattern_sets {
+            for kind in [MatchKind::LeftmostFirst, MatchKind::LeftmostLongest] {
+                let ac = build(kind, patterns);
+                assert_eq!(spans(&ac, haystack), naive_leftmost(kind, patterns, haystack));
+            }
+        }
+    }
+
+    #[test]
+    fn test_leftmost_non_overlapping() {
+        let ac = build(MatchKind::LeftmostLongest, &["ab", "ba", "aba"]);
+        assert_eq!(spans(&ac, "ababa"), vec![(2, 0, 3), (1, 3, 5)]);
+    }
+
+    #[test]
+    fn test_ascii_case_insensitive() {
+        let ac = AhoCorasickBuilder::new()
+            .ascii_case_insensitive(true)
+            .build(["error", "WARN"])
+            .unwrap();
+        assert_eq!(spans(&ac, "ERROR: warn Error"), vec![(0, 0, 5), (1, 7, 11), (0, 12, 17)]);
+
+        let dense = AhoCorasickBuilder::new()
+            .ascii_case_insensitive(true)
+            .dense(true)
+            .build(["error", "WARN"])
+            .unwrap();
+        assert_eq!(spans(&dense, "ERROR: warn Error"), spans(&ac, "ERROR: warn Error"));
+    }
+
+    #[test]
+    fn test_dense_matches_sparse() {
+        let patterns = ["foo", "foobar", "bar", "oba", "r"];
+        let haystack = "foobarbazfoobaroofoo";
+        for kind in [MatchKind::Overlapping, MatchKind::LeftmostFirst, MatchKind::LeftmostLongest] {
+            let sparse = build(kind, &patterns);
+            let dense = AhoCorasickBuilder::new()
+                .match_kind(kind)
+                .dense(true)
+                .build(patterns)
+                .unwrap();
+            assert!(dense.is_dense());
+            assert_eq!(spans(&sparse, haystack), spans(&dense, haystack));
+        }
+    }
+
+    #[test]
+    fn test_stream_across_chunks() {
+        let patterns = ["needle", "need", "dle", "hay"];
+        let haystack = "haystack with a needle and a needless neeneedle";
+        for kind in [MatchKind::Overlapping, MatchKind::LeftmostFirst, MatchKind::LeftmostLongest] {
+            let ac = build(kind, &patterns);
+            let expected: Vec<Match> = ac.find_iter(haystack.as_bytes()).collect();
+
+            for chunk_size in 1..8 {
+                let mut stream = ac.stream();
+                let mut res = Vec::new();
+                for chunk in haystack.as_bytes().chunks(chunk_size) {
+                    res.extend(stream.feed(chunk));
+                }
+                res.extend(stream.finish());
+                assert_eq!(res, expected, "{:?} with chunks of {}", kind, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_match() {
+        let ac = AhoCorasick::new(["abc"]).unwrap();
+        assert_eq!(ac.find("ababab"), None);
+        assert!(!ac.is_match(""));
+    }
+
+    #[test]
+    fn test_empty_pattern_is_rejected() {
+        let res = AhoCorasick::new(["a", ""]);
+        assert_eq!(res.unwrap_err(), BuildError::EmptyPattern(1));
+    }
+}
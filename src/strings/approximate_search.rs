@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+const WORD: usize = 64;
+
+/// An approximate occurrence of the pattern: some substring of the text
+/// ending right before `end` is within `distance` edits of the pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApproximateMatch {
+    pub end: usize,
+    pub distance: usize,
+}
+
+/// Returns the Levenshtein distance between `a` and `b` using Myers'
+/// bit-parallel algorithm.
+///
+/// A column of the dynamic programming table is encoded as the bit vectors of
+/// its +1 and -1 vertical deltas, so a whole column is updated with a handful
+/// of word operations. Patterns up to 64 symbols fit in a single word, longer
+/// ones are split in blocks of 64 rows.
+/// Time complexity: O(ceil(m / 64) * n), where `m` is the length of `a`.
+pub fn myers_distance<T: Eq + Hash>(a: &[T], b: &[T]) -> usize {
+    if a.is_empty() {
+        return b.len();
+    }
+
+    let mut score = a.len();
+    if a.len() <= WORD {
+        myers_word(a, b, true, |_, s| score = s);
+    } else {
+        myers_blocks(a, b, true, |_, s| score = s);
+    }
+    return score;
+}
+
+/// Returns every position where an occurrence of `pattern` with at most `k`
+/// insertions, deletions or substitutions ends in `text`.
+///
+/// Unlike `myers_distance`, the first row of the table is all zeros, so an
+/// occurrence may start anywhere in the text (Sellers' formulation). Every end
+/// position is reported with the smallest distance of a substring ending
+/// there.
+/// Time complexity: O(ceil(m / 64) * n).
+pub fn approximate_search<T: Eq + Hash>(pattern: &[T], text: &[T], k: usize) -> Vec<ApproximateMatch> {
+    let mut matches = Vec::new();
+
+    // The empty pattern occurs everywhere without a single edit.
+    if pattern.is_empty() {
+        return (0..=text.len()).map(|end| ApproximateMatch { end, distance: 0 }).collect();
+    }
+
+    // A substring may be empty, so the pattern is within `m` edits everywhere,
+    // including before the first symbol.
+    if pattern.len() <= k {
+        matches.push(ApproximateMatch {
+            end: 0,
+            distance: pattern.len(),
+        });
+    }
+
+    let mut report = |end: usize, distance: usize| {
+        if distance <= k {
+            matches.push(ApproximateMatch { end, distance });
+        }
+    };
+    if pattern.len() <= WORD {
+        myers_word(pattern, text, false, &mut report);
+    } else {
+        myers_blocks(pattern, text, false, &mut report);
+    }
+
+    return matches;
+}
+
+// Builds the match masks: bit `i` of `peq[c][block]` is set iff
+// `pattern[64 * block + i] == c`.
+fn match_masks<T: Eq + Hash>(pattern: &[T]) -> HashMap<&T, Vec<u64>> {
+    let blocks = pattern.len().div_ceil(WORD);
+    let mut peq: HashMap<&T, Vec<u64>> = HashMap::new();
+
+    for (i, symbol) in pattern.iter().enumerate() {
+        peq.entry(symbol).or_insert_with(|| vec![0; blocks])[i / WORD] |= 1 << (i % WORD);
+    }
+    return peq;
+}
+
+// Single word version, for patterns of at most 64 symbols. Calls `report`
+// with the position and the score of the last row after every column.
+//
+// `global` selects the edit distance between the whole sequences (the first
+// row grows by one each column) or the search version (the first row is 0).
+fn myers_word<T: Eq + Hash, F: FnMut(usize, usize)>(pattern: &[T], text: &[T], global: bool, mut report: F) {
+    let m = pattern.len();
+    let peq = match_masks(pattern);
+    let last = 1u64 << (m - 1);
+
+    // vertical positive and negative deltas, initially the first column 0..m
+    let mut pv = !0u64;
+    let mut mv = 0u64;
+    let mut score = m;
+
+    for (j, symbol) in text.iter().enumerate() {
+        let eq = peq.get(symbol).map_or(0, |masks| masks[0]);
+        let xv = eq | mv;
+        let xh = (((eq & pv).wrapping_add(pv)) ^ pv) | eq;
+
+        // horizontal deltas of this column
+        let mut ph = mv | !(xh | pv);
+        let mut mh = pv & xh;
+
+        if ph & last != 0 {
+            score += 1;
+        } else if mh & last != 0 {
+            score -= 1;
+        }
+
+        ph <<= 1;
+        mh <<= 1;
+        if global {
+            ph |= 1;
+        }
+
+        pv = mh | !(xv | ph);
+        mv = ph & xv;
+
+        report(j + 1, score);
+    }
+}
+
+// Blocked version for patterns longer than 64 symbols. The blocks of a column
+// are computed from the top one down, each passing the horizontal delta of its
+// last row to the first row of the next block.
+fn myers_blocks<T: Eq + Hash, F: FnMut(usize, usize)>(pattern: &[T], text: &[T], global: bool, mut report: F) {
+    let m = pattern.len();
+    let blocks = m.div_ceil(WORD);
+    let peq = match_masks(pattern);
+
+    let mut pv = vec![!0u64; blocks];
+    let mut mv = vec![0u64; blocks];
+    let mut score = m;
+
+    for (j, symbol) in text.iter().enumerate() {
+        let masks = peq.get(symbol);
+
+        // horizontal delta entering the first block from row 0
+        let mut h: i32 = if global { 1 } else { 0 };
+
+        for b in 0..blocks {
+            let eq = masks.map_or(0, |masks| masks[b]);
+
+            // the last block only uses the rows that exist in the pattern
+            let last = if b + 1 == blocks {
+                1u64 << ((m - 1) % WORD)
+            } else {
+                1u64 << (WORD - 1)
+            };
+            h = advance_block(&mut pv[b], &mut mv[b], eq, h, last);
+        }
+
+        if h > 0 {
+            score += 1;
+        } else if h < 0 {
+            score -= 1;
+        }
+        report(j + 1, score);
+    }
+}
+
+// Advances one block by one column, given the horizontal delta `h_in` of the
+// row above the block. Returns the horizontal delta of the row `last`.
+fn advance_block(pv: &mut u64, mv: &mut u64, eq: u64, h_in: i32, last: u64) -> i32 {
+    let h_in_negative = (h_in < 0) as u64;
+    let h_in_positive = (h_in > 0) as u64;
+
+    let xv = eq | *mv;
+    // a negative delta coming in acts like a match on the first row
+    let eq = eq | h_in_negative;
+    let xh = (((eq & *pv).wrapping_add(*pv)) ^ *pv) | eq;
+
+    let mut ph = *mv | !(xh | *pv);
+    let mut mh = *pv & xh;
+
+    let h_out = if ph & last != 0 {
+        1
+    } else if mh & last != 0 {
+        -1
+    } else {
+        0
+    };
+
+    ph = (ph << 1) | h_in_positive;
+    mh = (mh << 1) | h_in_negative;
+
+    *pv = mh | !(xv | ph);
+    *mv = ph & xv;
+
+    return h_out;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::levenshtein_distance;
+    use super::*;
+    use crate::test_rng::XorShift;
+
+    // Sellers' dynamic programming: the smallest distance between the pattern
+    // and a substring of the text ending at each position.
+    fn sellers(pattern: &[u8], text: &[u8], k: usize) -> Vec<ApproximateMatch> {
+        let mut column: Vec<usize> = (0..=pattern.len()).collect();
+        let mut matches = Vec::new();
+        if column[pattern.len()] <= k {
+            matches.push(ApproximateMatch {
+                end: 0,
+                distance: column[pattern.len()],
+            });
+        }
+
+        for (j, &c) in text.iter().enumerate() {
+            let mut diagonal = column[0];
+            for i in 1..=pattern.len() {
+                let value = (diagonal + (pattern[i - 1] != c) as usize)
+                    .min(column[i] + 1)
+                    .min(column[i - 1] + 1);
+                diagonal = column[i];
+                column[i] = value;
+            }
+            if column[pattern.len()] <= k {
+                matches.push(ApproximateMatch {
+                    end: j + 1,
+                    distance: column[pattern.len()],
+                });
+            }
+        }
+        matches
+    }
+
+    // Deterministic pseudo-random text over a small alphabet.
+    fn text(seed: u64, len: usize) -> Vec<u8> {
+        let mut rng = XorShift::new(seed);
+        (0..len).map(|_| b"acgt"[rng.below(4) as usize]).collect()
+    }
+
+    #[test]
+    fn test_myers_distance() {
+        assert_eq!(myers_distance(b"kitten", b"sitting"), 3);
+        assert_eq!(myers_distance(b"", b"abc"), 3);
+        assert_eq!(myers_distance(b"abc", b""), 3);
+        assert_eq!(myers_distance(b"abc", b"abc"), 0);
+    }
+
+    #[test]
+    fn test_myers_distance_agrees_with_levenshtein() {
+        for (a_len, b_len) in [(5, 9), (63, 70), (64, 64), (65, 40), (150, 140), (200, 260)] {
+            let a = text(a_len as u64 + 1, a_len);
+            let b = text(b_len as u64 + 7, b_len);
+            assert_eq!(myers_distance(&a, &b), levenshtein_distance(&a, &b), "{} x {}", a_len, b_len);
+        }
+    }
+
+    #[test]
+    fn test_approximate_search() {
+        let matches = approximate_search(b"annual", b"annealing", 2);
+        let ends: Vec<usize> = matches.iter().map(|m| m.end).collect();
+        assert_eq!(ends, vec![5, 6, 7]);
+        assert_eq!(matches[1], ApproximateMatch { end: 6, distance: 1 });
+
+        let matches = approximate_search(b"needle", b"haystack", 0);
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_approximate_search_agrees_with_sellers() {
+        let haystack = text(42, 500);
+        for (pattern_len, k) in [(1, 0), (8, 2), (64, 10), (65, 12), (130, 30)] {
+            let pattern = text(pattern_len as u64, pattern_len);
+            assert_eq!(
+                approximate_search(&pattern, &haystack, k),
+                sellers(&pattern, &haystack, k),
+                "pattern of {} with k = {}",
+                pattern_len,
+                k
+            );
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_lookup_of_product_names() {
+        let name: Vec<char> = "wireless keybaord".chars().collect();
+        let query: Vec<char> = "keyboard".chars().collect();
+        let best = approximate_search(&query, &name, 2).into_iter().min_by_key(|m| m.distance);
+        assert_eq!(best, Some(ApproximateMatch { end: 17, distance: 2 }));
+    }
+}
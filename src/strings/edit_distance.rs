@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Returns the number of positions at which `a` and `b` differ, or `None` if
+/// they don't have the same length.
+/// Time complexity: O(n).
+pub fn hamming_distance<T: PartialEq>(a: &[T], b: &[T]) -> Option<usize> {
+    if a.len() != b.len() {
+        return None;
+    }
+    Some(a.iter().zip(b).filter(|(x, y)| x != y).count())
+}
+
+/// Returns the minimum number of insertions, deletions and substitutions that
+/// turn `a` into `b`.
+/// Time complexity: O(n * m).
+/// Space complexity: O(min(n, m)).
+pub fn levenshtein_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    // keep the shorter sequence along the row to save memory
+    let (a, b) = if a.len() < b.len() { (b, a) } else { (a, b) };
+
+    // `row[j]` holds the distance between the current prefix of `a` and `b[..j]`
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        // `diagonal` is the value of the previous row at `j - 1`
+        let mut diagonal = row[0];
+        row[0] = i;
+
+        for j in 1..=b.len() {
+            let substitution = diagonal + (a[i - 1] != b[j - 1]) as usize;
+            diagonal = row[j];
+            row[j] = substitution.min(row[j] + 1).min(row[j - 1] + 1);
+        }
+    }
+
+    return row[b.len()];
+}
+
+/// Like `levenshtein_distance`, but transposing two adjacent symbols also
+/// costs a single edit. This is the unrestricted distance, so a substring may
+/// be edited again after a transposition (`"ca"` to `"abc"` costs 2).
+/// Time complexity: O(n * m).
+/// Space complexity: O(n * m).
+pub fn damerau_levenshtein_distance<T: Eq + Hash>(a: &[T], b: &[T]) -> usize {
+    let (n, m) = (a.len(), b.len());
+    let infinity = n + m;
+
+    // The table is shifted by one row and column holding `infinity`, so that
+    // transpositions reaching before the start of the strings are ignored.
+    let mut d = vec![vec![0; m + 2]; n + 2];
+    d[0][0] = infinity;
+    for i in 0..=n {
+        d[i + 1][0] = infinity;
+        d[i + 1][1] = i;
+    }
+    for j in 0..=m {
+        d[0][j + 1] = infinity;
+        d[1][j + 1] = j;
+    }
+
+    // last row of `a` where each symbol was seen
+    let mut last_row: HashMap<&T, usize> = HashMap::new();
+
+    for i in 1..=n {
+        // last column of `b` in the current row that matched `a[i - 1]`
+        let mut last_match_col = 0;
+
+        for j in 1..=m {
+            let k = *last_row.get(&b[j - 1]).unwrap_or(&0);
+            let l = last_match_col;
+            let cost = if a[i - 1] == b[j - 1] {
+                last_match_col = j;
+                0
+            } else {
+                1
+            };
+
+            d[i + 1][j + 1] = (d[i][j] + cost)
+                .min(d[i + 1][j] + 1)
+                .min(d[i][j + 1] + 1)
+                // transpose `a[k - 1]` and `a[i - 1]`, deleting what lies
+                // between them in `a` and inserting what lies between the
+                // matching symbols in `b`
+                .min(d[k][l] + (i - k - 1) + 1 + (j - l - 1));
+        }
+
+        last_row.insert(&a[i - 1], i);
+    }
+
+    return d[n + 1][m + 1];
+}
+
+/// Returns the Levenshtein distance between `a` and `b` if it is at most `k`,
+/// and `None` otherwise.
+///
+/// This is Ukkonen's cut-off: a cell on the diagonal `j - i` costs at least
+/// `|j - i|`, so only the band of `2k + 1` diagonals around the main one has
+/// to be computed, and the search stops as soon as a whole row exceeds `k`.
+/// Time complexity: O(k * min(n, m)).
+pub fn bounded_levenshtein_distance<T: PartialEq>(a: &[T], b: &[T], k: usize) -> Option<usize> {
+    let (a, b) = if a.len() < b.len() { (b, a) } else { (a, b) };
+    if a.len() - b.len() > k {
+        return None;
+    }
+
+    // Cells outside the band are treated as `k + 1`, i.e. "too far".
+    let too_far = k + 1;
+    let mut previous = vec![too_far; b.len() + 1];
+    let mut current = vec![too_far; b.len() + 1];
+    for j in 0..=b.len().min(k) {
+        previous[j] = j;
+    }
+
+    for i in 1..=a.len() {
+        let low = i.saturating_sub(k).max(1);
+        let high = (i + k).min(b.len());
+
+        // Only the cells next to the band are read outside of it, so they are
+        // the only ones that need resetting.
+        current[0] = if i <= k { i } else { too_far };
+        if low > 1 {
+            current[low - 1] = too_far;
+        }
+        if high < b.len() {
+            current[high + 1] = too_far;
+        }
+
+        let mut row_min = current[0];
+        for j in low..=high {
+            let substitution = previous[j - 1] + (a[i - 1] != b[j - 1]) as usize;
+            let value = substitution.min(previous[j] + 1).min(current[j - 1] + 1);
+            current[j] = value.min(too_far);
+            row_min = row_min.min(current[j]);
+        }
+
+        // every later row only grows from this one
+        if row_min > k {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[b.len()];
+    if distance <= k {
+        return Some(distance);
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(b"karolin", b"kathrin"), Some(3));
+        assert_eq!(hamming_distance(b"", b""), Some(0));
+        assert_eq!(hamming_distance(b"abc", b"ab"), None);
+    }
+
+    #[test]
+    fn test_levenshtein_distance() {
+        assert_eq!(levenshtein_distance(b"kitten", b"sitting"), 3);
+        assert_eq!(levenshtein_distance(b"sitting", b"kitten"), 3);
+        assert_eq!(levenshtein_distance(b"flaw", b"lawn"), 2);
+        assert_eq!(levenshtein_distance(b"", b"abc"), 3);
+        assert_eq!(levenshtein_distance(b"abc", b"abc"), 0);
+        assert_eq!(levenshtein_distance(&chars("crème"), &chars("creme")), 1);
+    }
+
+    #[test]
+    fn test_damerau_levenshtein_distance() {
+        assert_eq!(damerau_levenshtein_distance(b"ca", b"ac"), 1);
+        assert_eq!(damerau_levenshtein_distance(b"ca", b"abc"), 2);
+        assert_eq!(damerau_levenshtein_distance(b"abcdef", b"badcfe"), 3);
+        assert_eq!(damerau_levenshtein_distance(b"kitten", b"sitting"), 3);
+        assert_eq!(damerau_levenshtein_distance(b"", b"ab"), 2);
+        assert_eq!(damerau_levenshtein_distance(b"ab", b""), 2);
+    }
+
+    #[test]
+    fn test_damerau_is_never_more_than_levenshtein() {
+        let words = ["", "a", "ab", "ba", "abc", "acb", "bca", "cab", "abcd", "badc", "dcba"];
+        for a in words {
+            for b in words {
+                let (a, b) = (a.as_bytes(), b.as_bytes());
+                assert!(damerau_levenshtein_distance(a, b) <= levenshtein_distance(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounded_levenshtein_distance() {
+        assert_eq!(bounded_levenshtein_distance(b"kitten", b"sitting", 3), Some(3));
+        assert_eq!(bounded_levenshtein_distance(b"kitten", b"sitting", 2), None);
+        assert_eq!(bounded_levenshtein_distance(b"abc", b"abcdef", 2), None);
+        assert_eq!(bounded_levenshtein_distance(b"", b"", 0), Some(0));
+    }
+
+    #[test]
+    fn test_bounded_agrees_with_levenshtein() {
+        let words = ["", "a", "abc", "abd", "xabc", "abcabc", "cba", "bcab", "aaaa", "abab"];
+        for a in words {
+            for b in words {
+                let (a, b) = (a.as_bytes(), b.as_bytes());
+                let distance = levenshtein_distance(a, b);
+                for k in 0..7 {
+                    let expected = if distance <= k { Some(distance) } else { None };
+                    assert_eq!(bounded_levenshtein_distance(a, b, k), expected);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,21 @@
+mod aho_corasick;
+mod approximate_search;
+mod edit_distance;
+pub mod regex;
+
+pub use self::aho_corasick::{
+    AhoCorasick,
+    AhoCorasickBuilder,
+    BuildError,
+    FindIter,
+    Match,
+    MatchKind,
+    StreamSearcher
+};
+pub use self::approximate_search::{approximate_search, myers_distance, ApproximateMatch};
+pub use self::edit_distance::{
+    bounded_levenshtein_distance,
+    damerau_levenshtein_distance,
+    hamming_distance,
+    levenshtein_distance
+};
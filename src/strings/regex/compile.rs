@@ -0,0 +1,246 @@
+use super::parse::Ast;
+use super::{Error, ErrorKind};
+
+// Upper bound on the number of instructions of a compiled program. Bounded
+// repetitions copy their operand, so nesting them can blow up quickly.
+const MAX_INSTS: usize = 100_000;
+
+/// An instruction of a Thompson NFA program.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Inst {
+    // Consumes one char inside one of the sorted, disjoint ranges.
+    Class(Vec<(char, char)>),
+    // Forks the execution. The first branch has the higher priority.
+    Split(usize, usize),
+    Jmp(usize),
+    // Records the current position in the given capture slot.
+    Save(usize),
+    Assert(Look),
+    Match,
+}
+
+/// Zero-width assertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Look {
+    StartText,
+    EndText,
+}
+
+/// A compiled pattern. Execution starts at instruction 0, and capture group
+/// `i` is recorded in the slots `2 * i` and `2 * i + 1`.
+#[derive(Clone, Debug)]
+pub(crate) struct Program {
+    pub(crate) insts: Vec<Inst>,
+    pub(crate) slots: usize,
+}
+
+/// Compiles the syntax tree into an NFA wrapped in the implicit group 0.
+pub(crate) fn compile(ast: &Ast, groups: usize) -> Result<Program, Error> {
+    let mut compiler = Compiler { insts: Vec::new() };
+
+    compiler.push(Inst::Save(0))?;
+    compiler.compile(ast)?;
+    compiler.push(Inst::Save(1))?;
+    compiler.push(Inst::Match)?;
+
+    Ok(Program {
+        insts: compiler.insts,
+        slots: 2 * groups,
+    })
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Result<usize, Error> {
+        if self.insts.len() >= MAX_INSTS {
+            return Err(Error::new(ErrorKind::TooBig, 0));
+        }
+        self.insts.push(inst);
+        Ok(self.insts.len() - 1)
+    }
+
+    // Points the branch of a `Split` or the target of a `Jmp` emitted earlier
+    // with a placeholder to `target`.
+    fn patch(&mut self, pc: usize, second: bool, target: usize) {
+        match &mut self.insts[pc] {
+            Inst::Split(first_branch, second_branch) => {
+                if second {
+                    *second_branch = target;
+                } else {
+                    *first_branch = target;
+                }
+            }
+            Inst::Jmp(to) => *to = target,
+            _ => unreachable!("only splits and jumps can be patched"),
+        }
+    }
+
+    fn compile(&mut self, ast: &Ast) -> Result<(), Error> {
+        match ast {
+            Ast::Empty => {}
+            Ast::Class(ranges) => {
+                self.push(Inst::Class(ranges.clone()))?;
+            }
+            Ast::StartText => {
+                self.push(Inst::Assert(Look::StartText))?;
+            }
+            Ast::EndText => {
+                self.push(Inst::Assert(Look::EndText))?;
+            }
+            Ast::Group(inner, None) => self.compile(inner)?,
+            Ast::Group(inner, Some(index)) => {
+                self.push(Inst::Save(2 * index))?;
+                self.compile(inner)?;
+                self.push(Inst::Save(2 * index + 1))?;
+            }
+            Ast::Concat(items) => {
+                for item in items {
+                    self.compile(item)?;
+                }
+            }
+            Ast::Alternate(branches) => self.compile_alternate(branches)?,
+            Ast::Repeat { ast, min, max, greedy } => self.compile_repeat(ast, *min, *max, *greedy)?,
+        }
+        Ok(())
+    }
+
+    //       split L1, L2
+    // L1:   <first branch>
+    //       jmp end
+    // L2:   split L3, L4
+    // ...
+    // Ln:   <last branch>
+    // end:
+    fn compile_alternate(&mut self, branches: &[Ast]) -> Result<(), Error> {
+        let mut jumps = Vec::new();
+
+        for (i, branch) in branches.iter().enumerate() {
+            if i + 1 == branches.len() {
+                self.compile(branch)?;
+                break;
+            }
+
+            let split = self.push(Inst::Split(0, 0))?;
+            self.patch(split, false, split + 1);
+            self.compile(branch)?;
+            jumps.push(self.push(Inst::Jmp(0))?);
+            let next = self.insts.len();
+            self.patch(split, true, next);
+        }
+
+        let end = self.insts.len();
+        for jump in jumps {
+            self.patch(jump, false, end);
+        }
+        Ok(())
+    }
+
+    // Expands `e{min,max}` into `min` mandatory copies of `e` followed by
+    // either a loop (unbounded) or `max - min` nested optional copies.
+    fn compile_repeat(&mut self, ast: &Ast, min: u32, max: Option<u32>, greedy: bool) -> Result<(), Error> {
+        for _ in 0..min {
+            self.compile(ast)?;
+        }
+
+        // A greedy split prefers to run the operand again, a lazy one prefers
+        // to skip it.
+        let (body, skip) = if greedy { (false, true) } else { (true, false) };
+
+        match max {
+            None => {
+                // loop: split body, end
+                // body: <e>
+                //       jmp loop
+                // end:
+                let split = self.push(Inst::Split(0, 0))?;
+                self.patch(split, body, split + 1);
+                self.compile(ast)?;
+                self.push(Inst::Jmp(split))?;
+                let end = self.insts.len();
+                self.patch(split, skip, end);
+            }
+            Some(max) => {
+                // Each optional copy is only tried if the previous one
+                // matched, so all of them skip straight to the end.
+                let mut splits = Vec::new();
+                for _ in min..max {
+                    let split = self.push(Inst::Split(0, 0))?;
+                    self.patch(split, body, split + 1);
+                    self.compile(ast)?;
+                    splits.push(split);
+                }
+                let end = self.insts.len();
+                for split in splits {
+                    self.patch(split, skip, end);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse::parse;
+    use super::*;
+
+    fn program(pattern: &str) -> Program {
+        let (ast, groups) = parse(pattern).unwrap();
+        compile(&ast, groups).unwrap()
+    }
+
+    #[test]
+    fn test_compile_star() {
+        let a = Inst::Class(vec![('a', 'a')]);
+        assert_eq!(
+            program("a*").insts,
+            vec![Inst::Save(0), Inst::Split(2, 4), a, Inst::Jmp(1), Inst::Save(1), Inst::Match]
+        );
+    }
+
+    #[test]
+    fn test_compile_lazy_optional() {
+        let a = Inst::Class(vec![('a', 'a')]);
+        assert_eq!(
+            program("a??").insts,
+            vec![Inst::Save(0), Inst::Split(3, 2), a, Inst::Save(1), Inst::Match]
+        );
+    }
+
+    #[test]
+    fn test_compile_alternate() {
+        let class = |c| Inst::Class(vec![(c, c)]);
+        assert_eq!(
+            program("a|b|c").insts,
+            vec![
+                Inst::Save(0),
+                Inst::Split(2, 4),
+                class('a'),
+                Inst::Jmp(8),
+                Inst::Split(5, 7),
+                class('b'),
+                Inst::Jmp(8),
+                class('c'),
+                Inst::Save(1),
+                Inst::Match,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_captures() {
+        let p = program("(a)(b)");
+        assert_eq!(p.slots, 6);
+        assert_eq!(p.insts[1], Inst::Save(2));
+        assert_eq!(p.insts[3], Inst::Save(3));
+    }
+
+    #[test]
+    fn test_compile_too_big() {
+        let (ast, groups) = parse("(((a{100}){100}){100})").unwrap();
+        assert_eq!(compile(&ast, groups).unwrap_err().kind(), &ErrorKind::TooBig);
+    }
+}
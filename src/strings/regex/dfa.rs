@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::compile::{Inst, Look, Program};
+use super::pikevm::contains;
+
+// Once the cache holds this many states it is flushed and rebuilt on demand.
+// Each char still costs at most one closure computation, so a flush only
+// costs time, never correctness or the linear time bound.
+const MAX_STATES: usize = 4096;
+
+/// A DFA state: the set of NFA instructions that are alive, and its
+/// transitions, one per char class, filled in lazily.
+struct State {
+    insts: Vec<usize>,
+    is_match: bool,
+    next: Vec<Option<usize>>,
+}
+
+#[derive(Default)]
+struct Cache {
+    states: Vec<State>,
+    map: HashMap<Vec<usize>, usize>,
+}
+
+/// A DFA built on the fly from the NFA by the subset construction.
+///
+/// Only the states that the haystacks actually reach are ever built, which
+/// avoids the exponential blowup of a full subset construction while keeping
+/// the cost per char down to a table lookup once a state is known.
+pub(crate) struct LazyDfa {
+    // Chars are grouped in classes that no instruction can tell apart. The
+    // class of a char is the number of boundaries less or equal to it.
+    boundaries: Vec<char>,
+    cache: Mutex<Cache>,
+}
+
+impl LazyDfa {
+    pub(crate) fn new(prog: &Program) -> Self {
+        let mut boundaries = Vec::new();
+        for inst in &prog.insts {
+            if let Inst::Class(ranges) = inst {
+                for &(low, high) in ranges {
+                    boundaries.push(low);
+                    match high {
+                        '\u{D7FF}' => boundaries.push('\u{E000}'),
+                        char::MAX => {}
+                        high => boundaries.push(char::from_u32(high as u32 + 1).unwrap()),
+                    }
+                }
+            }
+        }
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        Self {
+            boundaries,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    fn class(&self, c: char) -> usize {
+        self.boundaries.partition_point(|&b| b <= c)
+    }
+
+    /// Returns true if the program matches anywhere in `text`.
+    pub(crate) fn is_match(&self, prog: &Program, text: &str) -> bool {
+        let mut cache = self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let classes = self.boundaries.len() + 1;
+
+        let start = closure(prog, &[0], true, false);
+        let mut current = cache.add(start, prog, classes);
+
+        for c in text.chars() {
+            if cache.states[current].is_match {
+                return true;
+            }
+            if cache.states[current].insts.is_empty() {
+                // Dead state, nothing can match anymore.
+                return false;
+            }
+
+            let class = self.class(c);
+            current = match cache.states[current].next[class] {
+                Some(next) => next,
+                None => {
+                    let set = step(prog, &cache.states[current].insts, c);
+                    if cache.states.len() >= MAX_STATES {
+                        cache.states.clear();
+                        cache.map.clear();
+                        cache.add(set, prog, classes)
+                    } else {
+                        let next = cache.add(set, prog, classes);
+                        cache.states[current].next[class] = Some(next);
+                        next
+                    }
+                }
+            };
+        }
+
+        let state = &cache.states[current];
+        if state.is_match {
+            return true;
+        }
+
+        // The `$` assertions left pending in the last state hold now.
+        let pending: Vec<usize> = state
+            .insts
+            .iter()
+            .filter(|&&pc| prog.insts[pc] == Inst::Assert(Look::EndText))
+            .map(|&pc| pc + 1)
+            .collect();
+        closure(prog, &pending, text.is_empty(), true)
+            .iter()
+            .any(|&pc| prog.insts[pc] == Inst::Match)
+    }
+}
+
+impl Cache {
+    fn add(&mut self, insts: Vec<usize>, prog: &Program, classes: usize) -> usize {
+        if let Some(&id) = self.map.get(&insts) {
+            return id;
+        }
+
+        let is_match = insts.iter().any(|&pc| prog.insts[pc] == Inst::Match);
+        self.states.push(State {
+            insts: insts.clone(),
+            is_match,
+            next: vec![None; classes],
+        });
+        self.map.insert(insts, self.states.len() - 1);
+        self.states.len() - 1
+    }
+}
+
+// Moves every instruction of `insts` over `c`, then starts a new attempt at
+// the following position since the search is unanchored.
+fn step(prog: &Program, insts: &[usize], c: char) -> Vec<usize> {
+    let mut targets = Vec::new();
+    for &pc in insts {
+        if let Inst::Class(ranges) = &prog.insts[pc] {
+            if contains(ranges, c) {
+                targets.push(pc + 1);
+            }
+        }
+    }
+    targets.push(0);
+    closure(prog, &targets, false, false)
+}
+
+// Returns the sorted set of instructions reachable from `pcs` without
+// consuming input. Unsatisfied `$` assertions are kept in the set so they can
+// be resolved at the end of the haystack.
+fn closure(prog: &Program, pcs: &[usize], at_start: bool, at_end: bool) -> Vec<usize> {
+    let mut seen = vec![false; prog.insts.len()];
+    let mut set = Vec::new();
+    let mut stack: Vec<usize> = pcs.iter().rev().copied().collect();
+
+    while let Some(pc) = stack.pop() {
+        if seen[pc] {
+            continue;
+        }
+        seen[pc] = true;
+
+        match &prog.insts[pc] {
+            Inst::Jmp(to) => stack.push(*to),
+            Inst::Split(first, second) => {
+                stack.push(*second);
+                stack.push(*first);
+            }
+            Inst::Save(_) => stack.push(pc + 1),
+            Inst::Assert(Look::StartText) => {
+                if at_start {
+                    stack.push(pc + 1);
+                }
+            }
+            Inst::Assert(Look::EndText) => {
+                if at_end {
+                    stack.push(pc + 1);
+                } else {
+                    set.push(pc);
+                }
+            }
+            Inst::Class(_) | Inst::Match => set.push(pc),
+        }
+    }
+
+    set.sort_unstable();
+    set
+}
@@ -0,0 +1,402 @@
+//! A small regular expression engine that runs in linear time.
+//!
+//! Supported syntax:
+//!
+//! - literals, `.` (any char but `\n`) and the escapes `\n \t \r \d \w \s
+//!   \D \W \S` as well as any escaped ASCII punctuation, e.g. `\.`;
+//! - classes such as `[a-z_]`, `[^0-9]` or `[\d.]`;
+//! - concatenation, alternation `|`, capture groups `(...)` and
+//!   non-capturing groups `(?:...)`;
+//! - the repetitions `* + ? {n} {n,} {n,m}`, each with a lazy `?` variant;
+//! - the anchors `^` (start of text) and `$` (end of text).
+//!
+//! The pattern is compiled to a Thompson NFA. Searches that need the match
+//! bounds or the capture groups run a Pike VM, which simulates all NFA threads
+//! in lockstep. `is_match` runs a lazy DFA built from the same NFA. Neither
+//! engine backtracks, so a search costs `O(n * m)` in the worst case for a
+//! haystack of length `n` and a pattern of size `m`.
+
+mod compile;
+mod dfa;
+mod parse;
+mod pikevm;
+
+use std::fmt;
+
+use self::compile::Program;
+use self::dfa::LazyDfa;
+
+/// The reasons a pattern may be rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnclosedGroup,
+    UnopenedGroup,
+    UnclosedClass,
+    InvalidRange,
+    InvalidEscape,
+    TrailingBackslash,
+    NothingToRepeat,
+    InvalidRepetition,
+    RepetitionTooLarge,
+    TooBig,
+}
+
+/// A pattern that failed to compile, along with the char offset in the
+/// pattern where the problem was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    position: usize,
+}
+
+impl Error {
+    pub(crate) fn new(kind: ErrorKind, position: usize) -> Self {
+        Self { kind, position }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self.kind {
+            ErrorKind::UnclosedGroup => "unclosed group",
+            ErrorKind::UnopenedGroup => "unopened group",
+            ErrorKind::UnclosedClass => "unclosed character class",
+            ErrorKind::InvalidRange => "invalid character class range",
+            ErrorKind::InvalidEscape => "invalid escape sequence",
+            ErrorKind::TrailingBackslash => "trailing backslash",
+            ErrorKind::NothingToRepeat => "repetition operator without operand",
+            ErrorKind::InvalidRepetition => "invalid bounded repetition",
+            ErrorKind::RepetitionTooLarge => "bounded repetition is too large",
+            ErrorKind::TooBig => "compiled pattern is too big",
+        };
+        write!(f, "{} at position {}", description, self.position)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A compiled regular expression.
+pub struct Regex {
+    pattern: String,
+    prog: Program,
+    dfa: LazyDfa,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let (ast, groups) = parse::parse(pattern)?;
+        let prog = compile::compile(&ast, groups)?;
+        let dfa = LazyDfa::new(&prog);
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            prog,
+            dfa,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Number of capture groups, including the implicit group 0 that spans
+    /// the whole match.
+    pub fn captures_len(&self) -> usize {
+        self.prog.slots / 2
+    }
+
+    /// Returns true if the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        self.dfa.is_match(&self.prog, text)
+    }
+
+    /// Returns the leftmost-first match in `text`.
+    pub fn find<'h>(&self, text: &'h str) -> Option<Match<'h>> {
+        self.find_at(text, 0)
+    }
+
+    /// Returns the leftmost-first match starting at or after the byte offset
+    /// `start`. Anchors still refer to the bounds of the whole `text`.
+    ///
+    /// Returns `None` if `start` is past the end of `text` or inside a
+    /// character.
+    pub fn find_at<'h>(&self, text: &'h str, start: usize) -> Option<Match<'h>> {
+        if !text.is_char_boundary(start) {
+            return None;
+        }
+        let slots = pikevm::search(&self.prog, text, start)?;
+        Some(Match {
+            text,
+            start: slots[0].unwrap(),
+            end: slots[1].unwrap(),
+        })
+    }
+
+    /// Iterates over the successive non-overlapping matches in `text`.
+    pub fn find_iter<'r, 'h>(&'r self, text: &'h str) -> FindIter<'r, 'h> {
+        FindIter {
+            regex: self,
+            text,
+            position: 0,
+            last_end: None,
+        }
+    }
+
+    /// Returns the capture groups of the leftmost-first match in `text`.
+    pub fn captures<'h>(&self, text: &'h str) -> Option<Captures<'h>> {
+        let slots = pikevm::search(&self.prog, text, 0)?;
+        Some(Captures { text, slots })
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.pattern).finish()
+    }
+}
+
+impl Clone for Regex {
+    fn clone(&self) -> Self {
+        // The DFA cache is not worth copying, it is rebuilt on demand.
+        Self {
+            pattern: self.pattern.clone(),
+            prog: self.prog.clone(),
+            dfa: LazyDfa::new(&self.prog),
+        }
+    }
+}
+
+/// The byte range `start..end` of a match and the text it was found in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match<'h> {
+    text: &'h str,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl<'h> Match<'h> {
+    pub fn as_str(&self) -> &'h str {
+        &self.text[self.start..self.end]
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// The capture groups of a match. Group 0 is the whole match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Captures<'h> {
+    text: &'h str,
+    slots: Vec<Option<usize>>,
+}
+
+impl<'h> Captures<'h> {
+    /// Returns the group `i`, or `None` if it did not take part in the match.
+    pub fn get(&self, i: usize) -> Option<Match<'h>> {
+        let start = (*self.slots.get(2 * i)?)?;
+        let end = (*self.slots.get(2 * i + 1)?)?;
+        Some(Match {
+            text: self.text,
+            start,
+            end,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+/// Iterator over the non-overlapping matches of a `Regex`.
+#[derive(Debug)]
+pub struct FindIter<'r, 'h> {
+    regex: &'r Regex,
+    text: &'h str,
+    position: usize,
+    last_end: Option<usize>,
+}
+
+impl<'r, 'h> Iterator for FindIter<'r, 'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        loop {
+            if self.position > self.text.len() {
+                return None;
+            }
+
+            let m = self.regex.find_at(self.text, self.position)?;
+            if m.start == m.end {
+                // Step over the next char so an empty match can't repeat.
+                let step = self.text[m.end..].chars().next().map_or(1, |c| c.len_utf8());
+                self.position = m.end + step;
+
+                // An empty match right after the previous match is skipped.
+                if Some(m.end) == self.last_end {
+                    continue;
+                }
+            } else {
+                self.position = m.end;
+            }
+
+            self.last_end = Some(m.end);
+            return Some(m);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+        Regex::new(pattern).unwrap().find(text).map(|m| (m.start, m.end))
+    }
+
+    #[test]
+    fn test_literals_and_classes() {
+        assert_eq!(find("abc", "xxabcxx"), Some((2, 5)));
+        assert_eq!(find("a.c", "a\nc abc"), Some((4, 7)));
+        assert_eq!(find("[0-9]+", "abc 123 456"), Some((4, 7)));
+        assert_eq!(find("\\d+\\.\\d+", "v1.25"), Some((1, 5)));
+        assert_eq!(find("[^a-z ]+", "abc DEF ghi"), Some((4, 7)));
+        assert_eq!(find("\\w+", "  héllo"), Some((2, 3)));
+        assert_eq!(find("h.llo", "  héllo"), Some((2, 8)));
+        assert_eq!(find("xyz", "abc"), None);
+    }
+
+    #[test]
+    fn test_alternation_is_leftmost_first() {
+        assert_eq!(find("samwise|sam", "samwise"), Some((0, 7)));
+        assert_eq!(find("sam|samwise", "samwise"), Some((0, 3)));
+        assert_eq!(find("b|abc", "abc"), Some((0, 3)));
+    }
+
+    #[test]
+    fn test_greedy_and_lazy_repetitions() {
+        assert_eq!(find("a+", "baaab"), Some((1, 4)));
+        assert_eq!(find("a+?", "baaab"), Some((1, 2)));
+        assert_eq!(find("<.*>", "<a><b>"), Some((0, 6)));
+        assert_eq!(find("<.*?>", "<a><b>"), Some((0, 3)));
+        assert_eq!(find("ab?c", "ac"), Some((0, 2)));
+        assert_eq!(find("a*", "bbb"), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_bounded_repetitions() {
+        assert_eq!(find("a{3}", "aaaaa"), Some((0, 3)));
+        assert_eq!(find("a{2,3}", "aaaaa"), Some((0, 3)));
+        assert_eq!(find("a{2,3}?", "aaaaa"), Some((0, 2)));
+        assert_eq!(find("a{2,}", "aaaaa"), Some((0, 5)));
+        assert_eq!(find("x{2}", "xaxx"), Some((2, 4)));
+        assert_eq!(find("(ab){2}", "abab"), Some((0, 4)));
+    }
+
+    #[test]
+    fn test_anchors() {
+        assert_eq!(find("^abc", "abcabc"), Some((0, 3)));
+        assert_eq!(find("^bc", "abc"), None);
+        assert_eq!(find("abc$", "abcabc"), Some((3, 6)));
+        assert_eq!(find("^$", ""), Some((0, 0)));
+        assert_eq!(find("^$", "a"), None);
+        assert_eq!(find("a|^b", "cb"), None);
+    }
+
+    #[test]
+    fn test_captures() {
+        let re = Regex::new("(\\d{4})-(\\d{2})-(\\d{2})").unwrap();
+        let caps = re.captures("date: 2023-07-14!").unwrap();
+        assert_eq!(re.captures_len(), 4);
+        assert_eq!(caps.len(), 4);
+        assert_eq!(caps.get(0).unwrap().as_str(), "2023-07-14");
+        assert_eq!(caps.get(1).unwrap().as_str(), "2023");
+        assert_eq!(caps.get(2).unwrap().as_str(), "07");
+        assert_eq!(caps.get(3).unwrap().as_str(), "14");
+        assert_eq!(caps.get(4), None);
+    }
+
+    #[test]
+    fn test_captures_of_unmatched_and_repeated_groups() {
+        let re = Regex::new("(a)|(b)").unwrap();
+        let caps = re.captures("b").unwrap();
+        assert_eq!(caps.get(1), None);
+        assert_eq!(caps.get(2).unwrap().range(), 0..1);
+
+        // The last iteration of a repeated group wins.
+        let re = Regex::new("(?:(\\w)\\s?)+").unwrap();
+        let caps = re.captures("a b c").unwrap();
+        assert_eq!(caps.get(1).unwrap().as_str(), "c");
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let re = Regex::new("\\d+").unwrap();
+        let all: Vec<&str> = re.find_iter("a1b22c333").map(|m| m.as_str()).collect();
+        assert_eq!(all, vec!["1", "22", "333"]);
+
+        // Empty matches are reported once per position, but never right after
+        // a non-empty match.
+        let re = Regex::new("a*").unwrap();
+        let all: Vec<(usize, usize)> = re.find_iter("baab").map(|m| (m.start, m.end)).collect();
+        assert_eq!(all, vec![(0, 0), (1, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn test_find_at() {
+        let re = Regex::new("a").unwrap();
+        assert_eq!(re.find_at("aba", 1).map(|m| m.start), Some(2));
+        assert_eq!(re.find_at("aba", 3), None);
+        assert_eq!(re.find_at("aba", 4), None);
+        assert_eq!(re.find_at("é", 1), None);
+        assert_eq!(re.find_at("éa", 2).map(|m| m.start), Some(2));
+        assert!(Regex::new("$").unwrap().find_at("ab", 2).is_some());
+    }
+
+    #[test]
+    fn test_dfa_agrees_with_pikevm() {
+        let patterns = [
+            "a|b", "ab*c", "^a", "a$", "^$", "(a|b)*abb", "x?y?z?", "[^ab]+c", "(?:ab|a)(?:bc|c)$", "\\s\\w{2,3}",
+            "é+$",
+        ];
+        let texts = ["", "a", "b", "ab", "abbc", "ac", "babb", "xyz", "ddc", "abc", "a bc", " xyz", "ééé", "aé"];
+        for pattern in patterns {
+            let re = Regex::new(pattern).unwrap();
+            for text in texts {
+                assert_eq!(re.is_match(text), re.find(text).is_some(), "{} on {:?}", pattern, text);
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_catastrophic_backtracking() {
+        // These take exponential time with a backtracking engine.
+        let text = "a".repeat(5000);
+        let re = Regex::new("(a*)*b").unwrap();
+        assert!(!re.is_match(&text));
+        assert!(re.find(&text).is_none());
+
+        let re = Regex::new("(a|aa)+$").unwrap();
+        assert!(re.is_match(&text));
+        assert_eq!(re.find(&text).unwrap().range(), 0..5000);
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        let err = Regex::new("(ab").unwrap_err();
+        assert_eq!(err.kind(), &ErrorKind::UnclosedGroup);
+        assert_eq!(err.to_string(), "unclosed group at position 0");
+    }
+}
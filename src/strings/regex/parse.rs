@@ -0,0 +1,457 @@
+use super::{Error, ErrorKind};
+
+// Largest counter accepted in a bounded repetition such as `a{2,1000}`.
+const MAX_REPEAT: u32 = 1000;
+
+/// Abstract syntax tree of a pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Ast {
+    Empty,
+    // Matches a single char inside one of the (sorted, disjoint) ranges.
+    Class(Vec<(char, char)>),
+    StartText,
+    EndText,
+    // A parenthesised group and its capture index, `None` for `(?:...)`.
+    Group(Box<Ast>, Option<usize>),
+    Repeat {
+        ast: Box<Ast>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+    Concat(Vec<Ast>),
+    Alternate(Vec<Ast>),
+}
+
+/// Parses `pattern` and returns its syntax tree along with the number of
+/// capture groups, counting the implicit group 0 around the whole pattern.
+pub(crate) fn parse(pattern: &str) -> Result<(Ast, usize), Error> {
+    let mut parser = Parser {
+        chars: pattern.chars().collect(),
+        pos: 0,
+        groups: 1,
+    };
+
+    let ast = parser.parse_alternate()?;
+
+    // The only way `parse_alternate` stops early is an unbalanced `)`.
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error(ErrorKind::UnopenedGroup));
+    }
+
+    Ok((ast, parser.groups))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+
+impl Parser {
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error::new(kind, self.pos)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    // alternate := concat ('|' concat)*
+    fn parse_alternate(&mut self) -> Result<Ast, Error> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+        }
+
+        if branches.len() == 1 {
+            return Ok(branches.pop().unwrap());
+        }
+        Ok(Ast::Alternate(branches))
+    }
+
+    // concat := repeat*
+    fn parse_concat(&mut self) -> Result<Ast, Error> {
+        let mut items = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            items.push(self.parse_quantifiers(atom)?);
+        }
+
+        match items.len() {
+            0 => Ok(Ast::Empty),
+            1 => Ok(items.pop().unwrap()),
+            _ => Ok(Ast::Concat(items)),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Ast, Error> {
+        let start = self.pos;
+        let c = self.bump().unwrap();
+
+        match c {
+            '(' => {
+                let index = if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups - 1)
+                };
+
+                let inner = self.parse_alternate()?;
+                if !self.eat(')') {
+                    return Err(Error::new(ErrorKind::UnclosedGroup, start));
+                }
+                Ok(Ast::Group(Box::new(inner), index))
+            }
+            '[' => self.parse_class(start),
+            '.' => Ok(Ast::Class(negate(&[('\n', '\n')]))),
+            '^' => Ok(Ast::StartText),
+            '$' => Ok(Ast::EndText),
+            '\\' => match self.parse_escape(start)? {
+                Escape::Char(c) => Ok(Ast::Class(vec![(c, c)])),
+                Escape::Class(ranges) => Ok(Ast::Class(ranges)),
+            },
+            '*' | '+' | '?' | '{' => Err(Error::new(ErrorKind::NothingToRepeat, start)),
+            c => Ok(Ast::Class(vec![(c, c)])),
+        }
+    }
+
+    // Applies every quantifier following `ast`, e.g. the `+` and `{2}` in `a+{2}`.
+    fn parse_quantifiers(&mut self, mut ast: Ast) -> Result<Ast, Error> {
+        loop {
+            let start = self.pos;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => {
+                    self.pos += 1;
+                    self.parse_counts(start)?
+                }
+                _ => return Ok(ast),
+            };
+            if self.chars[start] != '{' {
+                self.pos += 1;
+            }
+
+            let greedy = !self.eat('?');
+            ast = Ast::Repeat {
+                ast: Box::new(ast),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    // Parses the `n}`, `n,}` or `n,m}` following a `{`.
+    fn parse_counts(&mut self, start: usize) -> Result<(u32, Option<u32>), Error> {
+        let min = self.parse_number(start)?;
+        let max = if self.eat(',') {
+            if self.peek() == Some('}') {
+                None
+            } else {
+                Some(self.parse_number(start)?)
+            }
+        } else {
+            Some(min)
+        };
+
+        if !self.eat('}') {
+            return Err(Error::new(ErrorKind::InvalidRepetition, start));
+        }
+        if let Some(max) = max {
+            if max < min {
+                return Err(Error::new(ErrorKind::InvalidRepetition, start));
+            }
+        }
+        Ok((min, max))
+    }
+
+    fn parse_number(&mut self, start: usize) -> Result<u32, Error> {
+        let mut value: u32 = 0;
+        let mut digits = 0;
+        while let Some(d) = self.peek().and_then(|c| c.to_digit(10)) {
+            self.pos += 1;
+            digits += 1;
+            value = value.saturating_mul(10).saturating_add(d);
+        }
+
+        if digits == 0 {
+            return Err(Error::new(ErrorKind::InvalidRepetition, start));
+        }
+        if value > MAX_REPEAT {
+            return Err(Error::new(ErrorKind::RepetitionTooLarge, start));
+        }
+        Ok(value)
+    }
+
+    // Parses a bracketed class such as `[a-z_]` or `[^\d]`. The opening `[`
+    // has already been consumed.
+    fn parse_class(&mut self, start: usize) -> Result<Ast, Error> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+
+        // A `]` right after the opening bracket is a literal.
+        let mut first = true;
+        loop {
+            let item_start = self.pos;
+            let c = match self.bump() {
+                None => return Err(Error::new(ErrorKind::UnclosedClass, start)),
+                Some(']') if !first => break,
+                Some(c) => c,
+            };
+            first = false;
+
+            let low = match c {
+                '\\' => match self.parse_escape(item_start)? {
+                    Escape::Char(c) => c,
+                    Escape::Class(class) => {
+                        ranges.extend(class);
+                        continue;
+                    }
+                },
+                c => c,
+            };
+
+            // A `-` that is the last item of the class is a literal.
+            let is_range = self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']');
+            if !is_range {
+                ranges.push((low, low));
+                continue;
+            }
+
+            self.pos += 1;
+            let high_start = self.pos;
+            let high = match self.bump() {
+                Some('\\') => match self.parse_escape(high_start)? {
+                    Escape::Char(c) => c,
+                    Escape::Class(_) => return Err(Error::new(ErrorKind::InvalidRange, item_start)),
+                },
+                Some(c) => c,
+                None => return Err(Error::new(ErrorKind::UnclosedClass, start)),
+            };
+            if high < low {
+                return Err(Error::new(ErrorKind::InvalidRange, item_start));
+            }
+            ranges.push((low, high));
+        }
+
+        let ranges = normalize(ranges);
+        if negated {
+            return Ok(Ast::Class(negate(&ranges)));
+        }
+        Ok(Ast::Class(ranges))
+    }
+
+    // Parses the escape sequence following a `\`.
+    fn parse_escape(&mut self, start: usize) -> Result<Escape, Error> {
+        let c = match self.bump() {
+            Some(c) => c,
+            None => return Err(Error::new(ErrorKind::TrailingBackslash, start)),
+        };
+
+        let escape = match c {
+            'n' => Escape::Char('\n'),
+            't' => Escape::Char('\t'),
+            'r' => Escape::Char('\r'),
+            'd' => Escape::Class(digit()),
+            'w' => Escape::Class(word()),
+            's' => Escape::Class(space()),
+            'D' => Escape::Class(negate(&digit())),
+            'W' => Escape::Class(negate(&word())),
+            'S' => Escape::Class(negate(&space())),
+            c if c.is_ascii_punctuation() => Escape::Char(c),
+            _ => return Err(Error::new(ErrorKind::InvalidEscape, start)),
+        };
+        Ok(escape)
+    }
+}
+
+enum Escape {
+    Char(char),
+    Class(Vec<(char, char)>),
+}
+
+fn digit() -> Vec<(char, char)> {
+    vec![('0', '9')]
+}
+
+fn word() -> Vec<(char, char)> {
+    vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')]
+}
+
+fn space() -> Vec<(char, char)> {
+    vec![('\t', '\r'), (' ', ' ')]
+}
+
+// Sorts the ranges and merges the ones that overlap or touch.
+fn normalize(mut ranges: Vec<(char, char)>) -> Vec<(char, char)> {
+    ranges.sort();
+    let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+
+    for (low, high) in ranges {
+        if let Some(last) = merged.last_mut() {
+            if next_char(last.1).is_none_or(|next| low <= next) {
+                last.1 = last.1.max(high);
+                continue;
+            }
+        }
+        merged.push((low, high));
+    }
+    merged
+}
+
+// Returns the ranges of every char not covered by the normalized `ranges`.
+fn negate(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut negated = Vec::new();
+    let mut next = Some('\0');
+
+    for &(low, high) in ranges {
+        if let Some(start) = next {
+            if start < low {
+                negated.push((start, prev_char(low).unwrap()));
+            }
+        }
+        next = next_char(high);
+    }
+    if let Some(start) = next {
+        negated.push((start, char::MAX));
+    }
+    negated
+}
+
+// The successor and predecessor of a char, skipping the surrogate gap.
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        char::MAX => None,
+        c => char::from_u32(c as u32 + 1),
+    }
+}
+
+fn prev_char(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        '\0' => None,
+        c => char::from_u32(c as u32 - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(c: char) -> Ast {
+        Ast::Class(vec![(c, c)])
+    }
+
+    #[test]
+    fn test_parse_concat_and_alternate() {
+        let (ast, groups) = parse("ab|c").unwrap();
+        assert_eq!(groups, 1);
+        assert_eq!(ast, Ast::Alternate(vec![Ast::Concat(vec![lit('a'), lit('b')]), lit('c')]));
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        let (ast, groups) = parse("(a)(?:b)(c)").unwrap();
+        assert_eq!(groups, 3);
+        assert_eq!(
+            ast,
+            Ast::Concat(vec![
+                Ast::Group(Box::new(lit('a')), Some(1)),
+                Ast::Group(Box::new(lit('b')), None),
+                Ast::Group(Box::new(lit('c')), Some(2)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_repetitions() {
+        let (ast, _) = parse("a{2,5}?").unwrap();
+        assert_eq!(
+            ast,
+            Ast::Repeat {
+                ast: Box::new(lit('a')),
+                min: 2,
+                max: Some(5),
+                greedy: false
+            }
+        );
+
+        let (ast, _) = parse("a{3,}").unwrap();
+        assert_eq!(
+            ast,
+            Ast::Repeat {
+                ast: Box::new(lit('a')),
+                min: 3,
+                max: None,
+                greedy: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_classes() {
+        let (ast, _) = parse("[a-cx0-2]").unwrap();
+        assert_eq!(ast, Ast::Class(vec![('0', '2'), ('a', 'c'), ('x', 'x')]));
+
+        let (ast, _) = parse("[]a-]").unwrap();
+        assert_eq!(ast, Ast::Class(vec![('-', '-'), (']', ']'), ('a', 'a')]));
+
+        let (ast, _) = parse("[^b-y]").unwrap();
+        assert_eq!(ast, Ast::Class(vec![('\0', 'a'), ('z', char::MAX)]));
+
+        let (ast, _) = parse("[\\d_]").unwrap();
+        assert_eq!(ast, Ast::Class(vec![('0', '9'), ('_', '_')]));
+    }
+
+    #[test]
+    fn test_negate_skips_surrogates() {
+        let negated = negate(&[('\0', '\u{D7FF}')]);
+        assert_eq!(negated, vec![('\u{E000}', char::MAX)]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("(a", ErrorKind::UnclosedGroup, 0),
+            ("a)", ErrorKind::UnopenedGroup, 1),
+            ("[a", ErrorKind::UnclosedClass, 0),
+            ("[z-a]", ErrorKind::InvalidRange, 1),
+            ("*a", ErrorKind::NothingToRepeat, 0),
+            ("a|+", ErrorKind::NothingToRepeat, 2),
+            ("a{2", ErrorKind::InvalidRepetition, 1),
+            ("a{3,2}", ErrorKind::InvalidRepetition, 1),
+            ("a{1001}", ErrorKind::RepetitionTooLarge, 1),
+            ("\\q", ErrorKind::InvalidEscape, 0),
+            ("a\\", ErrorKind::TrailingBackslash, 1),
+        ];
+        for (pattern, kind, position) in cases {
+            let err = parse(pattern).unwrap_err();
+            assert_eq!((err.kind(), err.position()), (&kind, position), "{}", pattern);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use super::compile::{Inst, Look, Program};
+
+/// A thread of the Pike VM: the instruction it waits on and its captures.
+struct Thread {
+    pc: usize,
+    slots: Vec<Option<usize>>,
+}
+
+/// The ordered set of threads alive at one position of the haystack. The
+/// order of the threads is their priority, and `seen` makes sure every
+/// instruction is added at most once per position.
+struct Threads {
+    list: Vec<Thread>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(size: usize) -> Self {
+        Self {
+            list: Vec::new(),
+            seen: vec![false; size],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.seen.iter_mut().for_each(|s| *s = false);
+    }
+}
+
+/// Runs the program over `text` with leftmost-first semantics, looking for a
+/// match that starts at or after the byte offset `start`. Returns the capture
+/// slots of the match.
+///
+/// Every position of the haystack is visited once and holds at most one
+/// thread per instruction, so a search takes `O(n * m)` time for a haystack
+/// of length `n` and a program of `m` instructions.
+pub(crate) fn search(prog: &Program, text: &str, start: usize) -> Option<Vec<Option<usize>>> {
+    let mut current = Threads::new(prog.insts.len());
+    let mut next = Threads::new(prog.insts.len());
+    let mut matched = None;
+    let mut pos = start;
+
+    loop {
+        // Start a new thread at this position, with the lowest priority,
+        // unless a match starting earlier has already been found.
+        if matched.is_none() {
+            add_thread(prog, text, &mut current, 0, pos, vec![None; prog.slots]);
+        }
+        if current.list.is_empty() {
+            break;
+        }
+
+        let c = text[pos..].chars().next();
+        let next_pos = pos + c.map_or(0, |c| c.len_utf8());
+
+        for thread in current.list.drain(..) {
+            match &prog.insts[thread.pc] {
+                Inst::Match => {
+                    // Threads after this one have a lower priority, so they
+                    // can never beat this match.
+                    matched = Some(thread.slots);
+                    break;
+                }
+                Inst::Class(ranges) => {
+                    if c.is_some_and(|c| contains(ranges, c)) {
+                        add_thread(prog, text, &mut next, thread.pc + 1, next_pos, thread.slots);
+                    }
+                }
+                _ => unreachable!("only consuming instructions are queued"),
+            }
+        }
+
+        if c.is_none() {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        next.clear();
+        pos = next_pos;
+    }
+
+    matched
+}
+
+// Adds the thread at `pc` to `threads`, following every instruction that does
+// not consume input. Branches are explored depth first, highest priority
+// first, so the threads end up in priority order.
+fn add_thread(prog: &Program, text: &str, threads: &mut Threads, pc: usize, pos: usize, slots: Vec<Option<usize>>) {
+    let mut stack = vec![(pc, slots)];
+
+    while let Some((mut pc, mut slots)) = stack.pop() {
+        loop {
+            if threads.seen[pc] {
+                break;
+            }
+            threads.seen[pc] = true;
+
+            match &prog.insts[pc] {
+                Inst::Jmp(to) => pc = *to,
+                Inst::Split(first, second) => {
+                    stack.push((*second, slots.clone()));
+                    pc = *first;
+                }
+                Inst::Save(slot) => {
+                    slots[*slot] = Some(pos);
+                    pc += 1;
+                }
+                Inst::Assert(look) => {
+                    if !is_satisfied(*look, text, pos) {
+                        break;
+                    }
+                    pc += 1;
+                }
+                Inst::Class(_) | Inst::Match => {
+                    threads.list.push(Thread { pc, slots });
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn is_satisfied(look: Look, text: &str, pos: usize) -> bool {
+    match look {
+        Look::StartText => pos == 0,
+        Look::EndText => pos == text.len(),
+    }
+}
+
+pub(crate) fn contains(ranges: &[(char, char)], c: char) -> bool {
+    let i = ranges.partition_point(|&(_, high)| high < c);
+    i < ranges.len() && ranges[i].0 <= c
+}
@@ -0,0 +1,37 @@
+// A deterministic pseudo-random generator for the randomized tests, so that a
+// failing case can be replayed from its seed.
+//
+// It's Marsaglia's xorshift64, which is plenty for generating test inputs. A
+// zero seed is a fixed point and only ever yields zeros.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        XorShift(seed)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // Returns a value in `0..bound`.
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    // Returns a value in `-range..range`.
+    //
+    // Panics if the value doesn't fit in `T`.
+    pub(crate) fn value<T: TryFrom<i64>>(&mut self, range: i64) -> T {
+        let value = self.below(2 * range as u64) as i64 - range;
+        T::try_from(value).unwrap_or_else(|_| panic!("{} doesn't fit in the requested type", value))
+    }
+
+    // Returns `len` values in `-range..range`.
+    pub(crate) fn values<T: TryFrom<i64>>(&mut self, len: usize, range: i64) -> Vec<T> {
+        (0..len).map(|_| self.value(range)).collect()
+    }
+}